@@ -0,0 +1,98 @@
+package app.polarbear
+
+import android.app.Notification
+import android.app.NotificationChannel
+import android.app.NotificationManager
+import android.app.PendingIntent
+import android.app.Service
+import android.content.Intent
+import android.content.pm.ServiceInfo
+import android.os.Build
+import android.os.IBinder
+
+/**
+ * Keeps the Local Desktop process (and thus the PRoot container) alive while the activity is in
+ * the background. It is started and stopped from Rust, see `src/android/utils/foreground_service.rs`.
+ */
+class ContainerService : Service() {
+    companion object {
+        const val ACTION_STOP = "app.polarbear.action.STOP_CONTAINER"
+        private const val CHANNEL_ID = "container"
+        private const val NOTIFICATION_ID = 1
+
+        init {
+            System.loadLibrary("localdesktop")
+        }
+    }
+
+    /** Kills every running container session, implemented in Rust */
+    private external fun nativeStopContainer()
+
+    override fun onBind(intent: Intent?): IBinder? = null
+
+    override fun onStartCommand(intent: Intent?, flags: Int, startId: Int): Int {
+        if (intent?.action == ACTION_STOP) {
+            nativeStopContainer()
+            stopForeground(true)
+            stopSelf()
+            // The activity cannot render anything without the container, so close the whole app
+            android.os.Process.killProcess(android.os.Process.myPid())
+            return START_NOT_STICKY
+        }
+
+        val notification = buildNotification()
+        if (Build.VERSION.SDK_INT >= Build.VERSION_CODES.UPSIDE_DOWN_CAKE) {
+            startForeground(
+                NOTIFICATION_ID,
+                notification,
+                ServiceInfo.FOREGROUND_SERVICE_TYPE_SPECIAL_USE
+            )
+        } else {
+            startForeground(NOTIFICATION_ID, notification)
+        }
+        return START_NOT_STICKY
+    }
+
+    private fun buildNotification(): Notification {
+        val builder = if (Build.VERSION.SDK_INT >= Build.VERSION_CODES.O) {
+            val manager = getSystemService(NotificationManager::class.java)
+            manager.createNotificationChannel(
+                NotificationChannel(
+                    CHANNEL_ID,
+                    "Linux container",
+                    NotificationManager.IMPORTANCE_LOW
+                )
+            )
+            Notification.Builder(this, CHANNEL_ID)
+        } else {
+            @Suppress("DEPRECATION")
+            Notification.Builder(this)
+        }
+
+        val immutable = if (Build.VERSION.SDK_INT >= Build.VERSION_CODES.M) {
+            PendingIntent.FLAG_IMMUTABLE
+        } else {
+            0
+        }
+        val openIntent = packageManager.getLaunchIntentForPackage(packageName)
+        val open = PendingIntent.getActivity(this, 0, openIntent, immutable)
+        val stop = PendingIntent.getService(
+            this,
+            1,
+            Intent(this, ContainerService::class.java).setAction(ACTION_STOP),
+            immutable
+        )
+
+        @Suppress("DEPRECATION")
+        val stopAction = Notification.Action.Builder(0, "Stop container", stop).build()
+
+        return builder
+            .setContentTitle("Local Desktop is running")
+            .setContentText("The Linux container keeps running in the background")
+            .setSmallIcon(applicationInfo.icon)
+            .setOngoing(true)
+            .setContentIntent(open)
+            .addAction(stopAction)
+            .build()
+    }
+}
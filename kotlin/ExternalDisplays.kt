@@ -0,0 +1,101 @@
+package app.polarbear
+
+import android.app.Activity
+import android.app.Presentation
+import android.content.Context
+import android.hardware.display.DisplayManager
+import android.os.Handler
+import android.os.Looper
+import android.view.Display
+import android.view.Surface
+import android.view.SurfaceHolder
+import android.view.SurfaceView
+import android.view.WindowManager
+
+/**
+ * Shows a full screen [Presentation] on every external display (Samsung DeX, USB-C and wireless
+ * monitors) and hands its surface over to the compositor, which extends the desktop onto it.
+ * It is started from Rust, see `src/android/utils/displays.rs`.
+ */
+class ExternalDisplays private constructor(private val activity: Activity) :
+    DisplayManager.DisplayListener {
+    companion object {
+        init {
+            System.loadLibrary("localdesktop")
+        }
+
+        @JvmStatic
+        fun start(activity: Activity) {
+            // Windows can only be shown from the UI thread
+            activity.runOnUiThread {
+                val displays = ExternalDisplays(activity)
+                val manager = displays.displayManager()
+                manager.registerDisplayListener(displays, Handler(Looper.getMainLooper()))
+                manager.getDisplays(DisplayManager.DISPLAY_CATEGORY_PRESENTATION)
+                    .forEach { displays.show(it) }
+            }
+        }
+    }
+
+    private val presentations = HashMap<Int, Presentation>()
+
+    /** The surface of the display was created or resized, implemented in Rust */
+    private external fun nativeSurfaceChanged(displayId: Int, surface: Surface, width: Int, height: Int)
+
+    /** The surface of the display is gone, implemented in Rust */
+    private external fun nativeSurfaceDestroyed(displayId: Int)
+
+    /** The refresh rate, resolution or rotation of the display changed, implemented in Rust */
+    private external fun nativeDisplayChanged(displayId: Int)
+
+    private fun displayManager() =
+        activity.getSystemService(Context.DISPLAY_SERVICE) as DisplayManager
+
+    private fun show(display: Display) {
+        val displayId = display.displayId
+        @Suppress("DEPRECATION")
+        val activityDisplayId = activity.windowManager.defaultDisplay.displayId
+        // The activity itself may already be on the display, e.g. in DeX mode
+        if (presentations.containsKey(displayId) || displayId == activityDisplayId) {
+            return
+        }
+
+        val view = SurfaceView(activity)
+        view.holder.addCallback(object : SurfaceHolder.Callback {
+            override fun surfaceCreated(holder: SurfaceHolder) {}
+
+            override fun surfaceChanged(holder: SurfaceHolder, format: Int, width: Int, height: Int) {
+                nativeSurfaceChanged(displayId, holder.surface, width, height)
+            }
+
+            override fun surfaceDestroyed(holder: SurfaceHolder) {
+                nativeSurfaceDestroyed(displayId)
+            }
+        })
+
+        val presentation = Presentation(activity, display)
+        presentation.setContentView(view)
+        try {
+            presentation.show()
+        } catch (e: WindowManager.InvalidDisplayException) {
+            // The display went away in the meantime
+            return
+        }
+        presentations[displayId] = presentation
+    }
+
+    override fun onDisplayAdded(displayId: Int) {
+        val display = displayManager().getDisplay(displayId) ?: return
+        if (display.flags and Display.FLAG_PRESENTATION != 0) {
+            show(display)
+        }
+    }
+
+    override fun onDisplayRemoved(displayId: Int) {
+        presentations.remove(displayId)?.dismiss()
+    }
+
+    override fun onDisplayChanged(displayId: Int) {
+        nativeDisplayChanged(displayId)
+    }
+}
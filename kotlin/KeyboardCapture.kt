@@ -0,0 +1,42 @@
+package app.polarbear
+
+import android.app.Activity
+import android.content.ComponentName
+
+/**
+ * Captures the Meta key and the combinations Samsung DeX handles itself, like Alt+Tab, while a Linux
+ * app inhibits the keyboard shortcuts, e.g. a remote desktop client or a virtual machine. DeX only
+ * offers this through its own window manager, other devices do without.
+ * It is driven from Rust, see `src/android/utils/keyboard_capture.rs`.
+ */
+class KeyboardCapture private constructor() {
+    companion object {
+        @JvmStatic
+        fun request(activity: Activity) {
+            requestMetaKeyEvent(activity, true)
+        }
+
+        @JvmStatic
+        fun release(activity: Activity) {
+            requestMetaKeyEvent(activity, false)
+        }
+
+        private fun requestMetaKeyEvent(activity: Activity, request: Boolean) {
+            activity.runOnUiThread {
+                try {
+                    val windowManager = Class.forName("com.samsung.android.view.SemWindowManager")
+                    val instance = windowManager.getMethod("getInstance").invoke(null)
+                    windowManager
+                        .getMethod(
+                            "requestMetaKeyEvent",
+                            ComponentName::class.java,
+                            Boolean::class.javaPrimitiveType,
+                        )
+                        .invoke(instance, activity.componentName, request)
+                } catch (e: ReflectiveOperationException) {
+                    // Not a Samsung device
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,28 @@
+package app.polarbear
+
+import android.app.Activity
+import android.os.Build
+
+/**
+ * Captures the mouse while a Linux app locked or confined the pointer, e.g. a game or a remote
+ * desktop client. Android then reports how far the mouse moved instead of moving its own pointer,
+ * and the compositor passes that on through relative-pointer.
+ * It is driven from Rust, see `src/android/utils/pointer_capture.rs`.
+ */
+class PointerCapture private constructor() {
+    companion object {
+        @JvmStatic
+        fun request(activity: Activity) {
+            // Pointer capture came with Android 8
+            if (Build.VERSION.SDK_INT < Build.VERSION_CODES.O) return
+            // Only the UI thread may capture the pointer, and only while the window has the focus
+            activity.runOnUiThread { activity.window.decorView.requestPointerCapture() }
+        }
+
+        @JvmStatic
+        fun release(activity: Activity) {
+            if (Build.VERSION.SDK_INT < Build.VERSION_CODES.O) return
+            activity.runOnUiThread { activity.window.decorView.releasePointerCapture() }
+        }
+    }
+}
@@ -0,0 +1,105 @@
+package app.polarbear
+
+import android.app.Activity
+import android.content.Context
+import android.text.InputType
+import android.view.View
+import android.view.ViewGroup
+import android.view.inputmethod.BaseInputConnection
+import android.view.inputmethod.EditorInfo
+import android.view.inputmethod.InputConnection
+import android.view.inputmethod.InputMethodManager
+
+/**
+ * An invisible text field over the desktop that the Android keyboard types into. What is typed is
+ * handed over to the compositor, which passes it on to the focused Linux app through text-input.
+ * It is driven from Rust, see `src/android/utils/soft_keyboard.rs`.
+ */
+class SoftKeyboard private constructor(private val activity: Activity) : View(activity) {
+    companion object {
+        init {
+            System.loadLibrary("localdesktop")
+        }
+
+        private var instance: SoftKeyboard? = null
+
+        private fun get(activity: Activity): SoftKeyboard =
+            instance?.takeIf { it.activity === activity } ?: SoftKeyboard(activity).also {
+                activity.addContentView(it, ViewGroup.LayoutParams(1, 1))
+                instance = it
+            }
+
+        /** Show the keyboard, `inputType` being one of [InputType] */
+        @JvmStatic
+        fun show(activity: Activity, inputType: Int) {
+            activity.runOnUiThread {
+                val view = get(activity)
+                view.inputType = inputType
+                view.requestFocus()
+                // The keyboard asks for a new connection, with the new input type
+                view.inputMethodManager().restartInput(view)
+                view.inputMethodManager().showSoftInput(view, 0)
+            }
+        }
+
+        @JvmStatic
+        fun hide(activity: Activity) {
+            activity.runOnUiThread {
+                val view = instance ?: return@runOnUiThread
+                view.inputMethodManager().hideSoftInputFromWindow(view.windowToken, 0)
+                view.inputMethodManager().restartInput(view)
+            }
+        }
+    }
+
+    private var inputType = InputType.TYPE_CLASS_TEXT
+
+    init {
+        isFocusable = true
+        isFocusableInTouchMode = true
+    }
+
+    /** Text is being composed, implemented in Rust */
+    private external fun nativePreedit(text: String)
+
+    /** Text was typed, implemented in Rust */
+    private external fun nativeCommit(text: String)
+
+    /** The text being composed is kept as it is, implemented in Rust */
+    private external fun nativeFinishComposing()
+
+    /** Characters around the cursor were deleted, implemented in Rust */
+    private external fun nativeDeleteSurrounding(before: Int, after: Int)
+
+    private fun inputMethodManager() =
+        activity.getSystemService(Context.INPUT_METHOD_SERVICE) as InputMethodManager
+
+    override fun onCheckIsTextEditor() = true
+
+    override fun onCreateInputConnection(outAttrs: EditorInfo): InputConnection {
+        outAttrs.inputType = inputType
+        // The desktop stays visible, instead of a full screen text field in landscape
+        outAttrs.imeOptions = EditorInfo.IME_FLAG_NO_FULLSCREEN or EditorInfo.IME_FLAG_NO_EXTRACT_UI
+        return object : BaseInputConnection(this, false) {
+            override fun setComposingText(text: CharSequence, newCursorPosition: Int): Boolean {
+                nativePreedit(text.toString())
+                return true
+            }
+
+            override fun commitText(text: CharSequence, newCursorPosition: Int): Boolean {
+                nativeCommit(text.toString())
+                return true
+            }
+
+            override fun finishComposingText(): Boolean {
+                nativeFinishComposing()
+                return true
+            }
+
+            override fun deleteSurroundingText(beforeLength: Int, afterLength: Int): Boolean {
+                nativeDeleteSurrounding(beforeLength, afterLength)
+                return true
+            }
+        }
+    }
+}
@@ -0,0 +1,44 @@
+package app.polarbear
+
+import android.app.Activity
+import android.app.Dialog
+import android.webkit.WebView
+import android.webkit.WebViewClient
+import android.widget.Toast
+
+/**
+ * Shows the task manager page served by Rust over the desktop, and the low memory warnings.
+ * It is driven from Rust, see `src/android/proot/task_manager.rs`.
+ */
+class TaskManager private constructor() {
+    companion object {
+        private var dialog: Dialog? = null
+
+        @JvmStatic
+        fun show(activity: Activity, url: String) {
+            // Views and dialogs may only be created on the UI thread
+            activity.runOnUiThread {
+                if (dialog?.isShowing == true) return@runOnUiThread
+                val webView = WebView(activity).apply {
+                    settings.javaScriptEnabled = true
+                    // Keep the page in the overlay instead of opening a browser
+                    webViewClient = WebViewClient()
+                    loadUrl(url)
+                }
+                dialog = Dialog(activity, android.R.style.Theme_DeviceDefault_NoActionBar).apply {
+                    setContentView(webView)
+                    setOnDismissListener {
+                        webView.destroy()
+                        dialog = null
+                    }
+                    show()
+                }
+            }
+        }
+
+        @JvmStatic
+        fun warn(activity: Activity, message: String) {
+            activity.runOnUiThread { Toast.makeText(activity, message, Toast.LENGTH_LONG).show() }
+        }
+    }
+}
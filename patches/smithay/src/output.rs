@@ -344,6 +344,13 @@ impl Output {
         self.inner.0.lock().unwrap().physical.clone()
     }
 
+    /// Changes the physical size of the output, in millimeters, e.g. after the monitor was rotated
+    ///
+    /// It is advertised to clients along with the next change of the transform or location.
+    pub fn set_physical_size(&self, size: Size<i32, Raw>) {
+        self.inner.0.lock().unwrap().physical.size = size;
+    }
+
     /// Returns the currently advertised modes of the output
     pub fn modes(&self) -> Vec<Mode> {
         self.inner.0.lock().unwrap().modes.clone()
@@ -30,6 +30,8 @@ use super::{
 #[derive(Default, Debug)]
 pub(crate) struct InputMethod {
     pub instance: Option<Instance>,
+    /// The compositor handles text inputs itself while no client input method is bound
+    pub compositor_fallback: bool,
     pub popup_handle: PopupHandle,
     pub keyboard_grab: InputMethodKeyboardGrab,
 }
@@ -68,11 +70,26 @@ impl InputMethodHandle {
         }
     }
 
-    /// Whether there's an acitve instance of input-method.
+    /// Whether there's an acitve instance of input-method, or the compositor acts as one.
     pub(crate) fn has_instance(&self) -> bool {
+        let inner = self.inner.lock().unwrap();
+        inner.instance.is_some() || inner.compositor_fallback
+    }
+
+    /// Whether a client is bound as the input method
+    pub fn has_client(&self) -> bool {
         self.inner.lock().unwrap().instance.is_some()
     }
 
+    /// Let the compositor act as the input method while no client is bound as one, e.g. to type with
+    /// the on-screen keyboard of the platform.
+    ///
+    /// Text inputs are then entered and enabled as with a client input method, see
+    /// [`TextInputHandle::active_text_input_info`] to follow them.
+    pub fn set_compositor_fallback(&self, enabled: bool) {
+        self.inner.lock().unwrap().compositor_fallback = enabled;
+    }
+
     /// Callback function to access the input method object
     pub(crate) fn with_instance<F>(&self, f: F)
     where
@@ -76,6 +76,7 @@ impl TextInputHandle {
             instance: instance.clone(),
             serial: 0,
             pending_state: Default::default(),
+            info: Default::default(),
         });
     }
 
@@ -164,6 +165,21 @@ impl TextInputHandle {
         });
     }
 
+    /// What the active text-input of the focused surface told about the text being edited, `None` when
+    /// no text-input is enabled.
+    pub fn active_text_input_info(&self) -> Option<TextInputInfo> {
+        let inner = self.inner.lock().unwrap();
+        let focus = inner.focus.as_ref().filter(|surface| surface.is_alive())?;
+        let active_id = inner.active_text_input_id.as_ref()?;
+        inner
+            .instances
+            .iter()
+            .find(|instance| {
+                &instance.instance.id() == active_id && instance.instance.id().same_client_as(&focus.id())
+            })
+            .map(|instance| instance.info.clone())
+    }
+
     /// Call the callback with the serial of the active text_input or with the passed
     /// `default` one when empty.
     pub(crate) fn active_text_input_serial_or_default<F>(&self, default: u32, mut callback: F)
@@ -261,6 +277,24 @@ where
             zwp_text_input_v3::Request::Commit => {
                 let mut new_state = mem::take(pending_state);
                 let _ = pending_state;
+                if let Some(instance) = guard
+                    .instances
+                    .iter_mut()
+                    .find(|instance| instance.instance == *resource)
+                {
+                    if new_state.enable == Some(true) {
+                        instance.info = TextInputInfo::default();
+                    }
+                    if let Some(content_type) = new_state.content_type {
+                        instance.info.content_type = Some(content_type);
+                    }
+                    if let Some(rect) = new_state.cursor_rectangle {
+                        instance.info.cursor_rectangle = Some(rect);
+                    }
+                    if let Some(surrounding_text) = &new_state.surrounding_text {
+                        instance.info.surrounding_text = Some(surrounding_text.clone());
+                    }
+                }
                 let active_text_input_id = &mut guard.active_text_input_id;
 
                 if active_text_input_id.is_some() && *active_text_input_id != Some(resource.id()) {
@@ -358,6 +392,19 @@ struct Instance {
     instance: ZwpTextInputV3,
     serial: u32,
     pending_state: TextInputState,
+    info: TextInputInfo,
+}
+
+/// Committed state of a text-input, as told by the client
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct TextInputInfo {
+    /// Hint and purpose of the text, e.g. to pick a suitable keyboard layout
+    pub content_type: Option<(ContentHint, ContentPurpose)>,
+    /// Where the cursor is, relative to the focused surface
+    pub cursor_rectangle: Option<Rectangle<i32, Logical>>,
+    /// The text around the cursor, with the byte offsets of the cursor and of the other end of the
+    /// selection in it
+    pub surrounding_text: Option<(String, u32, u32)>,
 }
 
 #[derive(Debug, Default)]
@@ -1,6 +1,7 @@
 use std::{
     io::{Read, Write},
     os::unix::net::UnixStream,
+    path::{Path, PathBuf},
 };
 
 use tracing::{debug, info, warn};
@@ -8,15 +9,19 @@ use tracing::{debug, info, warn};
 use rustix::{io::Errno, net::SocketAddrUnix};
 
 /// Find a free X11 display slot and setup
+///
+/// The lockfiles and sockets are created in `tmp_dir`, which is `/tmp` unless
+/// the X server runs with a different root directory.
 pub(crate) fn prepare_x11_sockets(
+    tmp_dir: &Path,
     display: Option<u32>,
     open_abstract_socket: bool,
 ) -> Result<(X11Lock, Vec<UnixStream>), std::io::Error> {
     match display {
         Some(d) => {
-            if let Ok(lock) = X11Lock::grab(d) {
+            if let Ok(lock) = X11Lock::grab(tmp_dir, d) {
                 // we got a lockfile, try and create the socket
-                match open_x11_sockets_for_display(d, open_abstract_socket) {
+                match open_x11_sockets_for_display(tmp_dir, d, open_abstract_socket) {
                     Ok(sockets) => return Ok((lock, sockets)),
                     Err(err) => return Err(std::io::Error::from(err)),
                 };
@@ -25,9 +30,9 @@ pub(crate) fn prepare_x11_sockets(
         None => {
             for d in 0..33 {
                 // if fails, try the next one
-                if let Ok(lock) = X11Lock::grab(d) {
+                if let Ok(lock) = X11Lock::grab(tmp_dir, d) {
                     // we got a lockfile, try and create the socket
-                    match open_x11_sockets_for_display(d, open_abstract_socket) {
+                    match open_x11_sockets_for_display(tmp_dir, d, open_abstract_socket) {
                         Ok(sockets) => return Ok((lock, sockets)),
                         Err(err) => warn!(display = d, "Failed to create sockets: {}", err),
                     }
@@ -46,14 +51,15 @@ pub(crate) fn prepare_x11_sockets(
 
 #[derive(Debug)]
 pub(crate) struct X11Lock {
+    tmp_dir: PathBuf,
     display: u32,
 }
 
 impl X11Lock {
     /// Try to grab a lockfile for given X display number
-    fn grab(number: u32) -> Result<X11Lock, ()> {
+    fn grab(tmp_dir: &Path, number: u32) -> Result<X11Lock, ()> {
         debug!(display = number, "Attempting to aquire an X11 display lock");
-        let filename = format!("/tmp/.X{}-lock", number);
+        let filename = tmp_dir.join(format!(".X{}-lock", number));
         let lockfile = ::std::fs::OpenOptions::new()
             .write(true)
             .create_new(true)
@@ -73,7 +79,10 @@ impl X11Lock {
                 } else {
                     debug!(display = number, "X11 lock acquired");
                     // we got the lockfile and wrote our pid to it, all is good
-                    Ok(X11Lock { display: number })
+                    Ok(X11Lock {
+                        tmp_dir: tmp_dir.to_owned(),
+                        display: number,
+                    })
                 }
             }
             Err(_) => {
@@ -103,7 +112,7 @@ impl X11Lock {
                             display = number,
                             "Lock was blocked by a defunct X11 server, trying again"
                         );
-                        return X11Lock::grab(number);
+                        return X11Lock::grab(tmp_dir, number);
                     } else {
                         // we could not remove the lockfile, abort
                         return Err(());
@@ -124,10 +133,10 @@ impl Drop for X11Lock {
     fn drop(&mut self) {
         info!("Cleaning up X11 lock.");
         // Cleanup all the X11 files
-        if let Err(e) = ::std::fs::remove_file(format!("/tmp/.X11-unix/X{}", self.display)) {
+        if let Err(e) = ::std::fs::remove_file(self.tmp_dir.join(format!(".X11-unix/X{}", self.display))) {
             warn!(error = ?e, "Failed to remove X11 socket");
         }
-        if let Err(e) = ::std::fs::remove_file(format!("/tmp/.X{}-lock", self.display)) {
+        if let Err(e) = ::std::fs::remove_file(self.tmp_dir.join(format!(".X{}-lock", self.display))) {
             warn!(error = ?e, "Failed to remove X11 lockfile");
         }
     }
@@ -138,16 +147,19 @@ impl Drop for X11Lock {
 /// Should only be done after the associated lockfile is acquired!
 #[cfg(target_os = "linux")]
 fn open_x11_sockets_for_display(
+    tmp_dir: &Path,
     display: u32,
     open_abstract_socket: bool,
 ) -> rustix::io::Result<Vec<UnixStream>> {
-    let path = format!("/tmp/.X11-unix/X{}", display);
+    let path = tmp_dir.join(format!(".X11-unix/X{}", display));
     let _ = ::std::fs::remove_file(&path);
-    // We know this path is not too long, these unwrap cannot fail
-    let fs_addr = SocketAddrUnix::new(path.as_bytes()).unwrap();
+    let fs_addr = SocketAddrUnix::new(path)?;
     let mut sockets = vec![open_socket(fs_addr)?];
     if open_abstract_socket {
-        let abs_addr = SocketAddrUnix::new_abstract_name(path.as_bytes()).unwrap();
+        // Clients look for the abstract socket under the usual name, wherever the filesystem one is
+        let name = format!("/tmp/.X11-unix/X{}", display);
+        // We know this name is not too long, this unwrap cannot fail
+        let abs_addr = SocketAddrUnix::new_abstract_name(name.as_bytes()).unwrap();
         sockets.push(open_socket(abs_addr)?);
     }
     Ok(sockets)
@@ -158,13 +170,13 @@ fn open_x11_sockets_for_display(
 /// Should only be done after the associated lockfile is acquired!
 #[cfg(not(target_os = "linux"))]
 fn open_x11_sockets_for_display(
+    tmp_dir: &Path,
     display: u32,
     _open_abstract_socket: bool,
 ) -> rustix::io::Result<Vec<UnixStream>> {
-    let path = format!("/tmp/.X11-unix/X{}", display);
+    let path = tmp_dir.join(format!(".X11-unix/X{}", display));
     let _ = ::std::fs::remove_file(&path);
-    // We know this path is not too long, these unwrap cannot fail
-    let fs_addr = SocketAddrUnix::new(path.as_bytes()).unwrap();
+    let fs_addr = SocketAddrUnix::new(path)?;
     Ok(vec![open_socket(fs_addr)?])
 }
 
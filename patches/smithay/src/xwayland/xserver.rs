@@ -7,6 +7,7 @@ use std::{
         net::UnixStream,
         process::CommandExt,
     },
+    path::Path,
     process::{Child, Command},
     sync::{Arc, Mutex},
     thread,
@@ -123,22 +124,58 @@ impl XWayland {
         K: AsRef<OsStr>,
         V: AsRef<OsStr>,
         F: FnOnce(&UserDataMap),
+    {
+        let mut command = Command::new("Xwayland");
+        command.stdout(stdout).stderr(stderr);
+
+        // Setup the environment; clear everything except PATH and XDG_RUNTIME_DIR.
+        command.env_clear();
+        for (key, value) in env::vars_os() {
+            if key.to_str() == Some("PATH") || key.to_str() == Some("XDG_RUNTIME_DIR") {
+                command.env(key, value);
+                continue;
+            }
+        }
+        command.envs(envs);
+
+        Self::spawn_command(dh, display, command, "/tmp", open_abstract_socket, user_data)
+    }
+
+    /// Spawns an XWayland server instance with a custom command, e.g. to run
+    /// `Xwayland` through a wrapper or inside a container.
+    ///
+    /// ## Arguments
+    ///
+    /// - `command` - the command starting XWayland. The arguments and the
+    ///   `WAYLAND_SOCKET` variable XWayland needs are appended to it, its
+    ///   environment and output redirections are left untouched.
+    /// - `tmp_dir` - the directory the X11 lockfiles and sockets are created in,
+    ///   which has to be the `/tmp` directory seen by XWayland and its clients.
+    ///
+    /// See [`spawn`](Self::spawn) for the other arguments.
+    pub fn spawn_command<F>(
+        dh: &DisplayHandle,
+        display: impl Into<Option<u32>>,
+        mut command: Command,
+        tmp_dir: impl AsRef<Path>,
+        open_abstract_socket: bool,
+        user_data: F,
+    ) -> std::io::Result<(Self, Client)>
+    where
+        F: FnOnce(&UserDataMap),
     {
         let (x_wm_x11, x_wm_me) = UnixStream::pair()?;
         let (wl_x11, wl_me) = UnixStream::pair()?;
 
-        let (lock, listen_sockets) = prepare_x11_sockets(display.into(), open_abstract_socket)?;
+        let (lock, listen_sockets) =
+            prepare_x11_sockets(tmp_dir.as_ref(), display.into(), open_abstract_socket)?;
         let display_number = lock.display_number();
 
         // XWayland writes the the display number and a newline to this pipe when it's ready.
         let (displayfd_recv, displayfd_send) =
             rustix::pipe::pipe_with(rustix::pipe::PipeFlags::NONBLOCK | rustix::pipe::PipeFlags::CLOEXEC)?;
 
-        let mut command = Command::new("Xwayland");
-
         command
-            .stdout(stdout)
-            .stderr(stderr)
             .arg(format!(":{}", display_number))
             .arg("-verbose")
             .arg("-rootless")
@@ -152,17 +189,7 @@ impl XWayland {
             command.arg("-listenfd").arg(socket.as_raw_fd().to_string());
         }
 
-        // Setup the environment; clear everything except PATH and XDG_RUNTIME_DIR.
-        command.env_clear();
-        for (key, value) in env::vars_os() {
-            if key.to_str() == Some("PATH") || key.to_str() == Some("XDG_RUNTIME_DIR") {
-                command.env(key, value);
-                continue;
-            }
-        }
-
         command.env("WAYLAND_SOCKET", format!("{}", wl_x11.as_raw_fd()));
-        command.envs(envs);
 
         unsafe {
             let wayland_socket_fd = wl_x11.as_raw_fd();
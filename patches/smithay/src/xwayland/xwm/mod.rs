@@ -174,6 +174,8 @@ mod atoms {
             _NET_WM_MOVERESIZE,
             _NET_WM_PID,
             _NET_WM_WINDOW_TYPE,
+            _NET_WM_WINDOW_TYPE_DESKTOP,
+            _NET_WM_WINDOW_TYPE_DOCK,
             _NET_WM_WINDOW_TYPE_DROPDOWN_MENU,
             _NET_WM_WINDOW_TYPE_DIALOG,
             _NET_WM_WINDOW_TYPE_MENU,
@@ -364,6 +364,14 @@ pub enum WindowEvent {
     /// [`transform`]: https://developer.mozilla.org/en-US/docs/Web/CSS/transform
     Touch(Touch),
 
+    /// A stylus moved over the window, touched it or left it.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - **Android:** Reported instead of mouse events for styluses and erasers.
+    /// - **iOS / macOS / Orbital / Wayland / Web / Windows / X11:** Unsupported.
+    Stylus(Stylus),
+
     /// The window's scale factor has changed.
     ///
     /// The following user actions can cause DPI changes:
@@ -856,6 +864,44 @@ pub struct Touch {
     pub id: u64,
 }
 
+/// Represents a stylus event, with everything the stylus tells beside its location.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stylus {
+    pub device_id: DeviceId,
+    pub phase: StylusPhase,
+    pub location: PhysicalPosition<f64>,
+    /// How hard the tip is pressed, from 0.0 to 1.0.
+    pub pressure: f64,
+    /// The angle between the stylus and the perpendicular of the screen in radians, from 0.0 when
+    /// it stands upright to π/2 when it lies flat.
+    pub tilt: f64,
+    /// The direction the stylus points to in radians, from -π to π, 0.0 being towards the top of
+    /// the screen and π/2 towards the right.
+    pub orientation: f64,
+    /// How far the tip is from the screen while hovering, 0.0 when touching it. The unit is up to
+    /// the device.
+    pub distance: f64,
+    /// Whether it is the eraser end of the stylus.
+    pub eraser: bool,
+    /// Whether the button of the stylus closest to its tip is pressed.
+    pub primary_button: bool,
+    /// Whether the second button of the stylus is pressed.
+    pub secondary_button: bool,
+}
+
+/// Describes the phase of a stylus event.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
+pub enum StylusPhase {
+    /// The stylus moved, hovering or touching the screen, or one of its buttons changed.
+    Moved,
+    /// The tip touched the screen.
+    Down,
+    /// The tip no longer touches the screen.
+    Up,
+    /// The stylus went out of range.
+    Left,
+}
+
 /// Describes the force of a touch event
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Force {
@@ -1086,6 +1132,18 @@ mod tests {
                     id: 0,
                     force: Some(event::Force::Normalized(0.0)),
                 }));
+                with_window_event(Stylus(event::Stylus {
+                    device_id: did,
+                    phase: event::StylusPhase::Down,
+                    location: (0.0, 0.0).into(),
+                    pressure: 0.0,
+                    tilt: 0.0,
+                    orientation: 0.0,
+                    distance: 0.0,
+                    eraser: false,
+                    primary_button: false,
+                    secondary_button: false,
+                }));
                 with_window_event(ThemeChanged(crate::window::Theme::Light));
                 with_window_event(Occluded(true));
             }
@@ -385,8 +385,53 @@ impl<T: 'static> EventLoop<T> {
                 // So we also check for `source()`, as it correctly reports `Mouse` (although other devices such as Desktop AVDs report `Unknown``)
                 let source = motion_event.source();
 
-                if tool_type != ToolType::Finger
+                if (tool_type == ToolType::Stylus || tool_type == ToolType::Eraser)
+                    && source != Source::Mouse
+                    && source != Source::Touchpad
+                {
+                    // Styluses keep their pressure, tilt and buttons instead of acting as a mouse
+                    let phase = match action {
+                        MotionAction::Down | MotionAction::PointerDown => {
+                            Some(event::StylusPhase::Down)
+                        },
+                        MotionAction::Up | MotionAction::PointerUp | MotionAction::Cancel => {
+                            Some(event::StylusPhase::Up)
+                        },
+                        MotionAction::HoverExit => Some(event::StylusPhase::Left),
+                        MotionAction::Move
+                        | MotionAction::HoverEnter
+                        | MotionAction::HoverMove
+                        | MotionAction::ButtonPress
+                        | MotionAction::ButtonRelease => Some(event::StylusPhase::Moved),
+                        _ => None,
+                    };
+                    if let Some(phase) = phase {
+                        let button = motion_event.button_state();
+                        callback(
+                            Event::WindowEvent {
+                                window_id: window::WindowId(WindowId),
+                                event: WindowEvent::Stylus(event::Stylus {
+                                    device_id: event::DeviceId(DeviceId(motion_event.device_id())),
+                                    phase,
+                                    location: PhysicalPosition {
+                                        x: pointer.x() as _,
+                                        y: pointer.y() as _,
+                                    },
+                                    pressure: pointer.pressure() as _,
+                                    tilt: pointer.axis_value(input::Axis::Tilt) as _,
+                                    orientation: pointer.axis_value(input::Axis::Orientation) as _,
+                                    distance: pointer.axis_value(input::Axis::Distance) as _,
+                                    eraser: tool_type == ToolType::Eraser,
+                                    primary_button: button.stylus_primary(),
+                                    secondary_button: button.stylus_secondary(),
+                                }),
+                            },
+                            self.window_target(),
+                        );
+                    }
+                } else if tool_type != ToolType::Finger
                     || source == Source::Mouse
+                    || source == Source::MouseRelative
                     || source == Source::Touchpad
                 {
                     let window_id = window::WindowId(WindowId);
@@ -397,6 +442,20 @@ impl<T: 'static> EventLoop<T> {
 
                     // Mouse move (hover or drag)
                     match action {
+                        // While the pointer is captured, the mouse reports how far it moved instead of where it is
+                        MotionAction::HoverMove | MotionAction::Move
+                            if source == Source::MouseRelative =>
+                        {
+                            callback(
+                                Event::DeviceEvent {
+                                    device_id,
+                                    event: event::DeviceEvent::MouseMotion {
+                                        delta: (pointer.x() as f64, pointer.y() as f64),
+                                    },
+                                },
+                                self.window_target(),
+                            );
+                        },
                         MotionAction::HoverMove | MotionAction::Move => {
                             let location =
                                 PhysicalPosition { x: pointer.x() as _, y: pointer.y() as _ };
@@ -424,7 +483,7 @@ impl<T: 'static> EventLoop<T> {
 
                             // Skip `MotionAction::Down` and `MotionAction::Up` when source is mouse as they already reported on `MotionAction::PointerDown` and `MotionAction::PointerUp`
                             // The issue is here: drag gesture start with down and up
-                            if (source == Source::Mouse ||  source == Source::Touchpad) && (action == MotionAction::Down || action == MotionAction::Up) {
+                            if (source == Source::Mouse || source == Source::MouseRelative || source == Source::Touchpad) && (action == MotionAction::Down || action == MotionAction::Up) {
                                 return input_status;
                             }
 
@@ -157,6 +157,11 @@ pub fn build(env: &BuildEnv, libraries: Vec<(Target, PathBuf)>, out: &Path) -> R
     }
 
     std::fs::write(app.join("build.gradle"), app_build_gradle)?;
+    // Classes that are only loaded from native code look unused to R8, keep them
+    std::fs::write(
+        app.join("proguard-rules.pro"),
+        format!("-keep class {}.** {{ *; }}\n", package),
+    )?;
     std::fs::write(
         main.join("AndroidManifest.xml"),
         quick_xml::se::to_string(&manifest)?,
@@ -166,6 +171,7 @@ pub fn build(env: &BuildEnv, libraries: Vec<(Target, PathBuf)>, out: &Path) -> R
         env.cargo().package_root().join("kotlin"),
         env.platform_dir().join("wry"),
     ];
+    let mut has_sources = false;
     for src in srcs {
         if !src.exists() {
             continue;
@@ -173,6 +179,7 @@ pub fn build(env: &BuildEnv, libraries: Vec<(Target, PathBuf)>, out: &Path) -> R
         for entry in std::fs::read_dir(src)? {
             let entry = entry?;
             std::fs::copy(entry.path(), kotlin.join(entry.file_name()))?;
+            has_sources = true;
         }
     }
 
@@ -234,5 +241,21 @@ pub fn build(env: &BuildEnv, libraries: Vec<(Target, PathBuf)>, out: &Path) -> R
             _ => unreachable!(),
         });
     std::fs::copy(output, out)?;
+    if has_sources {
+        ensure_dex(out, format)?;
+    }
+    Ok(())
+}
+
+/// Fail the build if the Kotlin sources did not make it into the package
+fn ensure_dex(path: &Path, format: Format) -> Result<()> {
+    let dex = match format {
+        Format::Aab => "base/dex/classes.dex",
+        _ => "classes.dex",
+    };
+    let mut archive = zip::ZipArchive::new(std::fs::File::open(path)?)?;
+    archive
+        .by_name(dex)
+        .with_context(|| format!("`{}` is missing {}", path.display(), dex))?;
     Ok(())
 }
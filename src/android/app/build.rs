@@ -1,3 +1,4 @@
+use winit::event_loop::EventLoopProxy;
 use winit::platform::android::activity::AndroidApp;
 
 use crate::android::{
@@ -12,6 +13,8 @@ pub struct PolarBearApp {
 
 pub struct PolarBearFrontend {
     pub android_app: AndroidApp,
+    /// Wakes the event loop up when Wayland clients have requests waiting
+    pub event_loop_proxy: EventLoopProxy<()>,
 }
 
 pub enum PolarBearBackend {
@@ -24,10 +27,13 @@ pub enum PolarBearBackend {
 }
 
 impl PolarBearApp {
-    pub fn build(android_app: AndroidApp) -> Self {
+    pub fn build(android_app: AndroidApp, event_loop_proxy: EventLoopProxy<()>) -> Self {
         Self {
             backend: setup(android_app.clone()),
-            frontend: PolarBearFrontend { android_app },
+            frontend: PolarBearFrontend {
+                android_app,
+                event_loop_proxy,
+            },
         }
     }
 }
@@ -1,22 +1,46 @@
 use std::thread;
+use std::time::{Duration, Instant};
 
 use super::build::{PolarBearApp, PolarBearBackend};
 use crate::android::{
     backend::{
-        wayland::{bind, centralize, handle, State},
+        wayland::{
+            bind, centralize, centralize_device_event, handle, ExternalDisplay, State,
+            WaylandBackend,
+        },
         webview::ErrorVariant,
     },
-    proot::launch::launch,
-    utils::{ndk::run_in_jvm, webview::show_webview_popup},
+    proot::{launch::launch, process::ArchProcess, task_manager},
+    utils::{
+        application_context::get_application_context,
+        displays::{
+            activity_display, external_display, on_display_events, start_external_displays,
+            take_display_events, DisplayEvent,
+        },
+        foreground_service::start_foreground_service,
+        keyboard_capture::{release_keyboard_capture, request_keyboard_capture},
+        ndk::run_in_jvm,
+        pointer_capture::{release_pointer_capture, request_pointer_capture},
+        soft_keyboard::{
+            hide_soft_keyboard, input_type, on_soft_keyboard_events, show_soft_keyboard,
+            take_soft_keyboard_events, SoftKeyboardEvent,
+        },
+        webview::show_webview_popup,
+    },
 };
-use crate::core::config;
+use crate::compositor::{resize_output, set_output_modes};
+use crate::core::{config, frame_scheduler::DEFAULT_REFRESH_MHZ, output_layout::output_scale};
 use smithay::output::{Mode, Output, PhysicalProperties, Scale, Subpixel};
 use smithay::utils::Transform;
 use winit::application::ApplicationHandler;
-use winit::event::WindowEvent;
-use winit::event_loop::ActiveEventLoop;
+use winit::event::{DeviceEvent, DeviceId, WindowEvent};
+use winit::event_loop::{ActiveEventLoop, ControlFlow};
+use winit::platform::android::activity::{AndroidApp, WindowManagerFlags};
 use winit::window::WindowId;
 
+/// How often the present time of the frames swapped last is asked for, while the display has not shown them yet
+const PRESENT_POLL_INTERVAL: Duration = Duration::from_millis(4);
+
 impl ApplicationHandler for PolarBearApp {
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
         match self.backend {
@@ -42,18 +66,33 @@ impl ApplicationHandler for PolarBearApp {
             }
             PolarBearBackend::Wayland(ref mut backend) => {
                 // Initialize the Wayland backend
-                let winit = bind(&event_loop);
+                let mut winit = bind(&event_loop);
                 let window_size = winit.window_size();
-                let scale_factor = winit.scale_factor();
                 let size = (window_size.w, window_size.h);
+                for display in backend.external_displays.values_mut() {
+                    display.bind(winit.renderer());
+                }
                 backend.graphic_renderer = Some(winit);
-                backend.compositor.state.size = size.into();
+                backend.damage_tracker = None;
+                backend.compositor.state.frames.schedule();
+
+                if let Some(output) = &backend.compositor.output {
+                    // The container kept running while the activity was in the background,
+                    // so re-attach to the running session instead of launching a new one
+                    // and describe the display again, the activity may be shown on another one now
+                    resize_output(output, size.into());
+                    let android_app = self.frontend.android_app.clone();
+                    update_built_in_output(backend, &android_app);
+                    backend.arrange_outputs();
+                    return;
+                }
 
                 // Create the Output with given name and physical properties.
                 let output = Output::new(
                     "Local Desktop Wayland Compositor".into(), // the name of this output,
                     PhysicalProperties {
-                        size: size.into(),                 // dimensions (width, height) in mm
+                        // dimensions (width, height) in mm, see `update_built_in_output`
+                        size: (0, 0).into(),
                         subpixel: Subpixel::HorizontalRgb, // subpixel information
                         make: "Local Desktop".into(),      // make of the monitor
                         model: config::VERSION.into(),     // model of the monitor
@@ -69,16 +108,104 @@ impl ApplicationHandler for PolarBearApp {
                 output.change_current_state(
                     Some(Mode {
                         size: size.into(),
-                        refresh: 60000,
+                        refresh: DEFAULT_REFRESH_MHZ,
                     }), // the resolution mode,
                     Some(Transform::Normal), // global screen transformation
-                    Some(Scale::Fractional(scale_factor)), // global screen scaling factor
+                    Some(Scale::Fractional(output_scale(
+                        get_application_context().local_config.display.scale,
+                    ))), // global screen scaling factor
                     Some((0, 0).into()),     // output position
                 );
 
                 backend.compositor.output.replace(output);
+                let android_app = self.frontend.android_app.clone();
+                update_built_in_output(backend, &android_app);
+                backend.output_arrangement =
+                    get_application_context().local_config.display.arrangement;
+                backend.arrange_outputs();
+                backend.compositor.state.clipboard =
+                    get_application_context().local_config.clipboard;
+                backend
+                    .compositor
+                    .start_xwayland(ArchProcess::guest_command("/usr/bin/Xwayland"));
+                let proxy = self.frontend.event_loop_proxy.clone();
+                backend.compositor.watch_clients(move || {
+                    let _ = proxy.send_event(());
+                });
+                let proxy = self.frontend.event_loop_proxy.clone();
+                on_display_events(move || {
+                    let _ = proxy.send_event(());
+                });
+                run_in_jvm(start_external_displays, android_app.clone());
+                let proxy = self.frontend.event_loop_proxy.clone();
+                on_soft_keyboard_events(move || {
+                    let _ = proxy.send_event(());
+                });
+
+                if get_application_context().local_config.session.background {
+                    run_in_jvm(start_foreground_service, android_app.clone());
+                }
+                launch(android_app);
+            }
+        }
+    }
+
+    fn suspended(&mut self, _event_loop: &ActiveEventLoop) {
+        if let PolarBearBackend::Wayland(backend) = &mut self.backend {
+            // The native window is gone, drop everything bound to it.
+            // The clients are still served in `about_to_wait` until the window comes back.
+            for display in backend.external_displays.values_mut() {
+                display.unbind();
+            }
+            backend.graphic_renderer = None;
+        }
+    }
+
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        let PolarBearBackend::Wayland(backend) = &mut self.backend else {
+            return;
+        };
+        if backend.compositor.output.is_none() {
+            return;
+        }
+
+        // Woken up by input or by the client watcher. Clients would stall (or get disconnected once
+        // their socket buffer is full) if their requests were not dispatched, even in the background.
+        backend.compositor.dispatch();
+        handle_display_events(backend, &self.frontend.android_app);
+        handle_soft_keyboard(backend, &self.frontend.android_app);
+        handle_pointer_capture(backend, &self.frontend.android_app);
+        handle_keyboard_capture(backend, &self.frontend.android_app);
+        handle_idle_inhibit(backend, &self.frontend.android_app);
+        if std::mem::take(&mut backend.task_manager_requested) {
+            task_manager::show(self.frontend.android_app.clone());
+        }
+
+        let Some(winit) = &mut backend.graphic_renderer else {
+            // Nothing is rendered in the background
+            event_loop.set_control_flow(ControlFlow::Wait);
+            return;
+        };
+        // Frames on their way to the screen are polled until the display reports showing them
+        let state = &backend.compositor.state;
+        let mut presenting = winit.frame_timestamps().poll(state);
+        for display in backend.external_displays.values_mut() {
+            presenting |= display.poll_frame_timestamps(state);
+        }
 
-                launch();
+        let now = Instant::now();
+        match backend.compositor.state.frames.next_frame(now) {
+            Some(next_frame) if next_frame <= now => {
+                winit.window().request_redraw();
+                event_loop.set_control_flow(ControlFlow::Wait);
+            }
+            next_frame => {
+                let next_poll = presenting.then(|| now + PRESENT_POLL_INTERVAL);
+                match next_frame.into_iter().chain(next_poll).min() {
+                    Some(wake_up) => event_loop.set_control_flow(ControlFlow::WaitUntil(wake_up)),
+                    // Idle: sleep until the next input or client request
+                    None => event_loop.set_control_flow(ControlFlow::Wait),
+                }
             }
         }
     }
@@ -92,4 +219,209 @@ impl ApplicationHandler for PolarBearApp {
             handle(event, backend, event_loop);
         }
     }
+
+    fn device_event(
+        &mut self,
+        event_loop: &ActiveEventLoop,
+        _device_id: DeviceId,
+        event: DeviceEvent,
+    ) {
+        if let PolarBearBackend::Wayland(backend) = &mut self.backend {
+            let event = centralize_device_event(event, backend);
+            handle(event, backend, event_loop);
+        }
+    }
+}
+
+/// Create, resize and remove the outputs of the external displays, as told by `ExternalDisplays`
+fn handle_display_events(backend: &mut WaylandBackend, android_app: &AndroidApp) {
+    let events = take_display_events();
+    if events.is_empty() {
+        return;
+    }
+
+    for event in events {
+        match event {
+            DisplayEvent::SurfaceChanged {
+                display_id,
+                window,
+                size,
+            } => {
+                if let Some(display) = backend.external_displays.get_mut(&display_id) {
+                    resize_output(&display.output, size.into());
+                    display.set_window(window, size.into());
+                } else {
+                    let Some(info) = run_in_jvm(
+                        move |env, app| external_display(env, app, display_id),
+                        android_app.clone(),
+                    ) else {
+                        continue;
+                    };
+                    let output = Output::new(
+                        info.name,
+                        PhysicalProperties {
+                            size: info.physical_size.into(),
+                            subpixel: Subpixel::Unknown,
+                            make: "Local Desktop".into(),
+                            model: config::VERSION.into(),
+                        },
+                    );
+                    let global =
+                        output.create_global::<State>(&backend.compositor.display.handle());
+                    let scale = get_application_context()
+                        .local_config
+                        .display
+                        .external_scale;
+                    output.change_current_state(
+                        None,
+                        None,
+                        Some(Scale::Fractional(output_scale(scale))),
+                        None,
+                    );
+                    set_output_modes(&output, size.into(), info.refresh_mhz, &info.refresh_rates);
+                    backend.external_displays.insert(
+                        display_id,
+                        ExternalDisplay::new(output, global, window, size.into()),
+                    );
+                }
+                if let (Some(winit), Some(display)) = (
+                    &mut backend.graphic_renderer,
+                    backend.external_displays.get_mut(&display_id),
+                ) {
+                    display.bind(winit.renderer());
+                }
+            }
+            DisplayEvent::SurfaceDestroyed { display_id } => {
+                if let Some(display) = backend.external_displays.remove(&display_id) {
+                    display.remove(&backend.compositor.display.handle());
+                }
+            }
+            DisplayEvent::DisplayChanged { display_id } => {
+                let Some(display) = backend.external_displays.get(&display_id) else {
+                    update_built_in_output(backend, android_app);
+                    continue;
+                };
+                let info = run_in_jvm(
+                    move |env, app| external_display(env, app, display_id),
+                    android_app.clone(),
+                );
+                if let (Some(info), Some(mode)) = (info, display.output.current_mode()) {
+                    display.output.set_physical_size(info.physical_size.into());
+                    set_output_modes(
+                        &display.output,
+                        mode.size,
+                        info.refresh_mhz,
+                        &info.refresh_rates,
+                    );
+                }
+            }
+        }
+    }
+    backend.arrange_outputs();
+}
+
+/// Type what the soft keyboard says, and show it while a client wants text or hide it otherwise
+fn handle_soft_keyboard(backend: &mut WaylandBackend, android_app: &AndroidApp) {
+    for event in take_soft_keyboard_events() {
+        match event {
+            SoftKeyboardEvent::Preedit(text) => backend.compositor.ime_preedit(text, None),
+            SoftKeyboardEvent::Commit(text) => backend.compositor.ime_commit(text),
+            SoftKeyboardEvent::FinishComposing => backend.compositor.ime_finish_composing(),
+            SoftKeyboardEvent::DeleteSurrounding { before, after } => {
+                backend.compositor.ime_delete_surrounding(before, after)
+            }
+        }
+    }
+
+    let wanted = backend
+        .compositor
+        .active_text_input()
+        .map(|info| input_type(info.content_type));
+    if wanted == backend.soft_keyboard {
+        return;
+    }
+    match wanted {
+        Some(input_type) => run_in_jvm(
+            move |env, app| show_soft_keyboard(env, app, input_type),
+            android_app.clone(),
+        ),
+        None => run_in_jvm(hide_soft_keyboard, android_app.clone()),
+    }
+    backend.soft_keyboard = wanted;
+}
+
+/// Capture the mouse while a client locked or confined the pointer or inhibits the keyboard shortcuts,
+/// and give it back otherwise. A captured mouse also takes the keys Android would handle.
+fn handle_pointer_capture(backend: &mut WaylandBackend, android_app: &AndroidApp) {
+    let wanted =
+        backend.compositor.pointer_constrained() || backend.compositor.shortcuts_inhibited();
+    if wanted == backend.pointer_captured || backend.graphic_renderer.is_none() {
+        return;
+    }
+    if wanted {
+        run_in_jvm(request_pointer_capture, android_app.clone());
+    } else {
+        run_in_jvm(release_pointer_capture, android_app.clone());
+    }
+    backend.pointer_captured = wanted;
+}
+
+/// Capture the Meta key and the system key combinations while the focused client inhibits the keyboard
+/// shortcuts, e.g. a remote desktop client, and give them back to Android otherwise
+fn handle_keyboard_capture(backend: &mut WaylandBackend, android_app: &AndroidApp) {
+    let wanted = backend.compositor.shortcuts_inhibited();
+    if wanted == backend.keyboard_captured || backend.graphic_renderer.is_none() {
+        return;
+    }
+    if wanted {
+        run_in_jvm(request_keyboard_capture, android_app.clone());
+    } else {
+        run_in_jvm(release_keyboard_capture, android_app.clone());
+    }
+    backend.keyboard_captured = wanted;
+}
+
+/// Keep the screen on while a client inhibits idling, e.g. a video player, and let Android turn it
+/// off otherwise. It is kept on from the start until then, see `keep_screen_on`.
+fn handle_idle_inhibit(backend: &mut WaylandBackend, android_app: &AndroidApp) {
+    let wanted = backend.compositor.state.idle_inhibited();
+    if wanted == backend.screen_kept_on {
+        return;
+    }
+    if wanted {
+        android_app.set_window_flags(
+            WindowManagerFlags::KEEP_SCREEN_ON,
+            WindowManagerFlags::empty(),
+        );
+    } else {
+        android_app.set_window_flags(
+            WindowManagerFlags::empty(),
+            WindowManagerFlags::KEEP_SCREEN_ON,
+        );
+    }
+    backend.screen_kept_on = wanted;
+}
+
+/// Describe the display the activity is shown on to clients as Android reports it
+fn update_built_in_output(backend: &mut WaylandBackend, android_app: &AndroidApp) {
+    let Some(mode) = backend
+        .compositor
+        .output
+        .as_ref()
+        .and_then(|output| output.current_mode())
+    else {
+        return;
+    };
+    let Some(info) = run_in_jvm(activity_display, android_app.clone()) else {
+        return;
+    };
+    if let Some(output) = &backend.compositor.output {
+        output.set_physical_size(info.physical_size.into());
+        set_output_modes(output, mode.size, info.refresh_mhz, &info.refresh_rates);
+    }
+    backend
+        .compositor
+        .state
+        .frames
+        .set_refresh(info.refresh_mhz);
 }
@@ -1,15 +1,16 @@
 use crate::android::backend::wayland::{
     input::{
         RelativePosition, WinitInput, WinitKeyboardInputEvent, WinitMouseInputEvent,
-        WinitMouseMovedEvent, WinitMouseWheelEvent, WinitTouchCancelledEvent, WinitTouchEndedEvent,
-        WinitTouchMovedEvent, WinitTouchStartedEvent,
+        WinitMouseMovedEvent, WinitMouseRelativeMotionEvent, WinitMouseWheelEvent,
+        WinitTouchCancelledEvent, WinitTouchEndedEvent, WinitTouchMovedEvent,
+        WinitTouchStartedEvent,
     },
     keymap::physicalkey_to_scancode,
     WaylandBackend,
 };
 use smithay::backend::input::InputEvent;
 use smithay::utils::{Physical, Size};
-use winit::event::{ElementState, Touch, TouchPhase, WindowEvent};
+use winit::event::{DeviceEvent, ElementState, Ime, Stylus, Touch, TouchPhase, WindowEvent};
 
 /// Specific events generated by Winit
 #[derive(Debug)]
@@ -34,6 +35,12 @@ pub enum CentralizedEvent {
     /// A redraw was requested
     Redraw,
 
+    /// Text was composed or typed with an input method
+    Ime(Ime),
+
+    /// A stylus moved, touched the screen or went out of range
+    Stylus(Stylus),
+
     /// TODO: Support these events
     Unsupported,
 }
@@ -69,6 +76,8 @@ pub fn centralize(event: WindowEvent, backend: &mut WaylandBackend) -> Centraliz
         WindowEvent::RedrawRequested => CentralizedEvent::Redraw,
         WindowEvent::CloseRequested => CentralizedEvent::CloseRequested,
         WindowEvent::Focused(focused) => CentralizedEvent::Focus(focused),
+        WindowEvent::Ime(ime) => CentralizedEvent::Ime(ime),
+        WindowEvent::Stylus(stylus) => CentralizedEvent::Stylus(stylus),
         WindowEvent::KeyboardInput {
             event,
             is_synthetic,
@@ -225,3 +234,17 @@ pub fn centralize(event: WindowEvent, backend: &mut WaylandBackend) -> Centraliz
         }
     };
 }
+
+/// Events of input devices rather than of the window, i.e. the motion of a captured mouse
+pub fn centralize_device_event(
+    event: DeviceEvent,
+    backend: &mut WaylandBackend,
+) -> CentralizedEvent {
+    let time = backend.clock.now().as_millis() as u64;
+    match event {
+        DeviceEvent::MouseMotion { delta } => CentralizedEvent::Input(InputEvent::PointerMotion {
+            event: WinitMouseRelativeMotionEvent { time, delta },
+        }),
+        _ => CentralizedEvent::Unsupported,
+    }
+}
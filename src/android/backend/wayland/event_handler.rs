@@ -1,40 +1,19 @@
-use crate::android::backend::wayland::{
-    compositor::{send_frames_surface_tree, ClientState, State},
-    CentralizedEvent, WaylandBackend,
+use crate::android::backend::wayland::{CentralizedEvent, WaylandBackend};
+use crate::compositor::{resize_output, StylusInput, CLEAR_COLOR};
+use crate::core::stylus::{hover_distance, tilt_degrees};
+use smithay::backend::input::{
+    AbsolutePositionEvent, Axis, Event, InputEvent, KeyState, KeyboardKeyEvent, PointerAxisEvent,
+    PointerButtonEvent, PointerMotionEvent, TouchEvent,
 };
-use smithay::backend::renderer::element::surface::{
-    render_elements_from_surface_tree, WaylandSurfaceRenderElement,
-};
-use smithay::backend::renderer::element::Kind;
-use smithay::backend::renderer::gles::GlesRenderer;
-use smithay::backend::renderer::utils::draw_render_elements;
-use smithay::backend::renderer::{Color32F, Frame, Renderer};
-use smithay::input::keyboard::FilterResult;
-use smithay::input::{pointer, touch};
-use smithay::reexports::wayland_server::protocol::wl_pointer::ButtonState;
-use smithay::utils::{Rectangle, Transform, SERIAL_COUNTER};
-use smithay::wayland::shell::xdg::ToplevelSurface;
-use smithay::{
-    backend::input::{
-        AbsolutePositionEvent, Axis, Event, InputEvent, KeyboardKeyEvent, PointerAxisEvent,
-        PointerButtonEvent, TouchEvent,
-    },
-    output::{Mode, Scale},
-};
-use std::sync::Arc;
+use smithay::backend::renderer::{damage::OutputDamageTracker, gles::GlesTexture};
+use smithay::input::{keyboard::Keycode, pointer::AxisFrame};
+use smithay::utils::Transform;
+use std::time::Instant;
+use winit::event::{Ime, StylusPhase};
 use winit::event_loop::ActiveEventLoop;
 
-/**
- * As we currently use Xwayland, there is only 1 surface
- */
-fn get_surface(state: &State) -> Option<ToplevelSurface> {
-    state
-        .xdg_shell_state
-        .toplevel_surfaces()
-        .iter()
-        .next()
-        .cloned()
-}
+/// Xkb keycode of Esc, evdev ones are shifted by 8
+const TASK_MANAGER_KEY: Keycode = Keycode::new(9);
 
 pub fn handle(event: CentralizedEvent, backend: &mut WaylandBackend, event_loop: &ActiveEventLoop) {
     match event {
@@ -42,227 +21,147 @@ pub fn handle(event: CentralizedEvent, backend: &mut WaylandBackend, event_loop:
             event_loop.exit();
         }
         CentralizedEvent::Redraw => {
-            if let Some(winit) = backend.graphic_renderer.as_mut() {
+            let output = backend.compositor.output.clone();
+            if let (Some(winit), Some(output)) = (backend.graphic_renderer.as_mut(), output) {
+                // External displays are presented first, the built-in one only once the clients are dispatched
+                let mut damaged = false;
+                for display in backend.external_displays.values_mut() {
+                    damaged |= display.render(
+                        winit.renderer(),
+                        &mut backend.compositor.state,
+                        &mut backend.cursor_theme,
+                    );
+                }
+
                 let size = winit.window_size();
-                let damage = Rectangle::from_size(size);
-                {
+                // How many frames ago the back buffer was drawn, so only what changed since is redrawn
+                let age = winit.buffer_age().unwrap_or(0);
+                let damage = {
                     let (renderer, mut framebuffer) = winit.bind().unwrap();
 
                     let compositor = &mut backend.compositor;
+                    let elements = compositor.state.render_elements(
+                        renderer,
+                        &output,
+                        &mut backend.cursor_theme,
+                    );
 
-                    let elements = compositor
-                        .state
-                        .xdg_shell_state
-                        .toplevel_surfaces()
-                        .iter()
-                        .flat_map(|surface| {
-                            render_elements_from_surface_tree(
-                                renderer,
-                                surface.wl_surface(),
-                                (0, 0),
-                                1.0,
-                                1.0,
-                                Kind::Unspecified,
-                            )
-                        })
-                        .collect::<Vec<WaylandSurfaceRenderElement<GlesRenderer>>>();
-
-                    let mut frame = renderer
-                        .render(&mut framebuffer, size, Transform::Flipped180)
-                        .unwrap();
-                    frame
-                        .clear(Color32F::new(0.1, 0.0, 0.0, 1.0), &[damage])
-                        .unwrap();
-                    draw_render_elements(&mut frame, 1.0, &elements, &[damage]).unwrap();
+                    let damage_tracker = backend.damage_tracker.get_or_insert_with(|| {
+                        OutputDamageTracker::new(size, 1.0, Transform::Flipped180)
+                    });
                     // We rely on the nested compositor to do the sync for us
-                    let _ = frame.finish().unwrap();
-
-                    for surface in compositor.state.xdg_shell_state.toplevel_surfaces() {
-                        send_frames_surface_tree(
-                            surface.wl_surface(),
-                            compositor.start_time.elapsed().as_millis() as u32,
-                        );
-                    }
-
-                    if let Some(stream) = compositor
-                        .listener
-                        .accept()
-                        .expect("Failed to accept listener")
-                    {
-                        let client = compositor
-                            .display
-                            .handle()
-                            .insert_client(stream, Arc::new(ClientState::default()))
-                            .unwrap();
-                        compositor.clients.push(client);
-                    }
-
-                    compositor
-                        .display
-                        .dispatch_clients(&mut compositor.state)
-                        .expect("Failed to dispatch clients");
+                    let damage = match damage_tracker.render_output(
+                        renderer,
+                        &mut framebuffer,
+                        age,
+                        &elements,
+                        CLEAR_COLOR,
+                    ) {
+                        Ok(result) => result.damage.cloned().map(|damage| {
+                            let feedback = compositor
+                                .state
+                                .take_presentation_feedback(&output, &result.states);
+                            (damage, feedback)
+                        }),
+                        Err(e) => {
+                            log::info!("Failed to render: {:?}", e);
+                            None
+                        }
+                    };
                     compositor
-                        .display
-                        .flush_clients()
-                        .expect("Failed to flush clients");
-                }
+                        .state
+                        .frames
+                        .frame_done(Instant::now(), damaged || damage.is_some());
+                    compositor.state.send_frames(compositor.time());
+
+                    compositor.dispatch();
+                    damage
+                };
 
                 // It is important that all events on the display have been dispatched and flushed to clients before
                 // swapping buffers because this operation may block.
-                winit.submit(Some(&[damage])).unwrap();
-            }
-
-            // Redraw the application.
-            //
-            // It's preferable for applications that do not render continuously to render in
-            // this event rather than in AboutToWait, since rendering in here allows
-            // the program to gracefully handle redraws requested by the OS.
-
-            // Draw.
-
-            // Queue a RedrawRequested event.
-            //
-            // You only need to call this if you've determined that you need to redraw in
-            // applications which do not always need to. Applications that redraw continuously
-            // can render here instead.
-            backend
-                .graphic_renderer
-                .as_ref()
-                .unwrap()
-                .window()
-                .request_redraw();
-        }
-        CentralizedEvent::Input(event) => match event {
-            InputEvent::Keyboard { event } => {
-                let compositor = &mut backend.compositor;
-                let state = &mut compositor.state;
-                let serial = SERIAL_COUNTER.next_serial();
-                let time = compositor.start_time.elapsed().as_millis() as u32;
-                compositor.keyboard.input::<(), _>(
-                    state,
-                    event.key_code(),
-                    event.state(),
-                    serial,
-                    time,
-                    |_, _, _| {
-                        //
-                        FilterResult::Forward
-                    },
+                // Nothing changed since the last frame: the back buffer is left as is and not presented.
+                let state = &backend.compositor.state;
+                if let Some((damage, feedback)) = damage {
+                    let frame_id = winit.frame_timestamps().next_frame_id();
+                    winit.submit(Some(&damage)).unwrap();
+                    winit
+                        .frame_timestamps()
+                        .frame_swapped(frame_id, feedback, state);
+                }
+                winit.frame_timestamps().poll(state);
+
+                // Screenshots draw on buffers of their own, which would leave the window unbound
+                let state = &mut backend.compositor.state;
+                state.render_screencopies::<_, GlesTexture>(
+                    winit.renderer(),
+                    &output,
+                    &mut backend.cursor_theme,
+                    Transform::Flipped180,
                 );
-            }
-            InputEvent::TouchDown { event } => {
-                let compositor = &mut backend.compositor;
-                let state = &mut compositor.state;
-                if let Some(surface) = get_surface(state) {
-                    compositor.keyboard.set_focus(
-                        state,
-                        Some(surface.wl_surface().clone()),
-                        0.into(),
-                    );
-                    let serial = SERIAL_COUNTER.next_serial();
-                    let time = compositor.start_time.elapsed().as_millis() as u32;
-                    compositor.touch.down(
-                        state,
-                        Some((surface.wl_surface().clone(), (0f64, 0f64).into())),
-                        &touch::DownEvent {
-                            slot: event.slot(),
-                            location: (event.x(), event.y()).into(),
-                            serial,
-                            time,
-                        },
-                    );
-                };
-            }
-            InputEvent::TouchUp { event } => {
-                let compositor = &mut backend.compositor;
-                let state = &mut compositor.state;
-                if let Some(_surface) = get_surface(state) {
-                    let serial = SERIAL_COUNTER.next_serial();
-                    let time = compositor.start_time.elapsed().as_millis() as u32;
-                    compositor.touch.up(
-                        state,
-                        &touch::UpEvent {
-                            slot: event.slot(),
-                            serial,
-                            time,
-                        },
-                    );
-                };
-            }
-            InputEvent::TouchMotion { event } => {
-                let compositor = &mut backend.compositor;
-                let state = &mut compositor.state;
-                if let Some(surface) = get_surface(state) {
-                    let time = compositor.start_time.elapsed().as_millis() as u32;
-                    compositor.touch.motion(
-                        state,
-                        Some((surface.wl_surface().clone(), (0f64, 0f64).into())),
-                        &touch::MotionEvent {
-                            slot: event.slot(),
-                            location: (event.x(), event.y()).into(),
-                            time,
-                        },
-                    );
-                };
-            }
-            InputEvent::PointerMotionAbsolute { event, .. } => {
-                let compositor = &mut backend.compositor;
-                let pointer = compositor.pointer.clone();
-                let serial = SERIAL_COUNTER.next_serial();
-
-                if let Some(surface) = get_surface(&compositor.state) {
-                    pointer.motion(
-                        &mut compositor.state,
-                        Some((surface.wl_surface().clone(), (0f64, 0f64).into())),
-                        &pointer::MotionEvent {
-                            location: (event.x(), event.y()).into(),
-                            serial,
-                            time: event.time_msec(),
-                        },
+                for display in backend.external_displays.values() {
+                    state.render_screencopies::<_, GlesTexture>(
+                        winit.renderer(),
+                        &display.output,
+                        &mut backend.cursor_theme,
+                        Transform::Flipped180,
                     );
                 }
-                pointer.frame(&mut compositor.state);
             }
-            InputEvent::PointerButton { event, .. } => {
-                let serial = SERIAL_COUNTER.next_serial();
-                let button = event.button_code();
-
-                let state = ButtonState::from(event.state());
-
-                let compositor = &mut backend.compositor;
-                let pointer = compositor.pointer.clone();
-
-                if let Some(surface) = get_surface(&compositor.state) {
-                    compositor.keyboard.set_focus(
-                        &mut compositor.state,
-                        Some(surface.wl_surface().clone()),
-                        0.into(),
+            // The next frame is requested in `about_to_wait`, once something changes
+        }
+        CentralizedEvent::Input(event) => {
+            let compositor = &mut backend.compositor;
+            match event {
+                InputEvent::Keyboard { event } => {
+                    // Ctrl+Shift+Esc opens the task manager, unless the focused client wants every key
+                    let modifiers = compositor.keyboard.modifier_state();
+                    if event.key_code() == TASK_MANAGER_KEY
+                        && modifiers.ctrl
+                        && modifiers.shift
+                        && !compositor.shortcuts_inhibited()
+                    {
+                        backend.task_manager_requested |= event.state() == KeyState::Pressed;
+                        return;
+                    }
+                    compositor.key(event.key_code(), event.state());
+                }
+                InputEvent::TouchDown { event } => {
+                    let location = compositor.output_location(event.x(), event.y());
+                    compositor.touch_down(event.slot(), location);
+                }
+                InputEvent::TouchUp { event } => {
+                    compositor.touch_up(event.slot());
+                }
+                InputEvent::TouchMotion { event } => {
+                    let location = compositor.output_location(event.x(), event.y());
+                    compositor.touch_motion(event.slot(), location);
+                }
+                InputEvent::PointerMotionAbsolute { event, .. } => {
+                    let location = compositor.output_location(event.x(), event.y());
+                    compositor.pointer_motion(location, event.time_msec());
+                }
+                InputEvent::PointerMotion { event } => {
+                    compositor.pointer_motion_relative(event.delta(), event.time_msec());
+                }
+                InputEvent::PointerButton { event, .. } => {
+                    compositor.pointer_button(
+                        event.button_code(),
+                        event.state(),
+                        event.time_msec(),
                     );
                 }
-                pointer.button(
-                    &mut compositor.state,
-                    &pointer::ButtonEvent {
-                        button,
-                        state: state.try_into().unwrap(),
-                        serial,
-                        time: event.time_msec(),
-                    },
-                );
-                pointer.frame(&mut compositor.state);
-            }
-            InputEvent::PointerAxis { event } => {
-                let horizontal_amount = event
-                    .amount(Axis::Horizontal)
-                    .unwrap_or_else(|| event.amount_v120(Axis::Horizontal).unwrap_or(0.0) / 120.);
-                let vertical_amount = event
-                    .amount(Axis::Vertical)
-                    .unwrap_or_else(|| event.amount_v120(Axis::Vertical).unwrap_or(0.0) / 120.);
-                let horizontal_amount_discrete = event.amount_v120(Axis::Horizontal);
-                let vertical_amount_discrete = event.amount_v120(Axis::Vertical);
-
-                {
-                    let mut frame =
-                        pointer::AxisFrame::new(event.time_msec()).source(event.source());
+                InputEvent::PointerAxis { event } => {
+                    let horizontal_amount = event.amount(Axis::Horizontal).unwrap_or_else(|| {
+                        event.amount_v120(Axis::Horizontal).unwrap_or(0.0) / 120.
+                    });
+                    let vertical_amount = event
+                        .amount(Axis::Vertical)
+                        .unwrap_or_else(|| event.amount_v120(Axis::Vertical).unwrap_or(0.0) / 120.);
+                    let horizontal_amount_discrete = event.amount_v120(Axis::Horizontal);
+                    let vertical_amount_discrete = event.amount_v120(Axis::Vertical);
+
+                    let mut frame = AxisFrame::new(event.time_msec()).source(event.source());
                     if horizontal_amount != 0.0 {
                         frame = frame.relative_direction(
                             Axis::Horizontal,
@@ -289,31 +188,49 @@ pub fn handle(event: CentralizedEvent, backend: &mut WaylandBackend, event_loop:
                     if event.amount(Axis::Vertical) == Some(0.0) {
                         frame = frame.stop(Axis::Vertical);
                     }
-                    let compositor = &mut backend.compositor;
-                    let pointer = compositor.pointer.clone();
-                    pointer.axis(&mut compositor.state, frame);
-                    pointer.frame(&mut compositor.state);
+                    compositor.pointer_axis(frame);
                 }
+                _ => {}
             }
-            _ => {}
+        }
+        CentralizedEvent::Ime(ime) => match ime {
+            Ime::Preedit(text, cursor) => backend.compositor.ime_preedit(text, cursor),
+            Ime::Commit(text) => backend.compositor.ime_commit(text),
+            Ime::Enabled | Ime::Disabled => {}
         },
-        CentralizedEvent::Resized { size, scale_factor } => {
-            if let Some(output) = &backend.compositor.output {
-                // set the preferred mode
-                output.change_current_state(
-                    Some(Mode {
-                        size: size.into(),
-                        refresh: 60000,
-                    }), // the resolution mode,
-                    Some(Transform::Normal), // global screen transformation
-                    Some(Scale::Fractional(scale_factor)), // global screen scaling factor
-                    Some((0, 0).into()),     // output position
-                );
+        CentralizedEvent::Stylus(stylus) => {
+            let compositor = &mut backend.compositor;
+            if stylus.phase == StylusPhase::Left {
+                compositor.stylus_leave();
+                return;
             }
-
-            if let Some(surface) = get_surface(&backend.compositor.state) {
-                surface.xdg_toplevel().configure(size.w, size.h, vec![]);
+            compositor.stylus_motion(StylusInput {
+                location: compositor.output_location(stylus.location.x, stylus.location.y),
+                eraser: stylus.eraser,
+                pressure: stylus.pressure.clamp(0.0, 1.0),
+                tilt: tilt_degrees(stylus.tilt, stylus.orientation),
+                distance: hover_distance(stylus.distance),
+                primary_button: stylus.primary_button,
+                secondary_button: stylus.secondary_button,
+            });
+            match stylus.phase {
+                StylusPhase::Down => compositor.stylus_down(),
+                StylusPhase::Up => compositor.stylus_up(),
+                StylusPhase::Moved | StylusPhase::Left => {}
+            }
+        }
+        CentralizedEvent::Focus(false) => {
+            // Android releases the captured pointer along with the focus, it is captured again once back
+            backend.pointer_captured = false;
+        }
+        CentralizedEvent::Resized { size, .. } => {
+            backend.damage_tracker = None;
+            if let Some(output) = &backend.compositor.output {
+                // The refresh rates and the scale of the display stay the same
+                resize_output(output, size);
             }
+            // Maximized windows follow the new size, and the external displays the built-in one
+            backend.arrange_outputs();
         }
         _ => (),
     }
@@ -0,0 +1,191 @@
+use super::{frame_timestamps::FrameTimestamps, winit_backend::AndroidNativeSurface};
+use crate::compositor::{CursorTheme, State, CLEAR_COLOR};
+use smithay::{
+    backend::{
+        egl::EGLSurface,
+        renderer::{damage::OutputDamageTracker, gles::GlesRenderer, Bind},
+    },
+    output::Output,
+    reexports::wayland_server::{backend::GlobalId, DisplayHandle},
+    utils::{Physical, Rectangle, Size, Transform},
+};
+use std::{ffi::c_void, ptr::NonNull};
+use winit::raw_window_handle::AndroidNdkWindowHandle;
+
+#[link(name = "android")]
+extern "C" {
+    fn ANativeWindow_release(window: *mut c_void);
+}
+
+/// A reference to an `ANativeWindow`, released when dropped
+pub struct NativeWindow(NonNull<c_void>);
+
+unsafe impl Send for NativeWindow {}
+
+impl NativeWindow {
+    /// # Safety
+    /// `window` must be an `ANativeWindow` that was acquired for us, e.g. by `ANativeWindow_fromSurface`
+    pub unsafe fn from_raw(window: NonNull<c_void>) -> Self {
+        Self(window)
+    }
+}
+
+impl Drop for NativeWindow {
+    fn drop(&mut self) {
+        unsafe { ANativeWindow_release(self.0.as_ptr()) };
+    }
+}
+
+/// A display the desktop is extended to (Samsung DeX, USB-C monitor...), drawn into the surface of the
+/// `Presentation` shown on it with the GL context of the activity window
+pub struct ExternalDisplay {
+    pub output: Output,
+    global: GlobalId,
+    window: NativeWindow,
+    size: Size<i32, Physical>,
+    /// Gone while the activity is in the background, as the GL context is
+    surface: Option<EGLSurface>,
+    /// When the frames drawn into `surface` were shown
+    frame_timestamps: Option<FrameTimestamps>,
+    damage_tracker: Option<OutputDamageTracker>,
+}
+
+impl ExternalDisplay {
+    pub fn new(
+        output: Output,
+        global: GlobalId,
+        window: NativeWindow,
+        size: Size<i32, Physical>,
+    ) -> Self {
+        Self {
+            output,
+            global,
+            window,
+            size,
+            surface: None,
+            frame_timestamps: None,
+            damage_tracker: None,
+        }
+    }
+
+    /// The `Presentation` got a new surface, or its surface was resized
+    pub fn set_window(&mut self, window: NativeWindow, size: Size<i32, Physical>) {
+        self.unbind();
+        self.window = window;
+        self.size = size;
+    }
+
+    /// Create the EGL surface to draw into, unless there already is one
+    pub fn bind(&mut self, renderer: &GlesRenderer) {
+        if self.surface.is_some() {
+            return;
+        }
+        let context = renderer.egl_context();
+        let Some(pixel_format) = context.pixel_format() else {
+            return;
+        };
+        let native = AndroidNativeSurface::new(AndroidNdkWindowHandle::new(self.window.0));
+        match unsafe {
+            EGLSurface::new(context.display(), pixel_format, context.config_id(), native)
+        } {
+            Ok(surface) => {
+                self.frame_timestamps = Some(FrameTimestamps::new(context.display(), &surface));
+                self.surface = Some(surface);
+            }
+            Err(e) => log::info!(
+                "Failed to create the surface of {}: {}",
+                self.output.name(),
+                e
+            ),
+        }
+    }
+
+    /// Drop everything bound to the GL context
+    pub fn unbind(&mut self) {
+        self.frame_timestamps = None;
+        self.surface = None;
+        self.damage_tracker = None;
+    }
+
+    /// Draw what changed on the output since the last frame, returns whether anything was drawn
+    pub fn render(
+        &mut self,
+        renderer: &mut GlesRenderer,
+        state: &mut State,
+        cursor_theme: &mut CursorTheme,
+    ) -> bool {
+        let (Some(surface), Some(frame_timestamps)) =
+            (self.surface.as_mut(), self.frame_timestamps.as_mut())
+        else {
+            return false;
+        };
+        let age = surface.buffer_age().unwrap_or(0).max(0) as usize;
+        let elements = state.render_elements(renderer, &self.output, cursor_theme);
+        let size = self.size;
+        let damage_tracker = self
+            .damage_tracker
+            .get_or_insert_with(|| OutputDamageTracker::new(size, 1.0, Transform::Flipped180));
+
+        let damage = {
+            let mut framebuffer = match renderer.bind(surface) {
+                Ok(framebuffer) => framebuffer,
+                Err(e) => {
+                    log::info!(
+                        "Failed to bind the surface of {}: {}",
+                        self.output.name(),
+                        e
+                    );
+                    return false;
+                }
+            };
+            match damage_tracker.render_output(
+                renderer,
+                &mut framebuffer,
+                age,
+                &elements,
+                CLEAR_COLOR,
+            ) {
+                Ok(result) => result.damage.cloned().map(|damage| {
+                    let feedback = state.take_presentation_feedback(&self.output, &result.states);
+                    (damage, feedback)
+                }),
+                Err(e) => {
+                    log::info!("Failed to render {}: {:?}", self.output.name(), e);
+                    None
+                }
+            }
+        };
+        let Some((damage, feedback)) = damage else {
+            return false;
+        };
+
+        // EGL counts rows from the bottom
+        let mut damage = damage
+            .iter()
+            .map(|rect| {
+                Rectangle::new(
+                    (rect.loc.x, size.h - rect.loc.y - rect.size.h).into(),
+                    rect.size,
+                )
+            })
+            .collect::<Vec<_>>();
+        let frame_id = frame_timestamps.next_frame_id();
+        match surface.swap_buffers(Some(&mut damage)) {
+            Ok(()) => frame_timestamps.frame_swapped(frame_id, feedback, state),
+            Err(e) => log::info!("Failed to present {}: {}", self.output.name(), e),
+        }
+        true
+    }
+
+    /// Send the presentation feedback of the frames shown by now, returns whether any are still to come
+    pub fn poll_frame_timestamps(&mut self, state: &State) -> bool {
+        self.frame_timestamps
+            .as_mut()
+            .is_some_and(|frame_timestamps| frame_timestamps.poll(state))
+    }
+
+    /// Stop advertising the output to clients
+    pub fn remove(self, display_handle: &DisplayHandle) {
+        display_handle.remove_global::<State>(self.global);
+    }
+}
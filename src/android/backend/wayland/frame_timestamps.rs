@@ -0,0 +1,166 @@
+use crate::compositor::State;
+use smithay::{
+    backend::egl::{
+        display::EGLDisplayHandle,
+        ffi::egl::{
+            self,
+            types::{EGLBoolean, EGLDisplay as RawDisplay, EGLSurface as RawSurface, EGLint},
+        },
+        get_proc_address, EGLDisplay, EGLSurface,
+    },
+    desktop::utils::OutputPresentationFeedback,
+};
+use std::{collections::VecDeque, mem::transmute, sync::Arc, time::Duration};
+
+const EXTENSION: &str = "EGL_ANDROID_get_frame_timestamps";
+const EGL_TIMESTAMPS_ANDROID: EGLint = 0x3430;
+const EGL_DISPLAY_PRESENT_TIME_ANDROID: EGLint = 0x343A;
+const EGL_TIMESTAMP_PENDING_ANDROID: i64 = -2;
+
+/// Frames older than this are gone from the history of the driver
+const MAX_PENDING_FRAMES: usize = 8;
+
+type GetNextFrameId = unsafe extern "system" fn(RawDisplay, RawSurface, *mut u64) -> EGLBoolean;
+type GetFrameTimestamps = unsafe extern "system" fn(
+    RawDisplay,
+    RawSurface,
+    u64,
+    EGLint,
+    *const EGLint,
+    *mut i64,
+) -> EGLBoolean;
+type GetFrameTimestampSupported =
+    unsafe extern "system" fn(RawDisplay, RawSurface, EGLint) -> EGLBoolean;
+
+#[derive(Debug)]
+struct Api {
+    display: Arc<EGLDisplayHandle>,
+    surface: RawSurface,
+    get_next_frame_id: GetNextFrameId,
+    get_frame_timestamps: GetFrameTimestamps,
+}
+
+/// When the frames swapped to an `EGLSurface` were shown by the display, as reported by
+/// `EGL_ANDROID_get_frame_timestamps`. Their presentation feedback is held back until then.
+/// Must be dropped together with its surface.
+#[derive(Debug)]
+pub struct FrameTimestamps {
+    /// `None` when the driver does not report the present time
+    api: Option<Api>,
+    /// Frames swapped but not known to be on screen yet, oldest first
+    pending: VecDeque<(u64, OutputPresentationFeedback)>,
+}
+
+unsafe impl Send for FrameTimestamps {}
+
+impl FrameTimestamps {
+    pub fn new(display: &EGLDisplay, surface: &EGLSurface) -> Self {
+        let api = display
+            .extensions()
+            .iter()
+            .any(|extension| extension == EXTENSION)
+            .then(|| unsafe { Api::load(display.get_display_handle(), surface) })
+            .flatten();
+        if api.is_none() {
+            log::info!("The display does not report present times, the swap time is sent instead");
+        }
+        Self {
+            api,
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Id of the frame the next swap will present, to be given to `frame_swapped` after it
+    pub fn next_frame_id(&self) -> Option<u64> {
+        let api = self.api.as_ref()?;
+        let mut frame_id = 0;
+        let ok = unsafe {
+            (api.get_next_frame_id)(api.display.handle, api.surface, &mut frame_id) == egl::TRUE
+        };
+        ok.then_some(frame_id)
+    }
+
+    /// Send `feedback` once the frame `frame_id` is on screen, or right away if its time cannot be known
+    pub fn frame_swapped(
+        &mut self,
+        frame_id: Option<u64>,
+        feedback: OutputPresentationFeedback,
+        state: &State,
+    ) {
+        match frame_id {
+            Some(frame_id) => {
+                self.pending.push_back((frame_id, feedback));
+                if self.pending.len() > MAX_PENDING_FRAMES {
+                    if let Some((_, mut feedback)) = self.pending.pop_front() {
+                        feedback.discarded();
+                    }
+                }
+            }
+            None => state.frame_presented(feedback, None),
+        }
+    }
+
+    /// Send the feedback of the frames the display has shown by now, returns whether any are still to come
+    pub fn poll(&mut self, state: &State) -> bool {
+        let Some(api) = &self.api else {
+            return false;
+        };
+        while let Some((frame_id, _)) = self.pending.front() {
+            let mut present_time = 0;
+            let ok = unsafe {
+                (api.get_frame_timestamps)(
+                    api.display.handle,
+                    api.surface,
+                    *frame_id,
+                    1,
+                    &EGL_DISPLAY_PRESENT_TIME_ANDROID,
+                    &mut present_time,
+                ) == egl::TRUE
+            };
+            if ok && present_time == EGL_TIMESTAMP_PENDING_ANDROID {
+                break;
+            }
+            let Some((_, mut feedback)) = self.pending.pop_front() else {
+                break;
+            };
+            if ok && present_time >= 0 {
+                state.frame_presented(feedback, Some(Duration::from_nanos(present_time as u64)));
+            } else {
+                // Never shown, e.g. replaced by a newer frame before the display got to it
+                feedback.discarded();
+            }
+        }
+        !self.pending.is_empty()
+    }
+}
+
+impl Api {
+    unsafe fn load(display: Arc<EGLDisplayHandle>, surface: &EGLSurface) -> Option<Self> {
+        let next_frame_id = get_proc_address("eglGetNextFrameIdANDROID");
+        let frame_timestamps = get_proc_address("eglGetFrameTimestampsANDROID");
+        let supported = get_proc_address("eglGetFrameTimestampSupportedANDROID");
+        if next_frame_id.is_null() || frame_timestamps.is_null() || supported.is_null() {
+            return None;
+        }
+        let supported = transmute::<*const _, GetFrameTimestampSupported>(supported);
+
+        let surface = surface.get_surface_handle();
+        if supported(display.handle, surface, EGL_DISPLAY_PRESENT_TIME_ANDROID) != egl::TRUE
+            || egl::SurfaceAttrib(
+                display.handle,
+                surface,
+                EGL_TIMESTAMPS_ANDROID,
+                egl::TRUE as EGLint,
+            ) != egl::TRUE
+        {
+            return None;
+        }
+
+        Some(Self {
+            display,
+            surface,
+            get_next_frame_id: transmute::<*const _, GetNextFrameId>(next_frame_id),
+            get_frame_timestamps: transmute::<*const _, GetFrameTimestamps>(frame_timestamps),
+        })
+    }
+}
@@ -8,8 +8,8 @@ use winit::{
 use smithay::backend::input::{
     AbsolutePositionEvent, Axis, AxisRelativeDirection, AxisSource, ButtonState, Device,
     DeviceCapability, Event, InputBackend, KeyState, KeyboardKeyEvent, Keycode, PointerAxisEvent,
-    PointerButtonEvent, PointerMotionAbsoluteEvent, TouchCancelEvent, TouchDownEvent, TouchEvent,
-    TouchMotionEvent, TouchSlot, TouchUpEvent, UnusedEvent,
+    PointerButtonEvent, PointerMotionAbsoluteEvent, PointerMotionEvent, TouchCancelEvent,
+    TouchDownEvent, TouchEvent, TouchMotionEvent, TouchSlot, TouchUpEvent, UnusedEvent,
 };
 
 /// Marker used to define the `InputBackend` types for the winit backend.
@@ -119,6 +119,42 @@ impl AbsolutePositionEvent<WinitInput> for WinitMouseMovedEvent {
     }
 }
 
+/// Winit-Backend internal event wrapping `winit`'s types into a [`PointerMotionEvent`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WinitMouseRelativeMotionEvent {
+    pub(crate) time: u64,
+    pub(crate) delta: (f64, f64),
+}
+
+impl Event<WinitInput> for WinitMouseRelativeMotionEvent {
+    fn time(&self) -> u64 {
+        self.time
+    }
+
+    fn device(&self) -> WinitVirtualDevice {
+        WinitVirtualDevice
+    }
+}
+
+impl PointerMotionEvent<WinitInput> for WinitMouseRelativeMotionEvent {
+    fn delta_x(&self) -> f64 {
+        self.delta.0
+    }
+
+    fn delta_y(&self) -> f64 {
+        self.delta.1
+    }
+
+    // Android hands over the motion of a captured mouse without acceleration
+    fn delta_x_unaccel(&self) -> f64 {
+        self.delta.0
+    }
+
+    fn delta_y_unaccel(&self) -> f64 {
+        self.delta.1
+    }
+}
+
 /// Winit-Backend internal event wrapping `winit`'s types into a [`PointerAxisEvent`]
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct WinitMouseWheelEvent {
@@ -374,7 +410,7 @@ impl InputBackend for WinitInput {
     type KeyboardKeyEvent = WinitKeyboardInputEvent;
     type PointerAxisEvent = WinitMouseWheelEvent;
     type PointerButtonEvent = WinitMouseInputEvent;
-    type PointerMotionEvent = UnusedEvent;
+    type PointerMotionEvent = WinitMouseRelativeMotionEvent;
     type PointerMotionAbsoluteEvent = WinitMouseMovedEvent;
 
     type GestureSwipeBeginEvent = UnusedEvent;
@@ -1,20 +1,23 @@
-pub mod bind;
-mod compositor;
 mod event_centralizer;
 mod event_handler;
+mod external_display;
+mod frame_timestamps;
 mod input;
 mod keymap;
 mod winit_backend;
 
-pub use compositor::{Compositor, State};
-pub use event_centralizer::{centralize, CentralizedEvent};
+pub use crate::compositor::{Compositor, CursorTheme, State};
+pub use event_centralizer::{centralize, centralize_device_event, CentralizedEvent};
 pub use event_handler::handle;
+pub use external_display::{ExternalDisplay, NativeWindow};
 pub use winit_backend::{bind, WinitGraphicsBackend};
 
+use crate::core::config::OutputArrangement;
 use smithay::{
-    backend::renderer::gles::GlesRenderer,
+    backend::renderer::{damage::OutputDamageTracker, gles::GlesRenderer},
     utils::{Clock, Monotonic},
 };
+use std::collections::BTreeMap;
 
 pub struct WaylandBackend {
     pub compositor: Compositor,
@@ -22,4 +25,41 @@ pub struct WaylandBackend {
     pub clock: Clock<Monotonic>,
     pub key_counter: u32,
     pub scale_factor: f64,
+    pub cursor_theme: CursorTheme,
+    /// Created on the first frame drawn to the current window, at its size
+    pub damage_tracker: Option<OutputDamageTracker>,
+    /// By Android display id, laid out after the built-in display in that order
+    pub external_displays: BTreeMap<i32, ExternalDisplay>,
+    pub output_arrangement: OutputArrangement,
+    /// Input type the Android keyboard is shown with, `None` while it is hidden
+    pub soft_keyboard: Option<i32>,
+    /// Whether Android hands the raw motion of the mouse over, see `Compositor::pointer_constrained`
+    pub pointer_captured: bool,
+    /// Whether the system key combinations go to the focused client, see `Compositor::shortcuts_inhibited`
+    pub keyboard_captured: bool,
+    /// Whether Android keeps the screen on, see `State::idle_inhibited`
+    pub screen_kept_on: bool,
+    /// Ctrl+Shift+Esc was pressed, the task manager is shown in `about_to_wait`
+    pub task_manager_requested: bool,
+}
+
+impl WaylandBackend {
+    /// Lay the built-in display and the external ones out again, after one of them changed
+    pub fn arrange_outputs(&mut self) {
+        let outputs = self
+            .compositor
+            .output
+            .iter()
+            .chain(
+                self.external_displays
+                    .values()
+                    .map(|display| &display.output),
+            )
+            .cloned()
+            .collect::<Vec<_>>();
+        self.compositor
+            .state
+            .arrange_outputs(&outputs, self.output_arrangement);
+        self.compositor.state.frames.schedule();
+    }
 }
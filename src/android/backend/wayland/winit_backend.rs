@@ -18,6 +18,7 @@
 //! The other types in this module are the instances of the associated types of these
 //! two traits for the winit backend.
 
+use super::frame_timestamps::FrameTimestamps;
 use khronos_egl::DynamicInstance;
 use smithay::{
     backend::{
@@ -140,6 +141,12 @@ pub struct AndroidNativeSurface {
 
 unsafe impl Send for AndroidNativeSurface {}
 
+impl AndroidNativeSurface {
+    pub fn new(handle: AndroidNdkWindowHandle) -> Self {
+        Self { handle }
+    }
+}
+
 unsafe impl EGLNativeSurface for AndroidNativeSurface {
     unsafe fn create(
         &self,
@@ -235,13 +242,13 @@ pub fn bind(event_loop: &ActiveEventLoop) -> WinitGraphicsBackend<GlesRenderer>
 
     let renderer = unsafe { GlesRenderer::new(context) }.expect("Failed to create GLES Renderer");
     let damage_tracking = display.supports_damage();
-
-    event_loop.set_control_flow(winit::event_loop::ControlFlow::Poll);
+    let frame_timestamps = FrameTimestamps::new(&display, &surface);
 
     WinitGraphicsBackend {
         window: window.clone(),
         _display: display,
         egl_surface: surface,
+        frame_timestamps,
         damage_tracking,
         bind_size: None,
         renderer,
@@ -272,6 +279,7 @@ pub struct WinitGraphicsBackend<R> {
     // The display isn't used past this point but must be kept alive.
     _display: EGLDisplay,
     egl_surface: EGLSurface,
+    frame_timestamps: FrameTimestamps,
     window: Arc<WinitWindow>,
     damage_tracking: bool,
     bind_size: Option<Size<i32, Physical>>,
@@ -328,6 +336,11 @@ where
         &self.egl_surface
     }
 
+    /// When the frames submitted to the window were shown
+    pub fn frame_timestamps(&mut self) -> &mut FrameTimestamps {
+        &mut self.frame_timestamps
+    }
+
     /// Retrieve the buffer age of the current backbuffer of the window.
     ///
     /// This will only return a meaningful value, if this `WinitGraphicsBackend`
@@ -1,11 +1,24 @@
-use crate::android::proot::setup::SetupMessage;
-use serde_json::json;
-use std::sync::mpsc::Receiver;
+use crate::android::proot::{
+    services::{self, service_statuses, watch_services},
+    setup::SetupMessage,
+};
+use crate::core::services::ServiceStatus;
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
 use std::sync::{Arc, Mutex};
 use std::thread;
+use std::time::Duration;
 use websocket::sync::Server;
 use websocket::OwnedMessage;
 
+/// How often the services are checked for whether they listen, their starts and exits are sent right away
+const SERVICE_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+fn services_message(statuses: &[ServiceStatus]) -> OwnedMessage {
+    OwnedMessage::Text(json!({ "services": statuses }).to_string())
+}
+
 pub enum ErrorVariant {
     None,
     Unsupported,
@@ -48,9 +61,71 @@ impl WebviewBackend {
                 let client = request.use_protocol("rust-websocket").accept().unwrap();
                 let ip = client.peer_addr().unwrap();
                 log::info!("Connection from {}", ip);
+                let (mut reader, mut writer) = match client.split() {
+                    Ok(halves) => halves,
+                    Err(e) => {
+                        log::info!("Failed to split the connection from {}: {}", ip, e);
+                        continue;
+                    }
+                };
+
+                // Let the client know which services are configured before the progress updates
+                let mut statuses = service_statuses();
+                if writer.send_message(&services_message(&statuses)).is_err() {
+                    log::info!("Failed to send service statuses to {}", ip);
+                }
 
                 // Store the new client
-                *active_client = Some(client); // Store the writer part of the connection
+                *active_client = Some(writer); // Store the writer part of the connection
+                let connected = Arc::new(AtomicBool::new(true));
+
+                // Keep the service statuses up to date, as services start, exit or begin listening
+                let (watcher, changes) = mpsc::channel();
+                watch_services(watcher);
+                let status_client = active_client_clone.clone();
+                let still_connected = connected.clone();
+                thread::spawn(move || {
+                    while still_connected.load(Ordering::Relaxed) {
+                        if let Err(RecvTimeoutError::Disconnected) =
+                            changes.recv_timeout(SERVICE_POLL_INTERVAL)
+                        {
+                            break;
+                        }
+                        let current = service_statuses();
+                        if current == statuses {
+                            continue;
+                        }
+                        let mut active_client = status_client.lock().unwrap();
+                        let Some(writer) = active_client.as_mut() else {
+                            break;
+                        };
+                        if writer.send_message(&services_message(&current)).is_err() {
+                            break;
+                        }
+                        statuses = current;
+                    }
+                });
+
+                // Start the services the user asks for with `{"start": "<name>"}`
+                let reader_client = active_client_clone.clone();
+                thread::spawn(move || {
+                    for message in reader.incoming_messages() {
+                        let text = match message {
+                            Ok(OwnedMessage::Text(text)) => text,
+                            Ok(OwnedMessage::Close(_)) | Err(_) => break,
+                            Ok(_) => continue,
+                        };
+                        let request = serde_json::from_str::<Value>(&text).unwrap_or_default();
+                        if let Some(name) = request["start"].as_str() {
+                            if let Err(e) = services::start(name) {
+                                log::info!("Failed to start service {}: {}", name, e);
+                            }
+                        }
+                    }
+                    // Let the page connect again, e.g. once reloaded
+                    connected.store(false, Ordering::Relaxed);
+                    *reader_client.lock().unwrap() = None;
+                });
 
                 // Spawn a thread to handle messages for this client
                 let active_client_clone = active_client_clone.clone();
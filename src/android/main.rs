@@ -3,7 +3,9 @@ use crate::{
         app::build::PolarBearApp,
         utils::{
             application_context::ApplicationContext,
-            fullscreen_immersive::{enable_fullscreen_immersive_mode, keep_screen_on},
+            fullscreen_immersive::{
+                enable_fullscreen_immersive_mode, hide_pointer_icon, keep_screen_on,
+            },
             ndk::run_in_jvm,
         },
     },
@@ -61,6 +63,7 @@ fn android_main(android_app: AndroidApp) {
 
     run_in_jvm(enable_fullscreen_immersive_mode, android_app.clone());
     run_in_jvm(keep_screen_on, android_app.clone());
+    run_in_jvm(hide_pointer_icon, android_app.clone());
 
     let event_loop = EventLoop::builder()
         .with_android_app(android_app.clone())
@@ -77,7 +80,7 @@ fn android_main(android_app: AndroidApp) {
     event_loop.set_control_flow(ControlFlow::Wait);
 
     // Phase 1: Setup
-    let mut app = PolarBearApp::build(android_app);
+    let mut app = PolarBearApp::build(android_app, event_loop.create_proxy());
 
     // Phase 2: Run
     event_loop.run_app(&mut app).expect("Failed to run app");
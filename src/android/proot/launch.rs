@@ -1,26 +1,29 @@
-use super::process::ArchProcess;
-use crate::android::utils::application_context::get_application_context;
+use super::{process::ArchProcess, services::start_services, storage, task_manager};
+use crate::android::utils::{
+    application_context::get_application_context, clipboard::watch_clipboard,
+    foreground_service::stop_foreground_service, ndk::run_in_jvm,
+};
 use std::sync::Arc;
 use std::thread;
+use winit::platform::android::activity::AndroidApp;
 
-pub fn launch() {
+pub fn launch(android_app: AndroidApp) {
     thread::spawn(move || {
-        // Clean up potential leftover files for display :1
-        ArchProcess {
-            command: "rm -f /tmp/.X1-lock".into(),
-            user: None,
-            log: None,
-        }
-        .run();
-        ArchProcess {
-            command: "rm -f /tmp/.X11-unix/X1".into(),
-            user: None,
-            log: None,
+        let context = get_application_context();
+        let local_config = context.local_config;
+        let username = local_config.user.username;
+
+        if context.permission_all_files_access {
+            storage::write_user_dirs(&username);
+            storage::watch_volumes(android_app.clone());
         }
-        .run();
 
-        let local_config = get_application_context().local_config;
-        let username = local_config.user.username;
+        start_services();
+        task_manager::serve();
+        task_manager::watch_memory(android_app.clone());
+        if local_config.clipboard.sync {
+            watch_clipboard(android_app.clone());
+        }
 
         ArchProcess {
             command: local_config.command.launch,
@@ -28,5 +31,10 @@ pub fn launch() {
             log: Some(Arc::new(|it| log::trace!("{}", it))),
         }
         .run();
+
+        // The desktop session has ended, nothing is left to keep alive in the background
+        if local_config.session.background {
+            run_in_jvm(stop_foreground_service, android_app);
+        }
     });
 }
@@ -1,3 +1,4 @@
+use super::storage;
 use crate::android::utils::application_context::get_application_context;
 use crate::core::config;
 use std::ffi::CString;
@@ -6,13 +7,16 @@ use std::io::{BufRead, BufReader, Read};
 use std::os::unix::fs::PermissionsExt;
 use std::path::Path;
 use std::process::{Command, Output, Stdio};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use winit::platform::android::activity::AndroidApp;
 
 pub type Log = Arc<dyn Fn(String) + Send + Sync>;
 
 const SUPPORT_CHECK_BINARY: &str = "ld-linux-aarch64.so.1";
 
+/// PIDs of the PRoot processes that are streaming their output, i.e. long running sessions
+static RUNNING_SESSIONS: Mutex<Vec<u32>> = Mutex::new(Vec::new());
+
 /// Runs a shell command inside the Arch Linux PRoot environment.
 ///
 /// - `command`: The shell command to execute (passed to `sh -c`).
@@ -99,9 +103,31 @@ impl ArchProcess {
         supported
     }
 
-    pub fn run(self) -> Output {
+    /// PIDs of the PRoot processes of the long running sessions, i.e. the roots of the container process tree
+    pub fn running_sessions() -> Vec<u32> {
+        RUNNING_SESSIONS
+            .lock()
+            .expect("Failed to lock running sessions")
+            .clone()
+    }
+
+    /// Kill every long running session. PRoot is started with `--kill-on-exit`,
+    /// so killing it takes down the whole process tree inside the container.
+    pub fn kill_running_sessions() {
+        let pids = std::mem::take(
+            &mut *RUNNING_SESSIONS
+                .lock()
+                .expect("Failed to lock running sessions"),
+        );
+        for pid in pids {
+            log::info!("Killing container session {}", pid);
+            let _ = Command::new("kill").arg(pid.to_string()).status();
+        }
+    }
+
+    /// The PRoot command entering the Arch FS as `user`, the program to run inside is added by the caller
+    fn proot(user: &str) -> Command {
         let context = get_application_context();
-        let user = self.user.as_deref().unwrap_or("root");
 
         let mut process = Command::new(context.native_library_dir.join("libproot.so"));
         process
@@ -128,8 +154,9 @@ impl ArchProcess {
 
         if context.permission_all_files_access {
             process
-                .arg("--bind=/sdcard:/android")
-                .arg("--bind=/sdcard:/root/Android");
+                .arg(format!("--bind=/sdcard:{}", storage::SDCARD_MOUNT))
+                .arg(format!("--bind=/sdcard:{}/Android", config::home_dir(user)))
+                .arg(format!("--bind=/storage:{}", storage::STORAGE_MOUNT));
         }
 
         process
@@ -147,19 +174,38 @@ impl ArchProcess {
             .arg(format!("--bind={}/proc/.sysctl_inotify_max_user_watches:/proc/sys/fs/inotify/max_user_watches", config::ARCH_FS_ROOT))
             .arg(format!("--bind={}/sys/.empty:/sys/fs/selinux", config::ARCH_FS_ROOT));
 
+        process
+    }
+
+    /// Environment variables of the programs running inside the Arch FS
+    fn guest_env(user: &str) -> Vec<(&'static str, String)> {
+        vec![
+            ("HOME", config::home_dir(user)),
+            ("LANG", "C.UTF-8".to_string()),
+            ("PATH", "/usr/local/sbin:/usr/local/bin:/usr/sbin:/usr/bin:/sbin:/bin:/usr/local/games:/usr/games:/system/bin:/system/xbin".to_string()),
+            ("TMPDIR", "/tmp".to_string()),
+            ("USER", user.to_string()),
+            ("LOGNAME", user.to_string()),
+        ]
+    }
+
+    /// A command running `program` as root inside the Arch FS, without a shell in between, so that the caller
+    /// can add arguments, environment variables and file descriptors, then spawn it. Used to start Xwayland.
+    pub fn guest_command(program: &str) -> Command {
+        let mut process = Self::proot("root");
+        process.envs(Self::guest_env("root")).arg(program);
+        process
+    }
+
+    pub fn run(self) -> Output {
+        let user = self.user.as_deref().unwrap_or("root");
+        let mut process = Self::proot(user);
+
         // env vars
         process.arg("/usr/bin/env").arg("-i");
-        if user == "root" {
-            process.arg("HOME=/root");
-        } else {
-            process.arg(format!("HOME=/home/{}", user));
+        for (key, value) in Self::guest_env(user) {
+            process.arg(format!("{}={}", key, value));
         }
-        process
-            .arg("LANG=C.UTF-8")
-            .arg("PATH=/usr/local/sbin:/usr/local/bin:/usr/sbin:/usr/bin:/sbin:/bin:/usr/local/games:/usr/games:/system/bin:/system/xbin")
-            .arg("TMPDIR=/tmp")
-            .arg(format!("USER={}", user))
-            .arg(format!("LOGNAME={}", user));
 
         // user shell
         if user == "root" {
@@ -182,15 +228,26 @@ impl ArchProcess {
                 .spawn()
                 .expect("Failed to run command");
 
+            let pid = child.id();
+            RUNNING_SESSIONS
+                .lock()
+                .expect("Failed to lock running sessions")
+                .push(pid);
+
             let reader = BufReader::new(child.stdout.take().unwrap());
             for line in reader.lines() {
                 let line = line.unwrap();
                 log(line);
             }
 
-            child
+            let output = child
                 .wait_with_output()
-                .expect("Failed to wait for command")
+                .expect("Failed to wait for command");
+            RUNNING_SESSIONS
+                .lock()
+                .expect("Failed to lock running sessions")
+                .retain(|it| *it != pid);
+            output
         } else {
             process.output().expect("Failed to run command")
         }
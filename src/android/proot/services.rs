@@ -0,0 +1,144 @@
+use super::process::ArchProcess;
+use crate::{
+    android::utils::application_context::get_application_context,
+    core::{
+        config::ServiceConfig,
+        forward::forward,
+        services::{self, startable_service, ServiceState, ServiceStatus},
+    },
+};
+use std::{
+    net::{SocketAddr, TcpStream},
+    sync::{mpsc::Sender, Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+/// States of the services started in this process, by service name
+static SERVICE_STATES: Mutex<Vec<(String, ServiceState)>> = Mutex::new(Vec::new());
+
+/// Told whenever a service starts or exits, see `watch_services`
+static WATCHERS: Mutex<Vec<Sender<()>>> = Mutex::new(Vec::new());
+
+/// Set the state of `name`, returning whether it had one before
+fn update_state(states: &mut Vec<(String, ServiceState)>, name: &str, state: ServiceState) -> bool {
+    match states.iter_mut().find(|(it, _)| it == name) {
+        Some((_, current)) => {
+            *current = state;
+            true
+        }
+        None => {
+            states.push((name.to_string(), state));
+            false
+        }
+    }
+}
+
+fn notify_watchers() {
+    WATCHERS
+        .lock()
+        .expect("Failed to lock service watchers")
+        .retain(|watcher| watcher.send(()).is_ok());
+}
+
+fn set_state(name: &str, state: ServiceState) {
+    update_state(
+        &mut SERVICE_STATES
+            .lock()
+            .expect("Failed to lock service states"),
+        name,
+        state,
+    );
+    notify_watchers();
+}
+
+/// Mark `name` as running, returning whether it was started before
+fn claim_service(name: &str) -> bool {
+    update_state(
+        &mut SERVICE_STATES
+            .lock()
+            .expect("Failed to lock service states"),
+        name,
+        ServiceState::Running,
+    )
+}
+
+fn states() -> Vec<(String, ServiceState)> {
+    SERVICE_STATES
+        .lock()
+        .expect("Failed to lock service states")
+        .clone()
+}
+
+fn is_listening(port: u16) -> bool {
+    let addr = SocketAddr::from(([127, 0, 0, 1], port));
+    TcpStream::connect_timeout(&addr, Duration::from_millis(200)).is_ok()
+}
+
+/// Status of every configured service
+pub fn service_statuses() -> Vec<ServiceStatus> {
+    let services = get_application_context().local_config.services;
+    services::service_statuses(services, &states(), is_listening)
+}
+
+/// Have `watcher` told whenever a service starts or exits, until its receiver is dropped
+pub fn watch_services(watcher: Sender<()>) {
+    WATCHERS
+        .lock()
+        .expect("Failed to lock service watchers")
+        .push(watcher);
+}
+
+/// Run `service`, which has already been marked as running
+fn start_service(service: ServiceConfig, username: String, started_before: bool) {
+    // The forwarder of a service started before is still there for it
+    if let Some(public_port) = service.public_port.filter(|_| !started_before) {
+        let listen = SocketAddr::from(([0, 0, 0, 0], public_port));
+        let target = SocketAddr::from(([127, 0, 0, 1], service.port));
+        match forward(listen, target) {
+            Ok(addr) => log::info!("Service {} is exposed on {}", service.name, addr),
+            Err(e) => log::info!("Failed to expose service {}: {}", service.name, e),
+        }
+    }
+
+    notify_watchers();
+    thread::spawn(move || {
+        let name = service.name.clone();
+        let output = ArchProcess {
+            command: service.command,
+            user: Some(username),
+            log: Some(Arc::new(move |it| log::trace!("[{}] {}", name, it))),
+        }
+        .run();
+
+        log::info!("Service {} exited with {:?}", service.name, output.status);
+        set_state(&service.name, ServiceState::Exited);
+    });
+}
+
+/// Start the services that should run together with the desktop session
+pub fn start_services() {
+    let local_config = get_application_context().local_config;
+    for service in local_config.services {
+        if service.autostart {
+            let started_before = claim_service(&service.name);
+            start_service(service, local_config.user.username.clone(), started_before);
+        }
+    }
+}
+
+/// Start the service `name` on request of the user, e.g. one that is not autostarted or has exited
+pub fn start(name: &str) -> Result<(), String> {
+    let local_config = get_application_context().local_config;
+    // Checked and marked as running under one lock, so that concurrent requests cannot both start it
+    let (service, started_before) = {
+        let mut states = SERVICE_STATES
+            .lock()
+            .expect("Failed to lock service states");
+        let service = startable_service(local_config.services, &states, name)?;
+        let started_before = update_state(&mut states, &service.name, ServiceState::Running);
+        (service, started_before)
+    };
+    start_service(service, local_config.user.username, started_before);
+    Ok(())
+}
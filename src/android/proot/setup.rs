@@ -3,23 +3,29 @@ use crate::{
     android::{
         app::build::PolarBearBackend,
         backend::{
-            wayland::{Compositor, WaylandBackend},
+            wayland::{Compositor, CursorTheme, WaylandBackend},
             webview::{ErrorVariant, WebviewBackend},
         },
         utils::application_context::get_application_context,
         utils::ndk::run_in_jvm,
     },
-    core::config::{CommandConfig, ARCH_FS_ARCHIVE, ARCH_FS_ROOT},
+    core::{
+        config::{
+            home_dir, is_valid_username, CommandConfig, ARCH_FS_ARCHIVE, ARCH_FS_ROOT, CONFIG_FILE,
+        },
+        shell::quote,
+    },
 };
 use jni::objects::JObject;
 use jni::sys::_jobject;
 use pathdiff::diff_paths;
 use smithay::utils::Clock;
 use std::{
+    collections::BTreeMap,
     fs::{self, File},
     io::{Read, Write},
-    os::unix::fs::{symlink, PermissionsExt},
-    path::Path,
+    os::unix::fs::{symlink, MetadataExt, PermissionsExt},
+    path::{Path, PathBuf},
     sync::{
         mpsc::{self, Sender},
         Arc, Mutex,
@@ -509,127 +515,111 @@ fn update_ini_section(content: &str, section: &str, updates: &[(&str, String)])
     content
 }
 
-fn extract_attr_value(line: &str, attr: &str) -> Option<String> {
-    let needle = format!("{}=\"", attr);
-    let start = line.find(&needle)? + needle.len();
-    let rest = &line[start..];
-    let end = rest.find('"')?;
-    Some(rest[..end].to_string())
-}
-
-fn extract_tag_value(line: &str, tag: &str) -> Option<String> {
-    let open = format!("<{}>", tag);
-    let close = format!("</{}>", tag);
-    let start = line.find(&open)? + open.len();
-    let end = line.find(&close)?;
-    if end < start {
-        return None;
-    }
-    Some(line[start..end].trim().to_string())
-}
-
-fn update_openbox_rc(content: &str, scale: i32, font_name: &str) -> (String, Option<String>) {
-    let active_size = 10 * scale;
-    let menu_size = 11 * scale;
-    let mut out: Vec<String> = Vec::new();
-    let mut in_font = false;
-    let mut in_theme = false;
-    let mut font_place: Option<String> = None;
-    let mut theme_name: Option<String> = None;
-
-    for line in content.lines() {
-        let trimmed = line.trim();
-        if trimmed.starts_with("<theme>") {
-            in_theme = true;
-            out.push(line.to_string());
-            continue;
-        }
-        if trimmed.starts_with("</theme>") {
-            in_theme = false;
-            out.push(line.to_string());
-            continue;
-        }
-
-        if trimmed.starts_with("<font") {
-            in_font = true;
-            font_place = extract_attr_value(trimmed, "place");
-            out.push(line.to_string());
-            continue;
-        }
-        if trimmed.starts_with("</font>") {
-            in_font = false;
-            font_place = None;
-            out.push(line.to_string());
-            continue;
-        }
-
-        if in_theme && !in_font && theme_name.is_none() {
-            if let Some(name) = extract_tag_value(trimmed, "name") {
-                theme_name = Some(name);
-            }
-            out.push(line.to_string());
-            continue;
-        }
+/// Groups a provisioned user joins by default, so that audio, video and input devices work as expected
+const DEFAULT_USER_GROUPS: &[&str] = &["wheel", "audio", "video", "input", "storage"];
 
-        if in_font {
-            if extract_tag_value(trimmed, "name").is_some() {
-                let indent: String = line.chars().take_while(|c| c.is_whitespace()).collect();
-                out.push(format!("{}<name>{}</name>", indent, font_name));
-                continue;
-            }
-            if extract_tag_value(trimmed, "size").is_some() {
-                let indent: String = line.chars().take_while(|c| c.is_whitespace()).collect();
-                let size = match font_place.as_deref() {
-                    Some("ActiveWindow") | Some("InactiveWindow") => active_size,
-                    Some("MenuHeader")
-                    | Some("MenuItem")
-                    | Some("ActiveOnScreenDisplay")
-                    | Some("InactiveOnScreenDisplay") => menu_size,
-                    _ => menu_size,
-                };
-                out.push(format!("{}<size>{}</size>", indent, size));
-                continue;
-            }
-        }
+/// Files written by `setup_lxqt_scaling` before non-root users were supported, relative to the home directory
+const MIGRATED_HOME_FILES: &[&str] = &[
+    ".Xresources",
+    ".config/lxqt/session.conf",
+    ".config/autostart/lxqt-powermanagement.desktop",
+];
 
-        out.push(line.to_string());
-    }
+pub fn user_home(fs_root: &Path, username: &str) -> PathBuf {
+    fs_root.join(home_dir(username).trim_start_matches('/'))
+}
 
-    let mut out = out.join("\n");
-    out.push('\n');
-    (out, theme_name)
+fn is_user_provisioned(fs_root: &Path, username: &str) -> bool {
+    let passwd = fs::read_to_string(fs_root.join("etc/passwd")).unwrap_or_default();
+    let has_account = passwd
+        .lines()
+        .any(|line| line.split(':').next() == Some(username));
+    has_account
+        && user_home(fs_root, username).is_dir()
+        && fs_root.join("etc/sudoers.d/localdesktop").exists()
 }
 
-fn update_openbox_theme(fs_root: &Path, theme_name: &str, scale: i32) {
-    let user_theme = fs_root.join(format!("root/.themes/{}/openbox-3/themerc", theme_name));
-    let system_theme = fs_root.join(format!("usr/share/themes/{}/openbox-3/themerc", theme_name));
-    let source = if user_theme.exists() {
-        user_theme.clone()
-    } else if system_theme.exists() {
-        system_theme
-    } else {
-        return;
-    };
+fn provision_user(options: &SetupOptions) -> StageOutput {
+    let fs_root = Path::new(ARCH_FS_ROOT);
+    let context = get_application_context();
+    let username = context.local_config.user.username;
 
-    let content = fs::read_to_string(&source).unwrap_or_default();
-    if content.is_empty() {
-        return;
+    if username == "root" || is_user_provisioned(fs_root, &username) {
+        return None;
     }
 
-    let button_size = 18 * scale;
-    let title_height = 22 * scale;
-    let mut lines = parse_kv_lines(&content, ':');
-    set_kv_value(&mut lines, "button.width", &button_size.to_string(), ':');
-    set_kv_value(&mut lines, "button.height", &button_size.to_string(), ':');
-    set_kv_value(&mut lines, "title.height", &title_height.to_string(), ':');
+    // Files created by the user should be owned by the Android app on the host, so reuse its UID
+    let uid = fs::metadata(&context.data_dir)
+        .map(|meta| meta.uid())
+        .unwrap_or(1000);
+    let mpsc_sender = options.mpsc_sender.clone();
 
-    let content = render_kv_lines(&lines);
-    let _ = fs::create_dir_all(
-        user_theme
-            .parent()
-            .expect("Failed to read openbox theme directory"),
-    );
-    fs::write(&user_theme, content).expect("Failed to write openbox theme file");
+    Some(thread::spawn(move || {
+        mpsc_sender
+            .send(SetupMessage::Progress(format!(
+                "Creating user {}...",
+                username
+            )))
+            .expect("Failed to send log message");
+
+        // Validated in `setup`, quoted all the same since these commands run as root
+        let home = quote(&home_dir(&username));
+        let user = quote(&username);
+        let run_as_root = |command: String| {
+            let output = ArchProcess {
+                command,
+                user: None,
+                log: None,
+            }
+            .run();
+            assert!(
+                output.status.success(),
+                "{}",
+                String::from_utf8_lossy(&output.stderr)
+            );
+        };
+
+        run_as_root("pacman -Q sudo || (rm -f /var/lib/pacman/db.lck && pacman -S --needed --noconfirm --noprogressbar sudo)".into());
+        for group in DEFAULT_USER_GROUPS {
+            run_as_root(format!("getent group {0} || groupadd {0}", quote(group)));
+        }
+        run_as_root(format!(
+            "getent group {0} || groupadd -g {1} {0} || groupadd {0}",
+            user, uid
+        ));
+        run_as_root(format!(
+            "id -u {0} || useradd -m -u {1} -g {0} -G {2} -s /bin/bash -d {3} {0} || useradd -m -g {0} -G {2} -s /bin/bash -d {3} {0}",
+            user,
+            uid,
+            quote(&DEFAULT_USER_GROUPS.join(",")),
+            home
+        ));
+        // `useradd -m` only copies the skeleton when it creates the home directory, so fill any gaps
+        run_as_root(format!("mkdir -p {0} && cp -rn /etc/skel/. {0}/", home));
+
+        let sudoers_dir = fs_root.join("etc/sudoers.d");
+        let _ = fs::create_dir_all(&sudoers_dir);
+        let sudoers_path = sudoers_dir.join("localdesktop");
+        fs::write(
+            &sudoers_path,
+            format!(
+                "# Auto generated by Local Desktop, do not edit manually\n%wheel ALL=(ALL:ALL) NOPASSWD: ALL\n{} ALL=(ALL:ALL) NOPASSWD: ALL\n",
+                username
+            ),
+        )
+        .expect("Failed to write sudoers file");
+        fs::set_permissions(&sudoers_path, fs::Permissions::from_mode(0o440))
+            .expect("Failed to set sudoers file permissions");
+
+        // Migrate the HiDPI settings that used to be written only for root
+        for file in MIGRATED_HOME_FILES {
+            run_as_root(format!(
+                "[ ! -e /root/{0} ] || [ -e {1}/{0} ] || (mkdir -p \"$(dirname {1}/{0})\" && cp -r /root/{0} {1}/{0})",
+                quote(file), home
+            ));
+        }
+        run_as_root(format!("chown -R {0}:{0} {1}", user, home));
+    }))
 }
 
 fn setup_lxqt_scaling(options: &SetupOptions) -> StageOutput {
@@ -671,11 +661,15 @@ fn setup_lxqt_scaling(options: &SetupOptions) -> StageOutput {
 
     let scale = ((density_dpi as f32) / 160.0 * 1.1).max(1.0).round() as i32;
     let xft_dpi = scale * 96;
+    let home = user_home(
+        fs_root,
+        &get_application_context().local_config.user.username,
+    );
 
-    let xresources_path = fs_root.join("root/.Xresources");
+    let xresources_path = home.join(".Xresources");
     upsert_kv_file(&xresources_path, ':', &[("Xft.dpi", xft_dpi.to_string())]);
 
-    let session_path = fs_root.join("root/.config/lxqt/session.conf");
+    let session_path = home.join(".config/lxqt/session.conf");
     let _ = fs::create_dir_all(
         session_path
             .parent()
@@ -691,16 +685,23 @@ fn setup_lxqt_scaling(options: &SetupOptions) -> StageOutput {
             ("QT_SCALE_FACTOR", scale.to_string()),
         ],
     );
-    let session_out = update_ini_section(
-        &session_with_env,
-        "General",
-        &[("window_manager", "openbox".to_string())],
-    );
+    // The compositor is the X window manager and draws the title bars, lxqt-session starts none while one
+    // runs. Drop the openbox set by earlier versions all the same, it is no longer installed.
+    let mut session_out = session_with_env
+        .lines()
+        .filter(|line| {
+            line.split_once('=').is_none_or(|(key, value)| {
+                key.trim() != "window_manager" || value.trim() != "openbox"
+            })
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    session_out.push('\n');
     fs::write(&session_path, session_out).expect("Failed to write session.conf");
 
     // lxqt-powermanagement frequently crashes in a PRoot container due to missing
     // host power-management interfaces. Disable its autostart by default.
-    let autostart_dir = fs_root.join("root/.config/autostart");
+    let autostart_dir = home.join(".config/autostart");
     let _ = fs::create_dir_all(&autostart_dir);
     let powermanagement_override = autostart_dir.join("lxqt-powermanagement.desktop");
     let powermanagement_hidden = r#"[Desktop Entry]
@@ -711,31 +712,6 @@ Hidden=true
     fs::write(&powermanagement_override, powermanagement_hidden)
         .expect("Failed to disable lxqt-powermanagement autostart");
 
-    let openbox_user_rc = fs_root.join("root/.config/openbox/rc.xml");
-    let openbox_system_rc = fs_root.join("etc/xdg/openbox/rc.xml");
-    let openbox_source = if openbox_user_rc.exists() {
-        openbox_user_rc.clone()
-    } else if openbox_system_rc.exists() {
-        openbox_system_rc
-    } else {
-        return None;
-    };
-
-    let rc_content = fs::read_to_string(&openbox_source).unwrap_or_default();
-    if !rc_content.is_empty() {
-        let (rc_out, theme_name) = update_openbox_rc(&rc_content, scale, "DejaVu Sans");
-        let _ = fs::create_dir_all(
-            openbox_user_rc
-                .parent()
-                .expect("Failed to read openbox config directory"),
-        );
-        fs::write(&openbox_user_rc, rc_out).expect("Failed to write openbox rc.xml");
-
-        if let Some(theme_name) = theme_name {
-            update_openbox_theme(fs_root, &theme_name, scale);
-        }
-    }
-
     None
 }
 
@@ -801,6 +777,18 @@ pub fn setup(android_app: AndroidApp) -> PolarBearBackend {
         });
     }
 
+    // The username ends up in the commands of the setup, which run as root
+    let username = get_application_context().local_config.user.username;
+    if !is_valid_username(&username) {
+        sender
+            .send(SetupMessage::Error(format!(
+                "Invalid username {:?} in {}: use lowercase letters, digits, - and _, starting with a letter or _",
+                username, CONFIG_FILE
+            )))
+            .unwrap_or(());
+        return PolarBearBackend::WebView(WebviewBackend::build(receiver, progress));
+    }
+
     let options = SetupOptions {
         android_app,
         mpsc_sender: sender.clone(),
@@ -812,8 +800,9 @@ pub fn setup(android_app: AndroidApp) -> PolarBearBackend {
         Box::new(install_dependencies),         // Step 3. Install dependencies
         Box::new(setup_firefox_config),         // Step 4. Setup Firefox config
         Box::new(setup_qterminal_wrapper), // Step 5. Ensure qterminal launches interactive bash
-        Box::new(setup_lxqt_scaling),      // Step 6. Setup LXQt HiDPI scaling
-        Box::new(fix_xkb_symlink),         // Step 7. Fix xkb symlink (last)
+        Box::new(provision_user),          // Step 6. Create the configured non-root user
+        Box::new(setup_lxqt_scaling),      // Step 7. Setup LXQt HiDPI scaling
+        Box::new(fix_xkb_symlink),         // Step 8. Fix xkb symlink (last)
     ];
 
     let handle_stage_error = |e: Box<dyn std::any::Any + Send>, sender: &Sender<SetupMessage>| {
@@ -889,6 +878,16 @@ pub fn setup(android_app: AndroidApp) -> PolarBearBackend {
             clock: Clock::new(),
             key_counter: 0,
             scale_factor: 1.0,
+            cursor_theme: CursorTheme::default(),
+            damage_tracker: None,
+            external_displays: BTreeMap::new(),
+            output_arrangement: Default::default(),
+            soft_keyboard: None,
+            pointer_captured: false,
+            keyboard_captured: false,
+            task_manager_requested: false,
+            // Until the first check of the idle inhibitors, as set by `keep_screen_on` on startup
+            screen_kept_on: true,
         })
     } else {
         PolarBearBackend::WebView(WebviewBackend::build(receiver, progress))
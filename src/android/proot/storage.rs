@@ -0,0 +1,202 @@
+use super::setup::user_home;
+use crate::android::utils::ndk::run_in_jvm;
+use crate::core::config::ARCH_FS_ROOT;
+use crate::core::media::media_link_names;
+use jni::objects::{JObject, JString};
+use jni::JNIEnv;
+use std::collections::BTreeSet;
+use std::fs;
+use std::os::unix::fs::symlink;
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+use winit::platform::android::activity::AndroidApp;
+
+/// Where the Android `/storage` directory is bound inside the container.
+/// Binds are resolved on every access, so volumes attached later show up here without restarting PRoot.
+pub const STORAGE_MOUNT: &str = "/mnt/android-storage";
+
+/// Where `/sdcard` is bound inside the container
+pub const SDCARD_MOUNT: &str = "/android";
+
+/// XDG user dirs and the shared storage folders they point at
+const XDG_USER_DIRS: &[(&str, &str)] = &[
+    ("XDG_DOCUMENTS_DIR", "Documents"),
+    ("XDG_DOWNLOAD_DIR", "Download"),
+    ("XDG_PICTURES_DIR", "Pictures"),
+    ("XDG_MUSIC_DIR", "Music"),
+    ("XDG_VIDEOS_DIR", "Movies"),
+];
+
+/// First line of the generated `user-dirs.dirs`. Without it the file was edited by the user and is left alone.
+const USER_DIRS_HEADER: &str =
+    "# Generated by Local Desktop, pointing at the Android shared storage";
+
+const POLL_INTERVAL: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Clone, PartialEq)]
+struct StorageVolume {
+    /// Directory name under `/storage`, e.g. `1234-ABCD`
+    uuid: String,
+    /// Human readable name, e.g. `SanDisk SD card`
+    label: String,
+}
+
+/// Point the XDG user dirs of `username` at the matching folders of the Android shared storage,
+/// unless the user has edited them
+pub fn write_user_dirs(username: &str) {
+    let config_dir = user_home(Path::new(ARCH_FS_ROOT), username).join(".config");
+    if let Err(e) = fs::create_dir_all(&config_dir) {
+        log::info!("Failed to create {}: {}", config_dir.display(), e);
+        return;
+    }
+
+    let path = config_dir.join("user-dirs.dirs");
+    if fs::read_to_string(&path).is_ok_and(|existing| !existing.starts_with(USER_DIRS_HEADER)) {
+        return;
+    }
+
+    let mut content = format!("{}\n", USER_DIRS_HEADER);
+    for (key, folder) in XDG_USER_DIRS {
+        content.push_str(&format!("{}=\"{}/{}\"\n", key, SDCARD_MOUNT, folder));
+    }
+
+    if let Err(e) = fs::write(&path, content) {
+        log::info!("Failed to write {}: {}", path.display(), e);
+    }
+}
+
+/// Names of the removable volumes currently attached, as listed under `/storage`
+fn attached_volumes() -> BTreeSet<String> {
+    fs::read_dir("/storage")
+        .map(|entries| {
+            entries
+                .filter_map(Result::ok)
+                .map(|entry| entry.file_name().to_string_lossy().to_string())
+                .filter(|name| name != "emulated" && name != "self")
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn get_string(env: &mut JNIEnv, object: JObject) -> Option<String> {
+    if object.is_null() {
+        return None;
+    }
+    env.get_string(&JString::from(object)).ok().map(Into::into)
+}
+
+/// Query `StorageManager` for the mounted removable volumes and their user visible labels
+fn removable_volumes(env: &mut JNIEnv, android_app: &AndroidApp) -> Vec<StorageVolume> {
+    let activity = unsafe { JObject::from_raw(android_app.activity_as_ptr() as *mut _) };
+    let mut volumes = Vec::new();
+
+    let result = (|| -> jni::errors::Result<()> {
+        let service_name = env.new_string("storage")?;
+        let storage_manager = env
+            .call_method(
+                &activity,
+                "getSystemService",
+                "(Ljava/lang/String;)Ljava/lang/Object;",
+                &[(&service_name).into()],
+            )?
+            .l()?;
+        let list = env
+            .call_method(
+                &storage_manager,
+                "getStorageVolumes",
+                "()Ljava/util/List;",
+                &[],
+            )?
+            .l()?;
+        let size = env.call_method(&list, "size", "()I", &[])?.i()?;
+
+        for i in 0..size {
+            let volume = env
+                .call_method(&list, "get", "(I)Ljava/lang/Object;", &[i.into()])?
+                .l()?;
+            let removable = env.call_method(&volume, "isRemovable", "()Z", &[])?.z()?;
+            let state = env
+                .call_method(&volume, "getState", "()Ljava/lang/String;", &[])?
+                .l()?;
+            let uuid = env
+                .call_method(&volume, "getUuid", "()Ljava/lang/String;", &[])?
+                .l()?;
+            let description = env
+                .call_method(
+                    &volume,
+                    "getDescription",
+                    "(Landroid/content/Context;)Ljava/lang/String;",
+                    &[(&activity).into()],
+                )?
+                .l()?;
+
+            let state = get_string(env, state);
+            let uuid = get_string(env, uuid);
+            let description = get_string(env, description);
+            if let (true, Some("mounted"), Some(uuid)) = (removable, state.as_deref(), uuid) {
+                volumes.push(StorageVolume {
+                    label: description.unwrap_or_else(|| uuid.clone()),
+                    uuid,
+                });
+            }
+        }
+        Ok(())
+    })();
+
+    if let Err(e) = result {
+        log::info!("Failed to query storage volumes: {}", e);
+        let _ = env.exception_clear();
+    }
+
+    volumes
+}
+
+/// Replace the `/media/<label>` links with links to the given volumes
+fn refresh_media_links(volumes: &[StorageVolume]) {
+    let media_dir = Path::new(ARCH_FS_ROOT).join("media");
+    if let Err(e) = fs::create_dir_all(&media_dir) {
+        log::info!("Failed to create {}: {}", media_dir.display(), e);
+        return;
+    }
+
+    // Only remove the links we created, anything else in /media belongs to the user
+    for entry in fs::read_dir(&media_dir).into_iter().flatten().flatten() {
+        let path = entry.path();
+        let is_ours = fs::read_link(&path)
+            .map(|target| target.starts_with(STORAGE_MOUNT))
+            .unwrap_or(false);
+        if is_ours {
+            let _ = fs::remove_file(&path);
+        }
+    }
+
+    let names = volumes
+        .iter()
+        .map(|volume| (volume.uuid.as_str(), volume.label.as_str()))
+        .collect::<Vec<_>>();
+    for (volume, label) in volumes.iter().zip(media_link_names(&names)) {
+        let link = media_dir.join(&label);
+        let target = format!("{}/{}", STORAGE_MOUNT, volume.uuid);
+        match symlink(&target, &link) {
+            Ok(()) => log::info!("Mounted storage volume {} at /media/{}", volume.uuid, label),
+            Err(e) => log::info!("Failed to link {} to {}: {}", link.display(), target, e),
+        }
+    }
+}
+
+/// Keep `/media` in sync with the removable volumes (SD cards, USB OTG drives) attached to the device
+pub fn watch_volumes(android_app: AndroidApp) {
+    thread::spawn(move || {
+        let mut known = None;
+        loop {
+            let attached = attached_volumes();
+            if known.as_ref() != Some(&attached) {
+                let volumes = run_in_jvm(removable_volumes, android_app.clone());
+                refresh_media_links(&volumes);
+                known = Some(attached);
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    });
+}
@@ -0,0 +1,348 @@
+use super::{
+    process::ArchProcess,
+    services::{self, service_statuses},
+};
+use crate::{
+    android::utils::{
+        application_context::get_application_context,
+        ndk::{load_app_class, run_in_jvm},
+    },
+    core::procfs::{memory_info, process_tree, ProcessInfo},
+};
+use jni::objects::{JObject, JValue};
+use jni::JNIEnv;
+use serde_json::json;
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    path::Path,
+    process::Command,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        OnceLock,
+    },
+    thread,
+    time::Duration,
+};
+use winit::platform::android::activity::AndroidApp;
+
+const PAGE: &str = include_str!("../../../assets/task-manager.html");
+
+/// Shows the page over the desktop, implemented in `kotlin/TaskManager.kt`
+const TASK_MANAGER_CLASS: &str = "app.polarbear.TaskManager";
+
+/// Header the page sends `TOKEN` in. Being a custom header, browsers also refuse to send it cross-origin.
+const TOKEN_HEADER: &str = "x-task-manager-token";
+
+/// Random for every run of the app, handed to the overlay in the URL fragment and required by every `/api/` request,
+/// so that other pages and apps can neither read the command lines of the processes nor send signals
+static TOKEN: OnceLock<String> = OnceLock::new();
+
+/// Signals the page may send, anything else is rejected
+const ALLOWED_SIGNALS: &[&str] = &["TERM", "KILL", "INT", "HUP", "STOP", "CONT"];
+
+/// How long a connection may take to send its request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+const MEMORY_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Low memory killer threshold reported by `ActivityManager`, in KiB. 0 until first queried.
+static LOW_MEMORY_THRESHOLD_KB: AtomicU64 = AtomicU64::new(0);
+
+/// Every process in the container, i.e. the PRoot sessions and their descendants
+pub fn container_processes() -> Vec<ProcessInfo> {
+    process_tree(Path::new("/proc"), &ArchProcess::running_sessions())
+}
+
+/// Send `signal` (e.g. `TERM`) to a process of the container.
+/// PIDs outside of the container are refused so the page cannot signal the app itself.
+pub fn send_signal(pid: u32, signal: &str) -> Result<(), String> {
+    if !ALLOWED_SIGNALS.contains(&signal) {
+        return Err(format!("Unsupported signal {}", signal));
+    }
+    if !container_processes()
+        .iter()
+        .any(|process| process.pid == pid)
+    {
+        return Err(format!("Process {} is not part of the container", pid));
+    }
+
+    let status = Command::new("kill")
+        .arg(format!("-{}", signal))
+        .arg(pid.to_string())
+        .status()
+        .map_err(|e| e.to_string())?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("kill exited with {}", status))
+    }
+}
+
+fn respond(stream: &mut TcpStream, status: &str, content_type: &str, body: &str) {
+    let _ = write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    );
+}
+
+fn query_param<'a>(query: &'a str, name: &str) -> Option<&'a str> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value)
+}
+
+/// 128 random bits, hex encoded
+fn random_token() -> std::io::Result<String> {
+    let mut bytes = [0u8; 16];
+    File::open("/dev/urandom")?.read_exact(&mut bytes)?;
+    Ok(bytes.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+/// `value` of a query parameter with its `%XX` escapes decoded, as sent by `encodeURIComponent`
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes
+            .get(i + 1..i + 3)
+            .filter(|_| bytes[i] == b'%')
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Whether `host` is how the app itself reaches the server, rather than a name some page rebound to 127.0.0.1
+fn is_local_host(host: &str, port: u16) -> bool {
+    host == format!("127.0.0.1:{}", port) || host == format!("localhost:{}", port)
+}
+
+fn handle_request(mut stream: TcpStream, token: &str, port: u16) {
+    if stream.set_read_timeout(Some(REQUEST_TIMEOUT)).is_err() {
+        return;
+    }
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    if reader.read_line(&mut request_line).is_err() {
+        return;
+    }
+
+    let mut authorized = false;
+    let mut local_host = false;
+    let mut header = String::new();
+    while reader.read_line(&mut header).is_ok_and(|read| read > 0) && header.trim() != "" {
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case(TOKEN_HEADER) {
+                authorized = value.trim() == token;
+            } else if name.trim().eq_ignore_ascii_case("host") {
+                local_host = is_local_host(value.trim(), port);
+            }
+        }
+        header.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (method, target) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+
+    if !local_host || (path.starts_with("/api/") && !authorized) {
+        respond(&mut stream, "403 Forbidden", "text/plain", "Forbidden");
+        return;
+    }
+
+    match (method, path) {
+        ("GET", "/") => respond(
+            &mut stream,
+            "200 OK",
+            "text/html; charset=utf-8",
+            PAGE,
+        ),
+        ("GET", "/api/processes") => {
+            let body = json!({
+                "processes": container_processes(),
+                "services": service_statuses(),
+                "memory": memory_info(Path::new("/proc")),
+                "threshold_kb": LOW_MEMORY_THRESHOLD_KB.load(Ordering::Relaxed),
+                "margin_kb": get_application_context().local_config.task_manager.low_memory_margin_mb * 1024,
+            });
+            respond(&mut stream, "200 OK", "application/json", &body.to_string());
+        }
+        ("POST", "/api/signal") => {
+            let pid = query_param(query, "pid").and_then(|pid| pid.parse().ok());
+            let signal = query_param(query, "signal").unwrap_or("TERM");
+            match pid.map(|pid| send_signal(pid, signal)) {
+                Some(Ok(())) => respond(&mut stream, "200 OK", "text/plain", "OK"),
+                Some(Err(e)) => respond(&mut stream, "400 Bad Request", "text/plain", &e),
+                None => respond(&mut stream, "400 Bad Request", "text/plain", "Missing pid"),
+            }
+        }
+        ("POST", "/api/services/start") => match query_param(query, "name").map(percent_decode) {
+            Some(name) => match services::start(&name) {
+                Ok(()) => respond(&mut stream, "200 OK", "text/plain", "OK"),
+                Err(e) => respond(&mut stream, "400 Bad Request", "text/plain", &e),
+            },
+            None => respond(&mut stream, "400 Bad Request", "text/plain", "Missing name"),
+        },
+        _ => respond(&mut stream, "404 Not Found", "text/plain", "Not found"),
+    }
+}
+
+/// Serve the task manager page on `http://127.0.0.1:<port>`.
+/// Only the overlay knows the token the API requires, the page alone is served to anyone.
+pub fn serve() {
+    let port = get_application_context().local_config.task_manager.port;
+    let token = match random_token() {
+        Ok(token) => TOKEN.get_or_init(|| token),
+        Err(e) => {
+            log::info!("Failed to generate the task manager token: {}", e);
+            return;
+        }
+    };
+    let listener = match TcpListener::bind(("127.0.0.1", port)) {
+        Ok(listener) => listener,
+        Err(e) => {
+            log::info!("Failed to start the task manager on port {}: {}", port, e);
+            return;
+        }
+    };
+    log::info!("Task manager is available at http://127.0.0.1:{}", port);
+
+    thread::spawn(move || {
+        for stream in listener.incoming().filter_map(Result::ok) {
+            thread::spawn(move || handle_request(stream, token, port));
+        }
+    });
+}
+
+/// Call `method` of `TaskManager` with the activity and `argument`
+fn call_task_manager(env: &mut JNIEnv, android_app: &AndroidApp, method: &str, argument: &str) {
+    let activity = unsafe { JObject::from_raw(android_app.activity_as_ptr() as *mut _) };
+    let result = load_app_class(env, &activity, TASK_MANAGER_CLASS).and_then(|class| {
+        let argument = env.new_string(argument)?;
+        env.call_static_method(
+            &class,
+            method,
+            "(Landroid/app/Activity;Ljava/lang/String;)V",
+            &[JValue::Object(&activity), (&argument).into()],
+        )
+        .map(|_| ())
+    });
+
+    if let Err(e) = result {
+        log::info!("Failed to {} the task manager: {:?}", method, e);
+        let _ = env.exception_clear();
+    }
+}
+
+/// Show the task manager over the desktop, once it is served
+pub fn show(android_app: AndroidApp) {
+    let Some(token) = TOKEN.get() else {
+        return;
+    };
+    let port = get_application_context().local_config.task_manager.port;
+    // The fragment is never sent to the server, so the token only reaches the page
+    let url = format!("http://127.0.0.1:{}/#token={}", port, token);
+    run_in_jvm(
+        move |env, app| call_task_manager(env, app, "show", &url),
+        android_app,
+    );
+}
+
+/// `(availMem, threshold)` in bytes, from `ActivityManager.getMemoryInfo`
+fn query_memory(env: &mut JNIEnv, android_app: &AndroidApp) -> Option<(i64, i64)> {
+    let activity = unsafe { JObject::from_raw(android_app.activity_as_ptr() as *mut _) };
+    let result = (|| -> jni::errors::Result<(i64, i64)> {
+        let service_name = env.new_string("activity")?;
+        let activity_manager = env
+            .call_method(
+                &activity,
+                "getSystemService",
+                "(Ljava/lang/String;)Ljava/lang/Object;",
+                &[(&service_name).into()],
+            )?
+            .l()?;
+        let memory_info = env.new_object("android/app/ActivityManager$MemoryInfo", "()V", &[])?;
+        env.call_method(
+            &activity_manager,
+            "getMemoryInfo",
+            "(Landroid/app/ActivityManager$MemoryInfo;)V",
+            &[(&memory_info).into()],
+        )?;
+        let available = env.get_field(&memory_info, "availMem", "J")?.j()?;
+        let threshold = env.get_field(&memory_info, "threshold", "J")?.j()?;
+        Ok((available, threshold))
+    })();
+
+    result
+        .map_err(|e| {
+            log::info!("Failed to query memory info: {}", e);
+            let _ = env.exception_clear();
+        })
+        .ok()
+}
+
+/// Warn the user once the available memory gets close to the point where Android starts killing apps
+pub fn watch_memory(android_app: AndroidApp) {
+    let margin = get_application_context()
+        .local_config
+        .task_manager
+        .low_memory_margin_mb as i64
+        * 1024
+        * 1024;
+
+    thread::spawn(move || {
+        let mut warned = false;
+        loop {
+            if let Some((available, threshold)) = run_in_jvm(query_memory, android_app.clone()) {
+                LOW_MEMORY_THRESHOLD_KB.store(threshold as u64 / 1024, Ordering::Relaxed);
+
+                if !warned && available < threshold + margin {
+                    warned = true;
+                    let largest = container_processes()
+                        .into_iter()
+                        .max_by_key(|process| process.rss_kb);
+                    let mut message = format!(
+                        "Low memory: Android may close Local Desktop soon ({} MB left).",
+                        (available - threshold).max(0) / 1024 / 1024
+                    );
+                    if let Some(process) = largest {
+                        let name = process.command.split(' ').next().unwrap_or_default();
+                        message.push_str(&format!(
+                            " Largest process: {} ({} MB).",
+                            name,
+                            process.rss_kb / 1024
+                        ));
+                    }
+                    log::info!("{}", message);
+                    message.push_str(" Press Ctrl+Shift+Esc to open the task manager.");
+                    run_in_jvm(
+                        move |env, app| call_task_manager(env, app, "warn", &message),
+                        android_app.clone(),
+                    );
+                } else if warned && available > threshold + 2 * margin {
+                    // Memory was freed, warn again next time it runs low
+                    warned = false;
+                }
+            }
+            thread::sleep(MEMORY_POLL_INTERVAL);
+        }
+    });
+}
@@ -0,0 +1,326 @@
+use crate::{
+    android::utils::{application_context::get_application_context, ndk::run_in_jvm},
+    core::clipboard::{self, ClipContent},
+};
+use jni::{
+    objects::{JObject, JString, JValue},
+    JNIEnv,
+};
+use std::{thread, time::Duration};
+use winit::platform::android::activity::AndroidApp;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Shown by Android next to clips copied from the desktop
+const CLIP_LABEL: &str = "Local Desktop";
+
+/// `ClipDescription.getTimestamp` is only available from Android 8
+const TIMESTAMP_SDK: i32 = 26;
+
+const READ_CHUNK: i32 = 64 * 1024;
+
+/// What the Android clipboard holds. Images are only read once they changed.
+#[derive(Debug, Clone, PartialEq)]
+enum AndroidClip {
+    Text(String),
+    Image { uri: String, mime_type: String },
+}
+
+/// Keep the Android clipboard and the desktop one in sync, both ways
+pub fn watch_clipboard(android_app: AndroidApp) {
+    let limit = get_application_context().local_config.clipboard.max_size_kb * 1024;
+
+    thread::spawn(move || {
+        let has_timestamp = run_in_jvm(sdk_version, android_app.clone()) >= TIMESTAMP_SDK;
+        // What was last synced, so that a clip is not sent back to where it came from
+        let mut last = None;
+        let mut last_timestamp = None;
+        loop {
+            if let Some(ClipContent::Text(text)) = clipboard::take_for_android() {
+                let text_clone = text.clone();
+                if run_in_jvm(
+                    move |env, app| set_clip(env, app, &text_clone),
+                    android_app.clone(),
+                ) {
+                    last = Some(AndroidClip::Text(text));
+                }
+            }
+
+            // Android 12 tells the user every time the clip is read, so only read it once it changed
+            let timestamp = if has_timestamp {
+                run_in_jvm(clip_timestamp, android_app.clone())
+            } else {
+                None
+            };
+            if timestamp.is_none() || timestamp != last_timestamp {
+                last_timestamp = timestamp;
+                let clip = run_in_jvm(read_clip, android_app.clone());
+                if clip.is_some() && clip != last {
+                    if let Some(content) = import_clip(clip.clone(), limit, &android_app) {
+                        clipboard::send_to_desktop(content);
+                    }
+                    last = clip;
+                }
+            }
+
+            thread::sleep(POLL_INTERVAL);
+        }
+    });
+}
+
+fn import_clip(
+    clip: Option<AndroidClip>,
+    limit: usize,
+    android_app: &AndroidApp,
+) -> Option<ClipContent> {
+    match clip? {
+        AndroidClip::Text(text) if text.len() > limit => {
+            log::info!("Not copying to the desktop, over {} bytes", limit);
+            None
+        }
+        AndroidClip::Text(text) => Some(ClipContent::Text(text)),
+        AndroidClip::Image { uri, mime_type } => {
+            let data = run_in_jvm(
+                move |env, app| read_image(env, app, &uri, limit),
+                android_app.clone(),
+            )?;
+            Some(ClipContent::Image { mime_type, data })
+        }
+    }
+}
+
+fn sdk_version(env: &mut JNIEnv, _android_app: &AndroidApp) -> i32 {
+    env.get_static_field("android/os/Build$VERSION", "SDK_INT", "I")
+        .and_then(|it| it.i())
+        .unwrap_or_else(|e| {
+            log::info!("Failed to get the SDK version: {}", e);
+            let _ = env.exception_clear();
+            0
+        })
+}
+
+fn clipboard_manager<'local>(
+    env: &mut JNIEnv<'local>,
+    activity: &JObject,
+) -> jni::errors::Result<JObject<'local>> {
+    let service_name = env.new_string("clipboard")?;
+    env.call_method(
+        activity,
+        "getSystemService",
+        "(Ljava/lang/String;)Ljava/lang/Object;",
+        &[(&service_name).into()],
+    )?
+    .l()
+}
+
+fn content_resolver<'local>(
+    env: &mut JNIEnv<'local>,
+    activity: &JObject,
+) -> jni::errors::Result<JObject<'local>> {
+    env.call_method(
+        activity,
+        "getContentResolver",
+        "()Landroid/content/ContentResolver;",
+        &[],
+    )?
+    .l()
+}
+
+fn to_string(env: &mut JNIEnv, object: &JObject) -> jni::errors::Result<String> {
+    let string = env
+        .call_method(object, "toString", "()Ljava/lang/String;", &[])?
+        .l()?;
+    Ok(env.get_string(&JString::from(string))?.into())
+}
+
+/// When the current clip was copied, without reading it
+fn clip_timestamp(env: &mut JNIEnv, android_app: &AndroidApp) -> Option<i64> {
+    let activity = unsafe { JObject::from_raw(android_app.activity_as_ptr() as *mut _) };
+    let result = (|| -> jni::errors::Result<Option<i64>> {
+        let manager = clipboard_manager(env, &activity)?;
+        let description = env
+            .call_method(
+                &manager,
+                "getPrimaryClipDescription",
+                "()Landroid/content/ClipDescription;",
+                &[],
+            )?
+            .l()?;
+        if description.is_null() {
+            return Ok(None);
+        }
+        let timestamp = env
+            .call_method(&description, "getTimestamp", "()J", &[])?
+            .j()?;
+        Ok(Some(timestamp))
+    })();
+
+    result
+        .map_err(|e| {
+            log::info!("Failed to get the clipboard timestamp: {}", e);
+            let _ = env.exception_clear();
+        })
+        .ok()
+        .flatten()
+}
+
+fn read_clip(env: &mut JNIEnv, android_app: &AndroidApp) -> Option<AndroidClip> {
+    let activity = unsafe { JObject::from_raw(android_app.activity_as_ptr() as *mut _) };
+    let result = (|| -> jni::errors::Result<Option<AndroidClip>> {
+        let manager = clipboard_manager(env, &activity)?;
+        // Null when the app is in the background, from Android 10
+        let clip = env
+            .call_method(
+                &manager,
+                "getPrimaryClip",
+                "()Landroid/content/ClipData;",
+                &[],
+            )?
+            .l()?;
+        if clip.is_null() || env.call_method(&clip, "getItemCount", "()I", &[])?.i()? == 0 {
+            return Ok(None);
+        }
+        let item = env
+            .call_method(
+                &clip,
+                "getItemAt",
+                "(I)Landroid/content/ClipData$Item;",
+                &[JValue::Int(0)],
+            )?
+            .l()?;
+
+        let uri = env
+            .call_method(&item, "getUri", "()Landroid/net/Uri;", &[])?
+            .l()?;
+        if !uri.is_null() {
+            let resolver = content_resolver(env, &activity)?;
+            let mime_type = env
+                .call_method(
+                    &resolver,
+                    "getType",
+                    "(Landroid/net/Uri;)Ljava/lang/String;",
+                    &[(&uri).into()],
+                )?
+                .l()?;
+            if !mime_type.is_null() {
+                let mime_type: String = env.get_string(&JString::from(mime_type))?.into();
+                if mime_type.starts_with("image/") {
+                    let uri = to_string(env, &uri)?;
+                    return Ok(Some(AndroidClip::Image { uri, mime_type }));
+                }
+            }
+        }
+
+        // Anything else is pasted as text, links and intents included
+        let text = env
+            .call_method(
+                &item,
+                "coerceToText",
+                "(Landroid/content/Context;)Ljava/lang/CharSequence;",
+                &[(&activity).into()],
+            )?
+            .l()?;
+        Ok(Some(AndroidClip::Text(to_string(env, &text)?)))
+    })();
+
+    result
+        .map_err(|e| {
+            log::info!("Failed to read the Android clipboard: {}", e);
+            let _ = env.exception_clear();
+        })
+        .ok()
+        .flatten()
+}
+
+/// The content of an image clip, or `None` if it is over `limit` bytes
+fn read_image(
+    env: &mut JNIEnv,
+    android_app: &AndroidApp,
+    uri: &str,
+    limit: usize,
+) -> Option<Vec<u8>> {
+    let activity = unsafe { JObject::from_raw(android_app.activity_as_ptr() as *mut _) };
+    let result = (|| -> jni::errors::Result<Option<Vec<u8>>> {
+        let uri = env.new_string(uri)?;
+        let uri = env
+            .call_static_method(
+                "android/net/Uri",
+                "parse",
+                "(Ljava/lang/String;)Landroid/net/Uri;",
+                &[(&uri).into()],
+            )?
+            .l()?;
+        let resolver = content_resolver(env, &activity)?;
+        let stream = env
+            .call_method(
+                &resolver,
+                "openInputStream",
+                "(Landroid/net/Uri;)Ljava/io/InputStream;",
+                &[(&uri).into()],
+            )?
+            .l()?;
+
+        let buffer = env.new_byte_array(READ_CHUNK)?;
+        let mut data = Vec::new();
+        let complete = loop {
+            let read = env
+                .call_method(&stream, "read", "([B)I", &[(&buffer).into()])?
+                .i()?;
+            if read < 0 {
+                break true;
+            }
+            if data.len() + read as usize > limit {
+                break false;
+            }
+            let chunk = env.convert_byte_array(&buffer)?;
+            data.extend_from_slice(&chunk[..read as usize]);
+        };
+        env.call_method(&stream, "close", "()V", &[])?;
+
+        if !complete {
+            log::info!("Not copying the image to the desktop, over {} bytes", limit);
+            return Ok(None);
+        }
+        Ok(Some(data))
+    })();
+
+    result
+        .map_err(|e| {
+            log::info!("Failed to read the clipboard image: {}", e);
+            let _ = env.exception_clear();
+        })
+        .ok()
+        .flatten()
+}
+
+/// Put text copied on the desktop in the Android clipboard, returns whether it worked
+fn set_clip(env: &mut JNIEnv, android_app: &AndroidApp, text: &str) -> bool {
+    let activity = unsafe { JObject::from_raw(android_app.activity_as_ptr() as *mut _) };
+    let result = (|| -> jni::errors::Result<()> {
+        let manager = clipboard_manager(env, &activity)?;
+        let label = env.new_string(CLIP_LABEL)?;
+        let text = env.new_string(text)?;
+        let clip = env
+            .call_static_method(
+                "android/content/ClipData",
+                "newPlainText",
+                "(Ljava/lang/CharSequence;Ljava/lang/CharSequence;)Landroid/content/ClipData;",
+                &[(&label).into(), (&text).into()],
+            )?
+            .l()?;
+        env.call_method(
+            &manager,
+            "setPrimaryClip",
+            "(Landroid/content/ClipData;)V",
+            &[(&clip).into()],
+        )?;
+        Ok(())
+    })();
+
+    result
+        .map_err(|e| {
+            log::info!("Failed to write the Android clipboard: {}", e);
+            let _ = env.exception_clear();
+        })
+        .is_ok()
+}
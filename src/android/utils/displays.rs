@@ -0,0 +1,267 @@
+use crate::android::{backend::wayland::NativeWindow, utils::ndk::load_app_class};
+use crate::core::output_layout::{pixels_to_mm, refresh_mhz, refresh_rates};
+use jni::objects::{JObject, JObjectArray, JString, JValue};
+use jni::sys::{_jobject, jint, jobject};
+use jni::JNIEnv;
+use std::{ffi::c_void, ptr::NonNull, sync::Mutex};
+use winit::platform::android::activity::AndroidApp;
+
+/// Shows the desktop on external displays, implemented in `kotlin/ExternalDisplays.kt`
+const EXTERNAL_DISPLAYS_CLASS: &str = "app.polarbear.ExternalDisplays";
+
+#[link(name = "android")]
+extern "C" {
+    fn ANativeWindow_fromSurface(env: *mut jni::sys::JNIEnv, surface: jobject) -> *mut c_void;
+}
+
+/// What Android tells about a display
+#[derive(Debug, Clone)]
+pub struct DisplayInfo {
+    pub name: String,
+    pub refresh_mhz: i32,
+    /// Every refresh rate the display supports at its current resolution, in mHz
+    pub refresh_rates: Vec<i32>,
+    /// Width and height in millimetres, 0 when unknown
+    pub physical_size: (i32, i32),
+}
+
+pub enum DisplayEvent {
+    /// An external display got a surface to draw into, or its surface was resized
+    SurfaceChanged {
+        display_id: i32,
+        window: NativeWindow,
+        size: (i32, i32),
+    },
+    /// The surface of an external display is gone, e.g. the display was unplugged
+    SurfaceDestroyed { display_id: i32 },
+    /// A display changed its refresh rate, resolution or rotation, the built-in one included
+    DisplayChanged { display_id: i32 },
+}
+
+/// Events of the UI thread, waiting for the main loop
+static EVENTS: Mutex<Vec<DisplayEvent>> = Mutex::new(Vec::new());
+
+/// Wakes the main loop up to handle the events
+static WAKE: Mutex<Option<Box<dyn Fn() + Send>>> = Mutex::new(None);
+
+fn push_event(event: DisplayEvent) {
+    EVENTS.lock().unwrap().push(event);
+    if let Some(wake) = WAKE.lock().unwrap().as_ref() {
+        wake();
+    }
+}
+
+/// The external displays that changed since the last call, in order
+pub fn take_display_events() -> Vec<DisplayEvent> {
+    std::mem::take(&mut *EVENTS.lock().unwrap())
+}
+
+/// Call `wake` whenever there are new events for `take_display_events`
+pub fn on_display_events(wake: impl Fn() + Send + 'static) {
+    *WAKE.lock().unwrap() = Some(Box::new(wake));
+}
+
+fn display_info(env: &mut JNIEnv, display: &JObject) -> jni::errors::Result<DisplayInfo> {
+    let name = env
+        .call_method(display, "getName", "()Ljava/lang/String;", &[])?
+        .l()?;
+    let name: String = env.get_string(&JString::from(name))?.into();
+    let refresh = env
+        .call_method(display, "getRefreshRate", "()F", &[])?
+        .f()?;
+
+    let metrics = env.new_object("android/util/DisplayMetrics", "()V", &[])?;
+    env.call_method(
+        display,
+        "getRealMetrics",
+        "(Landroid/util/DisplayMetrics;)V",
+        &[JValue::Object(&metrics)],
+    )?;
+    let width = env.get_field(&metrics, "widthPixels", "I")?.i()?;
+    let height = env.get_field(&metrics, "heightPixels", "I")?.i()?;
+    let xdpi = env.get_field(&metrics, "xdpi", "F")?.f()?;
+    let ydpi = env.get_field(&metrics, "ydpi", "F")?.f()?;
+
+    let refresh_mhz = refresh_mhz(refresh);
+    let modes = supported_modes(env, display).unwrap_or_else(|e| {
+        log::info!("Failed to list the display modes: {:?}", e);
+        let _ = env.exception_clear();
+        Vec::new()
+    });
+    let mut refresh_rates = refresh_rates(&modes, (width, height));
+    if !refresh_rates.contains(&refresh_mhz) {
+        refresh_rates.push(refresh_mhz);
+    }
+
+    Ok(DisplayInfo {
+        name,
+        refresh_mhz,
+        refresh_rates,
+        physical_size: (pixels_to_mm(width, xdpi), pixels_to_mm(height, ydpi)),
+    })
+}
+
+/// Resolution and refresh rate in Hz of every mode of the display, only known since Android 6
+fn supported_modes(
+    env: &mut JNIEnv,
+    display: &JObject,
+) -> jni::errors::Result<Vec<((i32, i32), f32)>> {
+    let modes = env
+        .call_method(
+            display,
+            "getSupportedModes",
+            "()[Landroid/view/Display$Mode;",
+            &[],
+        )?
+        .l()?;
+    let modes = JObjectArray::from(modes);
+    let length = env.get_array_length(&modes)?;
+    let mut result = Vec::with_capacity(length as usize);
+    for index in 0..length {
+        let mode = env.get_object_array_element(&modes, index)?;
+        let width = env
+            .call_method(&mode, "getPhysicalWidth", "()I", &[])?
+            .i()?;
+        let height = env
+            .call_method(&mode, "getPhysicalHeight", "()I", &[])?
+            .i()?;
+        let refresh = env.call_method(&mode, "getRefreshRate", "()F", &[])?.f()?;
+        result.push(((width, height), refresh));
+    }
+    Ok(result)
+}
+
+/// A function that can be passed into `run_in_jvm` to describe the display the activity is shown on
+pub fn activity_display(env: &mut JNIEnv, android_app: &AndroidApp) -> Option<DisplayInfo> {
+    let activity = unsafe { JObject::from_raw(android_app.activity_as_ptr() as *mut _jobject) };
+
+    let result = env
+        .call_method(
+            &activity,
+            "getWindowManager",
+            "()Landroid/view/WindowManager;",
+            &[],
+        )
+        .and_then(|it| it.l())
+        .and_then(|window_manager| {
+            env.call_method(
+                window_manager,
+                "getDefaultDisplay",
+                "()Landroid/view/Display;",
+                &[],
+            )?
+            .l()
+        })
+        .and_then(|display| display_info(env, &display));
+
+    match result {
+        Ok(info) => Some(info),
+        Err(e) => {
+            log::info!("Failed to describe the display: {:?}", e);
+            let _ = env.exception_clear();
+            None
+        }
+    }
+}
+
+/// Describe the display with the given id, `None` if it is gone
+pub fn external_display(
+    env: &mut JNIEnv,
+    android_app: &AndroidApp,
+    display_id: i32,
+) -> Option<DisplayInfo> {
+    let activity = unsafe { JObject::from_raw(android_app.activity_as_ptr() as *mut _jobject) };
+
+    let result = (|| -> jni::errors::Result<Option<DisplayInfo>> {
+        let service = env.new_string("display")?;
+        let manager = env
+            .call_method(
+                &activity,
+                "getSystemService",
+                "(Ljava/lang/String;)Ljava/lang/Object;",
+                &[JValue::Object(&service)],
+            )?
+            .l()?;
+        let display = env
+            .call_method(
+                manager,
+                "getDisplay",
+                "(I)Landroid/view/Display;",
+                &[JValue::Int(display_id)],
+            )?
+            .l()?;
+        if display.is_null() {
+            return Ok(None);
+        }
+        display_info(env, &display).map(Some)
+    })();
+
+    result.unwrap_or_else(|e| {
+        log::info!("Failed to describe display {}: {:?}", display_id, e);
+        let _ = env.exception_clear();
+        None
+    })
+}
+
+/// A function that can be passed into `run_in_jvm` to extend the desktop to the external displays.
+/// What happens to them is then told by `take_display_events`.
+pub fn start_external_displays(env: &mut JNIEnv, android_app: &AndroidApp) {
+    let activity = unsafe { JObject::from_raw(android_app.activity_as_ptr() as *mut _jobject) };
+
+    let result = load_app_class(env, &activity, EXTERNAL_DISPLAYS_CLASS).and_then(|class| {
+        env.call_static_method(
+            &class,
+            "start",
+            "(Landroid/app/Activity;)V",
+            &[JValue::Object(&activity)],
+        )
+        .map(|_| ())
+    });
+
+    if let Err(e) = result {
+        log::info!("Failed to watch the external displays: {:?}", e);
+        let _ = env.exception_clear();
+    }
+}
+
+/// Called by `ExternalDisplays` on the UI thread when the surface of a display is created or resized
+#[no_mangle]
+pub extern "system" fn Java_app_polarbear_ExternalDisplays_nativeSurfaceChanged(
+    env: JNIEnv,
+    _displays: JObject,
+    display_id: jint,
+    surface: JObject,
+    width: jint,
+    height: jint,
+) {
+    let window = unsafe { ANativeWindow_fromSurface(env.get_raw(), surface.as_raw()) };
+    let Some(window) = NonNull::new(window) else {
+        log::info!("Display {} has no native window", display_id);
+        return;
+    };
+    push_event(DisplayEvent::SurfaceChanged {
+        display_id,
+        window: unsafe { NativeWindow::from_raw(window) },
+        size: (width, height),
+    });
+}
+
+/// Called by `ExternalDisplays` on the UI thread when the surface of a display is destroyed
+#[no_mangle]
+pub extern "system" fn Java_app_polarbear_ExternalDisplays_nativeSurfaceDestroyed(
+    _env: JNIEnv,
+    _displays: JObject,
+    display_id: jint,
+) {
+    push_event(DisplayEvent::SurfaceDestroyed { display_id });
+}
+
+/// Called by `ExternalDisplays` on the UI thread when a display changed, e.g. it was rotated
+#[no_mangle]
+pub extern "system" fn Java_app_polarbear_ExternalDisplays_nativeDisplayChanged(
+    _env: JNIEnv,
+    _displays: JObject,
+    display_id: jint,
+) {
+    push_event(DisplayEvent::DisplayChanged { display_id });
+}
@@ -0,0 +1,80 @@
+use crate::android::{proot::process::ArchProcess, utils::ndk::load_app_class};
+use jni::objects::{JObject, JValue};
+use jni::sys::_jobject;
+use jni::JNIEnv;
+use winit::platform::android::activity::AndroidApp;
+
+/// The service is declared in `manifest.yaml` and implemented in `kotlin/ContainerService.kt`
+const SERVICE_CLASS: &str = "app.polarbear.ContainerService";
+
+/// Create an explicit `Intent` targeting the container service
+fn service_intent<'local>(
+    env: &mut JNIEnv<'local>,
+    activity: &JObject,
+) -> jni::errors::Result<JObject<'local>> {
+    let service_class = load_app_class(env, activity, SERVICE_CLASS)?;
+    env.new_object(
+        "android/content/Intent",
+        "(Landroid/content/Context;Ljava/lang/Class;)V",
+        &[activity.into(), (&service_class).into()],
+    )
+}
+
+/// A function that can be passed into `run_in_jvm` to keep the container alive in the background.
+/// Shows a persistent notification with a "Stop container" action.
+pub fn start_foreground_service(env: &mut JNIEnv, android_app: &AndroidApp) {
+    let activity = unsafe { JObject::from_raw(android_app.activity_as_ptr() as *mut _jobject) };
+
+    let result = service_intent(env, &activity).and_then(|intent| {
+        let sdk_int = env
+            .get_static_field("android/os/Build$VERSION", "SDK_INT", "I")?
+            .i()?;
+        // Android O and later refuse to start background services unless they are promoted to foreground
+        let method = if sdk_int >= 26 {
+            "startForegroundService"
+        } else {
+            "startService"
+        };
+        env.call_method(
+            &activity,
+            method,
+            "(Landroid/content/Intent;)Landroid/content/ComponentName;",
+            &[JValue::Object(&intent)],
+        )
+        .map(|_| ())
+    });
+
+    if let Err(error) = result {
+        log::info!("Failed to start the container service: {:?}", error);
+        let _ = env.exception_clear();
+    }
+}
+
+/// A function that can be passed into `run_in_jvm` to remove the persistent notification.
+pub fn stop_foreground_service(env: &mut JNIEnv, android_app: &AndroidApp) {
+    let activity = unsafe { JObject::from_raw(android_app.activity_as_ptr() as *mut _jobject) };
+
+    let result = service_intent(env, &activity).and_then(|intent| {
+        env.call_method(
+            &activity,
+            "stopService",
+            "(Landroid/content/Intent;)Z",
+            &[JValue::Object(&intent)],
+        )
+        .map(|_| ())
+    });
+
+    if let Err(error) = result {
+        log::info!("Failed to stop the container service: {:?}", error);
+        let _ = env.exception_clear();
+    }
+}
+
+/// Called by `ContainerService` when the user taps "Stop container" in the notification.
+#[no_mangle]
+pub extern "system" fn Java_app_polarbear_ContainerService_nativeStopContainer(
+    _env: JNIEnv,
+    _service: JObject,
+) {
+    ArchProcess::kill_running_sessions();
+}
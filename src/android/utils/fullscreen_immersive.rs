@@ -58,6 +58,8 @@ pub fn enable_fullscreen_immersive_mode(env: &mut JNIEnv, android_app: &AndroidA
     .expect("Failed to call setSystemUiVisibility");
 }
 
+/// Keep the screen on while the app starts and installs the Arch FS. Once the desktop runs, it is only
+/// kept on while a client inhibits idling, see `handle_idle_inhibit`.
 pub fn keep_screen_on(env: &mut JNIEnv, android_app: &AndroidApp) {
     let activity_obj = unsafe { JObject::from_raw(android_app.activity_as_ptr() as *mut _jobject) };
 
@@ -89,3 +91,44 @@ pub fn keep_screen_on(env: &mut JNIEnv, android_app: &AndroidApp) {
     )
     .expect("Failed to call addFlags");
 }
+
+/// Hide the Android mouse pointer over the app, the compositor draws the cursor requested by the Linux apps.
+/// Pointer icons only exist from Android 7.
+pub fn hide_pointer_icon(env: &mut JNIEnv, android_app: &AndroidApp) {
+    let activity_obj = unsafe { JObject::from_raw(android_app.activity_as_ptr() as *mut _jobject) };
+
+    let result = (|| -> jni::errors::Result<()> {
+        let window = env
+            .call_method(&activity_obj, "getWindow", "()Landroid/view/Window;", &[])?
+            .l()?;
+        let decor_view = env
+            .call_method(&window, "getDecorView", "()Landroid/view/View;", &[])?
+            .l()?;
+        let type_null = env
+            .get_static_field("android/view/PointerIcon", "TYPE_NULL", "I")?
+            .i()?;
+        let icon = env
+            .call_static_method(
+                "android/view/PointerIcon",
+                "getSystemIcon",
+                "(Landroid/content/Context;I)Landroid/view/PointerIcon;",
+                &[
+                    (&activity_obj).into(),
+                    jni::objects::JValue::from(type_null),
+                ],
+            )?
+            .l()?;
+        env.call_method(
+            &decor_view,
+            "setPointerIcon",
+            "(Landroid/view/PointerIcon;)V",
+            &[(&icon).into()],
+        )?;
+        Ok(())
+    })();
+
+    if let Err(e) = result {
+        log::info!("Failed to hide the pointer icon: {}", e);
+        let _ = env.exception_clear();
+    }
+}
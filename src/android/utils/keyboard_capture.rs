@@ -0,0 +1,38 @@
+use crate::android::utils::ndk::load_app_class;
+use jni::objects::{JObject, JValue};
+use jni::sys::_jobject;
+use jni::JNIEnv;
+use winit::platform::android::activity::AndroidApp;
+
+/// Captures the system key combinations for the compositor, implemented in `kotlin/KeyboardCapture.kt`
+const KEYBOARD_CAPTURE_CLASS: &str = "app.polarbear.KeyboardCapture";
+
+fn call_keyboard_capture(env: &mut JNIEnv, android_app: &AndroidApp, method: &str) {
+    let activity = unsafe { JObject::from_raw(android_app.activity_as_ptr() as *mut _jobject) };
+
+    let result = load_app_class(env, &activity, KEYBOARD_CAPTURE_CLASS).and_then(|class| {
+        env.call_static_method(
+            &class,
+            method,
+            "(Landroid/app/Activity;)V",
+            &[JValue::Object(&activity)],
+        )
+        .map(|_| ())
+    });
+
+    if let Err(e) = result {
+        log::info!("Failed to {} the keyboard capture: {:?}", method, e);
+        let _ = env.exception_clear();
+    }
+}
+
+/// A function that can be passed into `run_in_jvm` to have the Meta key and the combinations Samsung
+/// DeX handles itself, like Alt+Tab, reported as key events
+pub fn request_keyboard_capture(env: &mut JNIEnv, android_app: &AndroidApp) {
+    call_keyboard_capture(env, android_app, "request");
+}
+
+/// A function that can be passed into `run_in_jvm` to give the system key combinations back to Android
+pub fn release_keyboard_capture(env: &mut JNIEnv, android_app: &AndroidApp) {
+    call_keyboard_capture(env, android_app, "release");
+}
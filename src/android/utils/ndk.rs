@@ -1,3 +1,4 @@
+use jni::objects::{JClass, JObject};
 use jni::sys::JNIInvokeInterface_;
 use jni::{JNIEnv, JavaVM};
 use winit::platform::android::activity::AndroidApp;
@@ -22,3 +23,38 @@ where
 
     res
 }
+
+/// Load a class of the app, e.g. one from the `kotlin` folder.
+/// App classes are not visible to `FindClass` from a native thread, so the activity's class loader is used instead.
+/// A class that cannot be loaded means the feature built on it is broken, so it is reported as an error.
+pub fn load_app_class<'local>(
+    env: &mut JNIEnv<'local>,
+    activity: &JObject,
+    name: &str,
+) -> jni::errors::Result<JClass<'local>> {
+    let result = call_load_class(env, activity, name);
+    if let Err(e) = &result {
+        log::error!("Failed to load the app class {}: {:?}", name, e);
+    }
+    result
+}
+
+fn call_load_class<'local>(
+    env: &mut JNIEnv<'local>,
+    activity: &JObject,
+    name: &str,
+) -> jni::errors::Result<JClass<'local>> {
+    let class_loader = env
+        .call_method(activity, "getClassLoader", "()Ljava/lang/ClassLoader;", &[])?
+        .l()?;
+    let class_name = env.new_string(name)?;
+    let class = env
+        .call_method(
+            class_loader,
+            "loadClass",
+            "(Ljava/lang/String;)Ljava/lang/Class;",
+            &[(&class_name).into()],
+        )?
+        .l()?;
+    Ok(JClass::from(class))
+}
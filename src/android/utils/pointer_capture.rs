@@ -0,0 +1,38 @@
+use crate::android::utils::ndk::load_app_class;
+use jni::objects::{JObject, JValue};
+use jni::sys::_jobject;
+use jni::JNIEnv;
+use winit::platform::android::activity::AndroidApp;
+
+/// Captures the mouse for the compositor, implemented in `kotlin/PointerCapture.kt`
+const POINTER_CAPTURE_CLASS: &str = "app.polarbear.PointerCapture";
+
+fn call_pointer_capture(env: &mut JNIEnv, android_app: &AndroidApp, method: &str) {
+    let activity = unsafe { JObject::from_raw(android_app.activity_as_ptr() as *mut _jobject) };
+
+    let result = load_app_class(env, &activity, POINTER_CAPTURE_CLASS).and_then(|class| {
+        env.call_static_method(
+            &class,
+            method,
+            "(Landroid/app/Activity;)V",
+            &[JValue::Object(&activity)],
+        )
+        .map(|_| ())
+    });
+
+    if let Err(e) = result {
+        log::info!("Failed to {} the pointer capture: {:?}", method, e);
+        let _ = env.exception_clear();
+    }
+}
+
+/// A function that can be passed into `run_in_jvm` to capture the mouse, whose motion is then
+/// reported as `DeviceEvent::MouseMotion` instead of moving the pointer
+pub fn request_pointer_capture(env: &mut JNIEnv, android_app: &AndroidApp) {
+    call_pointer_capture(env, android_app, "request");
+}
+
+/// A function that can be passed into `run_in_jvm` to give the mouse back to Android
+pub fn release_pointer_capture(env: &mut JNIEnv, android_app: &AndroidApp) {
+    call_pointer_capture(env, android_app, "release");
+}
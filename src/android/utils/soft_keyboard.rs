@@ -0,0 +1,216 @@
+use crate::android::utils::ndk::load_app_class;
+use jni::objects::{JObject, JString, JValue};
+use jni::sys::{_jobject, jint};
+use jni::JNIEnv;
+use smithay::reexports::wayland_protocols::wp::text_input::zv3::server::zwp_text_input_v3::{
+    ContentHint, ContentPurpose,
+};
+use std::sync::Mutex;
+use winit::platform::android::activity::AndroidApp;
+
+/// The text field the Android keyboard types into, implemented in `kotlin/SoftKeyboard.kt`
+const SOFT_KEYBOARD_CLASS: &str = "app.polarbear.SoftKeyboard";
+
+// Constants of `android.text.InputType`
+const TYPE_CLASS_TEXT: i32 = 0x1;
+const TYPE_CLASS_NUMBER: i32 = 0x2;
+const TYPE_CLASS_PHONE: i32 = 0x3;
+const TYPE_CLASS_DATETIME: i32 = 0x4;
+const TYPE_TEXT_FLAG_CAP_CHARACTERS: i32 = 0x1000;
+const TYPE_TEXT_FLAG_CAP_WORDS: i32 = 0x2000;
+const TYPE_TEXT_FLAG_CAP_SENTENCES: i32 = 0x4000;
+const TYPE_TEXT_FLAG_AUTO_CORRECT: i32 = 0x8000;
+const TYPE_TEXT_FLAG_AUTO_COMPLETE: i32 = 0x10000;
+const TYPE_TEXT_FLAG_MULTI_LINE: i32 = 0x20000;
+const TYPE_TEXT_FLAG_NO_SUGGESTIONS: i32 = 0x80000;
+const TYPE_TEXT_VARIATION_URI: i32 = 0x10;
+const TYPE_TEXT_VARIATION_EMAIL_ADDRESS: i32 = 0x20;
+const TYPE_TEXT_VARIATION_PERSON_NAME: i32 = 0x60;
+const TYPE_TEXT_VARIATION_PASSWORD: i32 = 0x80;
+const TYPE_NUMBER_FLAG_SIGNED: i32 = 0x1000;
+const TYPE_NUMBER_FLAG_DECIMAL: i32 = 0x2000;
+const TYPE_NUMBER_VARIATION_PASSWORD: i32 = 0x10;
+const TYPE_DATETIME_VARIATION_DATE: i32 = 0x10;
+const TYPE_DATETIME_VARIATION_TIME: i32 = 0x20;
+
+pub enum SoftKeyboardEvent {
+    /// Text is being composed, e.g. a word before autocorrect or CJK before conversion
+    Preedit(String),
+    /// Text was typed
+    Commit(String),
+    /// The text being composed is kept as it is
+    FinishComposing,
+    /// Characters before and after the cursor were deleted, counted in UTF-16 code units like Android
+    /// does. See `Compositor::ime_delete_surrounding` for how they become bytes for Wayland.
+    DeleteSurrounding { before: u32, after: u32 },
+}
+
+/// Events of the UI thread, waiting for the main loop
+static EVENTS: Mutex<Vec<SoftKeyboardEvent>> = Mutex::new(Vec::new());
+
+/// Wakes the main loop up to handle the events
+static WAKE: Mutex<Option<Box<dyn Fn() + Send>>> = Mutex::new(None);
+
+fn push_event(event: SoftKeyboardEvent) {
+    EVENTS.lock().unwrap().push(event);
+    if let Some(wake) = WAKE.lock().unwrap().as_ref() {
+        wake();
+    }
+}
+
+/// What was typed with the soft keyboard since the last call, in order
+pub fn take_soft_keyboard_events() -> Vec<SoftKeyboardEvent> {
+    std::mem::take(&mut *EVENTS.lock().unwrap())
+}
+
+/// Call `wake` whenever there are new events for `take_soft_keyboard_events`
+pub fn on_soft_keyboard_events(wake: impl Fn() + Send + 'static) {
+    *WAKE.lock().unwrap() = Some(Box::new(wake));
+}
+
+/// The `android.text.InputType` matching what a text-input client says about its text, so that e.g.
+/// a number pad is shown for numbers and no suggestions are made for passwords
+pub fn input_type(content_type: Option<(ContentHint, ContentPurpose)>) -> i32 {
+    let (hint, purpose) = content_type.unwrap_or((ContentHint::None, ContentPurpose::Normal));
+    let hidden = hint.intersects(ContentHint::HiddenText | ContentHint::SensitiveData);
+    let text_type = match purpose {
+        ContentPurpose::Digits => return TYPE_CLASS_NUMBER,
+        ContentPurpose::Number => {
+            return TYPE_CLASS_NUMBER | TYPE_NUMBER_FLAG_SIGNED | TYPE_NUMBER_FLAG_DECIMAL
+        }
+        ContentPurpose::Pin => return TYPE_CLASS_NUMBER | TYPE_NUMBER_VARIATION_PASSWORD,
+        ContentPurpose::Phone => return TYPE_CLASS_PHONE,
+        ContentPurpose::Date => return TYPE_CLASS_DATETIME | TYPE_DATETIME_VARIATION_DATE,
+        ContentPurpose::Time => return TYPE_CLASS_DATETIME | TYPE_DATETIME_VARIATION_TIME,
+        ContentPurpose::Datetime => return TYPE_CLASS_DATETIME,
+        ContentPurpose::Url => TYPE_CLASS_TEXT | TYPE_TEXT_VARIATION_URI,
+        ContentPurpose::Email => TYPE_CLASS_TEXT | TYPE_TEXT_VARIATION_EMAIL_ADDRESS,
+        ContentPurpose::Name => TYPE_CLASS_TEXT | TYPE_TEXT_VARIATION_PERSON_NAME,
+        ContentPurpose::Password => TYPE_CLASS_TEXT | TYPE_TEXT_VARIATION_PASSWORD,
+        // Commands are not words
+        ContentPurpose::Terminal => TYPE_CLASS_TEXT | TYPE_TEXT_FLAG_NO_SUGGESTIONS,
+        _ if hidden => TYPE_CLASS_TEXT | TYPE_TEXT_VARIATION_PASSWORD,
+        _ => TYPE_CLASS_TEXT,
+    };
+
+    let flags = [
+        (ContentHint::Completion, TYPE_TEXT_FLAG_AUTO_COMPLETE),
+        (ContentHint::Spellcheck, TYPE_TEXT_FLAG_AUTO_CORRECT),
+        (
+            ContentHint::AutoCapitalization,
+            TYPE_TEXT_FLAG_CAP_SENTENCES,
+        ),
+        (ContentHint::Uppercase, TYPE_TEXT_FLAG_CAP_CHARACTERS),
+        (ContentHint::Titlecase, TYPE_TEXT_FLAG_CAP_WORDS),
+        (ContentHint::Multiline, TYPE_TEXT_FLAG_MULTI_LINE),
+    ];
+    let mut input_type = text_type;
+    for (flag, android_flag) in flags {
+        if hint.contains(flag) {
+            input_type |= android_flag;
+        }
+    }
+    if hidden {
+        input_type &= !(TYPE_TEXT_FLAG_AUTO_COMPLETE | TYPE_TEXT_FLAG_AUTO_CORRECT);
+        input_type |= TYPE_TEXT_FLAG_NO_SUGGESTIONS;
+    }
+    input_type
+}
+
+/// A function that can be passed into `run_in_jvm` to show the soft keyboard with the given input type.
+/// What is typed is then told by `take_soft_keyboard_events`.
+pub fn show_soft_keyboard(env: &mut JNIEnv, android_app: &AndroidApp, input_type: i32) {
+    let activity = unsafe { JObject::from_raw(android_app.activity_as_ptr() as *mut _jobject) };
+
+    let result = load_app_class(env, &activity, SOFT_KEYBOARD_CLASS).and_then(|class| {
+        env.call_static_method(
+            &class,
+            "show",
+            "(Landroid/app/Activity;I)V",
+            &[JValue::Object(&activity), JValue::Int(input_type)],
+        )
+        .map(|_| ())
+    });
+
+    if let Err(e) = result {
+        log::info!("Failed to show the soft keyboard: {:?}", e);
+        let _ = env.exception_clear();
+    }
+}
+
+/// A function that can be passed into `run_in_jvm` to hide the soft keyboard
+pub fn hide_soft_keyboard(env: &mut JNIEnv, android_app: &AndroidApp) {
+    let activity = unsafe { JObject::from_raw(android_app.activity_as_ptr() as *mut _jobject) };
+
+    let result = load_app_class(env, &activity, SOFT_KEYBOARD_CLASS).and_then(|class| {
+        env.call_static_method(
+            &class,
+            "hide",
+            "(Landroid/app/Activity;)V",
+            &[JValue::Object(&activity)],
+        )
+        .map(|_| ())
+    });
+
+    if let Err(e) = result {
+        log::info!("Failed to hide the soft keyboard: {:?}", e);
+        let _ = env.exception_clear();
+    }
+}
+
+fn java_string(env: &mut JNIEnv, text: &JString) -> Option<String> {
+    match env.get_string(text) {
+        Ok(text) => Some(text.into()),
+        Err(e) => {
+            log::info!("Failed to read the typed text: {:?}", e);
+            None
+        }
+    }
+}
+
+/// Called by `SoftKeyboard` on the UI thread while text is being composed
+#[no_mangle]
+pub extern "system" fn Java_app_polarbear_SoftKeyboard_nativePreedit(
+    mut env: JNIEnv,
+    _view: JObject,
+    text: JString,
+) {
+    if let Some(text) = java_string(&mut env, &text) {
+        push_event(SoftKeyboardEvent::Preedit(text));
+    }
+}
+
+/// Called by `SoftKeyboard` on the UI thread when text was typed
+#[no_mangle]
+pub extern "system" fn Java_app_polarbear_SoftKeyboard_nativeCommit(
+    mut env: JNIEnv,
+    _view: JObject,
+    text: JString,
+) {
+    if let Some(text) = java_string(&mut env, &text) {
+        push_event(SoftKeyboardEvent::Commit(text));
+    }
+}
+
+/// Called by `SoftKeyboard` on the UI thread when the text being composed is kept as it is
+#[no_mangle]
+pub extern "system" fn Java_app_polarbear_SoftKeyboard_nativeFinishComposing(
+    _env: JNIEnv,
+    _view: JObject,
+) {
+    push_event(SoftKeyboardEvent::FinishComposing);
+}
+
+/// Called by `SoftKeyboard` on the UI thread when characters around the cursor were deleted
+#[no_mangle]
+pub extern "system" fn Java_app_polarbear_SoftKeyboard_nativeDeleteSurrounding(
+    _env: JNIEnv,
+    _view: JObject,
+    before: jint,
+    after: jint,
+) {
+    push_event(SoftKeyboardEvent::DeleteSurrounding {
+        before: before.max(0) as u32,
+        after: after.max(0) as u32,
+    });
+}
@@ -247,6 +247,7 @@ pub mod apk {
         if app.debuggable.is_none() {
             app.debuggable = Some(!release);
         }
+        // Keep the components declared by the manifest, e.g. the background service and its code
         if app.has_code.is_none() {
             app.has_code = Some(wry);
         }
@@ -355,8 +356,21 @@ pub mod apk {
             android_jar_override,
         )?;
 
+        let has_code = manifest.application.has_code.unwrap_or(false);
+        let dex_path = compile_kotlin(
+            &root,
+            &profile_dir,
+            &android_jar,
+            manifest.sdk.min_sdk_version.unwrap_or(min_sdk_version),
+            release,
+        )?;
+
         let mut apk = Apk::new(out_path.clone(), manifest, release)?;
 
+        if let Some(dex_path) = &dex_path {
+            apk.add_dex(dex_path)?;
+        }
+
         let icon_path = icon.map(|path| root.join(path));
         apk.add_res(icon_path.as_deref(), &android_jar)?;
 
@@ -391,6 +405,9 @@ pub mod apk {
         }
 
         apk.finish(None)?;
+        if has_code {
+            ensure_apk_has_dex(&out_path)?;
+        }
 
         println!("APK written to {}", out_path.display());
         Ok(())
@@ -960,12 +977,40 @@ pub mod apk {
             #[serde(rename(serialize = "activity"))]
             #[serde(default)]
             pub activities: Vec<Activity>,
+            #[serde(rename(serialize = "service"))]
+            #[serde(default)]
+            pub services: Vec<Service>,
             #[serde(rename(serialize = "android:usesCleartextTraffic"))]
             pub use_cleartext_traffic: Option<bool>,
             #[serde(rename(serialize = "android:extractNativeLibs"))]
             pub extract_native_libs: Option<bool>,
         }
 
+        /// Android [service element](https://developer.android.com/guide/topics/manifest/service-element).
+        #[derive(Clone, Debug, Default, Deserialize, Serialize)]
+        #[serde(deny_unknown_fields)]
+        pub struct Service {
+            #[serde(rename(serialize = "android:name"))]
+            pub name: String,
+            #[serde(rename(serialize = "android:exported"))]
+            pub exported: Option<bool>,
+            #[serde(rename(serialize = "android:foregroundServiceType"))]
+            pub foreground_service_type: Option<String>,
+            #[serde(rename(serialize = "property"))]
+            #[serde(default)]
+            pub properties: Vec<Property>,
+        }
+
+        /// Android [property element](https://developer.android.com/guide/topics/manifest/property-element).
+        #[derive(Clone, Debug, Default, Deserialize, Serialize)]
+        #[serde(deny_unknown_fields)]
+        pub struct Property {
+            #[serde(rename(serialize = "android:name"))]
+            pub name: String,
+            #[serde(rename(serialize = "android:value"))]
+            pub value: String,
+        }
+
         /// Android [activity element](https://developer.android.com/guide/topics/manifest/activity-element).
         #[derive(Clone, Debug, Default, Deserialize, Serialize)]
         #[serde(deny_unknown_fields)]
@@ -3813,6 +3858,83 @@ pub mod apk {
         Ok(jar_path)
     }
 
+    /// Compile the sources in the `kotlin` folder (e.g. the background service) into a `classes.dex`
+    fn compile_kotlin(
+        root: &Path,
+        profile_dir: &Path,
+        android_jar: &Path,
+        min_sdk: u32,
+        release: bool,
+    ) -> Result<Option<PathBuf>> {
+        let kotlin_dir = root.join("kotlin");
+        if !kotlin_dir.exists() {
+            return Ok(None);
+        }
+        let mut sources = vec![];
+        for entry in fs::read_dir(&kotlin_dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) == Some("kt") {
+                sources.push(path);
+            }
+        }
+        if sources.is_empty() {
+            return Ok(None);
+        }
+        sources.sort();
+
+        let out_dir = profile_dir.join("kotlin");
+        if out_dir.exists() {
+            fs::remove_dir_all(&out_dir)?;
+        }
+        fs::create_dir_all(&out_dir)?;
+
+        // The Kotlin runtime is not part of Android, so it is bundled next to the app classes
+        let jar_path = out_dir.join("classes.jar");
+        let status = Command::new("kotlinc")
+            .arg("-classpath")
+            .arg(android_jar)
+            .arg("-include-runtime")
+            .arg("-d")
+            .arg(&jar_path)
+            .args(&sources)
+            .status()
+            .context("Running `kotlinc`, install it with `pkg i kotlin`")?;
+        if !status.success() {
+            bail!("`kotlinc` failed");
+        }
+
+        let status = Command::new("d8")
+            .arg(if release { "--release" } else { "--debug" })
+            .arg("--lib")
+            .arg(android_jar)
+            .arg("--min-api")
+            .arg(min_sdk.to_string())
+            .arg("--output")
+            .arg(&out_dir)
+            .arg(&jar_path)
+            .status()
+            .context("Running `d8`, install it with `pkg i d8`")?;
+        if !status.success() {
+            bail!("`d8` failed");
+        }
+
+        let dex_path = out_dir.join("classes.dex");
+        if !dex_path.exists() {
+            bail!("Expected dex not found at `{}`", dex_path.display());
+        }
+        Ok(Some(dex_path))
+    }
+
+    /// Android refuses to install an APK that has code but no `classes.dex`
+    fn ensure_apk_has_dex(path: &Path) -> Result<()> {
+        let file = File::open(path)?;
+        let mut archive = ZipArchive::new(file)?;
+        archive
+            .by_name("classes.dex")
+            .with_context(|| format!("`{}` has code but no classes.dex", path.display()))?;
+        Ok(())
+    }
+
     fn ensure_android_jar_has_resources(path: &Path) -> Result<()> {
         let file = File::open(path)?;
         let mut archive = ZipArchive::new(file)?;
@@ -0,0 +1,78 @@
+use super::state::State;
+use smithay::{
+    delegate_xdg_activation,
+    desktop::Window,
+    input::Seat,
+    reexports::wayland_server::{protocol::wl_surface::WlSurface, Resource},
+    wayland::{
+        seat::WaylandFocus,
+        xdg_activation::{
+            XdgActivationHandler, XdgActivationState, XdgActivationToken, XdgActivationTokenData,
+        },
+    },
+};
+use std::time::Duration;
+
+/// How long after the input that created it a token may still raise a window
+const TOKEN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Apps raise their windows, e.g. a browser already running when a link is opened, or hand the focus
+/// over to the app they start
+impl XdgActivationHandler for State {
+    fn activation_state(&mut self) -> &mut XdgActivationState {
+        &mut self.xdg_activation_state
+    }
+
+    fn token_created(&mut self, _token: XdgActivationToken, data: XdgActivationTokenData) -> bool {
+        self.xdg_activation_state
+            .retain_tokens(|_, data| data.timestamp.elapsed() < TOKEN_TIMEOUT);
+
+        // Focus stealing prevention: only the focused client may hand the focus over, in reaction to
+        // input it received while it had the focus
+        let Some((serial, seat)) = data.serial else {
+            return false;
+        };
+        let Some(keyboard) = self.seat.get_keyboard() else {
+            return false;
+        };
+        let focused_client = keyboard
+            .current_focus()
+            .and_then(|focus| focus.wl_surface()?.client())
+            .map(|client| client.id());
+        Seat::<State>::from_resource(&seat).is_some_and(|seat| seat == self.seat)
+            && focused_client.is_some()
+            && focused_client == data.client_id
+            && keyboard
+                .last_enter()
+                .is_some_and(|enter| serial.is_no_older_than(&enter))
+    }
+
+    fn request_activation(
+        &mut self,
+        token: XdgActivationToken,
+        token_data: XdgActivationTokenData,
+        surface: WlSurface,
+    ) {
+        self.xdg_activation_state.remove_token(&token);
+        if token_data.timestamp.elapsed() >= TOKEN_TIMEOUT {
+            return;
+        }
+        if let Some(window) = self.toplevel_window(&surface) {
+            self.activate_window(&window);
+        }
+    }
+}
+
+delegate_xdg_activation!(State);
+
+impl State {
+    /// The window of a toplevel surface, mapped or minimized
+    pub(super) fn toplevel_window(&self, surface: &WlSurface) -> Option<Window> {
+        self.window_for_surface(surface).or_else(|| {
+            self.minimized
+                .iter()
+                .find(|(window, _)| window.wl_surface().as_deref() == Some(surface))
+                .map(|(window, _)| window.clone())
+        })
+    }
+}
@@ -0,0 +1,166 @@
+use crate::core::{config::ARCH_FS_ROOT, xcursor};
+use smithay::{
+    backend::{
+        allocator::Fourcc,
+        renderer::{
+            element::{
+                memory::{MemoryRenderBuffer, MemoryRenderBufferRenderElement},
+                surface::{render_elements_from_surface_tree, WaylandSurfaceRenderElement},
+                Kind,
+            },
+            ImportAll, ImportMem, Renderer,
+        },
+    },
+    input::pointer::{CursorIcon, CursorImageStatus, CursorImageSurfaceData},
+    render_elements,
+    utils::{Logical, Physical, Point, Size, Transform},
+    wayland::compositor::with_states,
+};
+use std::{
+    collections::HashMap,
+    fs, iter,
+    path::{Path, PathBuf},
+};
+
+/// Theme of the container used for named cursors, usually inheriting from the installed one
+const THEME: &str = "default";
+
+/// Size of the cursor at scale 1, the usual `XCURSOR_SIZE`
+const BASE_SIZE: f64 = 24.0;
+
+render_elements! {
+    pub DesktopRenderElement<R> where R: ImportAll + ImportMem;
+    Surface=WaylandSurfaceRenderElement<R>,
+    Memory=MemoryRenderBufferRenderElement<R>,
+}
+
+struct ThemedCursor {
+    buffer: MemoryRenderBuffer,
+    size: Size<i32, Logical>,
+    hotspot: Point<i32, Physical>,
+}
+
+/// Cursors of the Xcursor theme installed in the container, loaded on first use
+pub struct CursorTheme {
+    icons_dir: PathBuf,
+    size: u32,
+    /// `None` when the theme has no such cursor
+    cache: HashMap<CursorIcon, Option<ThemedCursor>>,
+}
+
+impl Default for CursorTheme {
+    fn default() -> Self {
+        Self {
+            icons_dir: PathBuf::from(ARCH_FS_ROOT).join("usr/share/icons"),
+            size: BASE_SIZE as u32,
+            cache: HashMap::new(),
+        }
+    }
+}
+
+impl CursorTheme {
+    fn get(&mut self, icon: CursorIcon, scale: f64) -> Option<&ThemedCursor> {
+        let size = (BASE_SIZE * scale).round() as u32;
+        if size != self.size {
+            self.cache.clear();
+            self.size = size;
+        }
+        let icons_dir = &self.icons_dir;
+        self.cache
+            .entry(icon)
+            .or_insert_with(|| load_cursor(icons_dir, icon, size))
+            .as_ref()
+    }
+
+    /// The cursor requested by the focused client, with its hotspot at `location`, drawn at `scale`
+    pub fn render_elements<R>(
+        &mut self,
+        renderer: &mut R,
+        status: &CursorImageStatus,
+        location: Point<f64, Logical>,
+        scale: f64,
+    ) -> Vec<DesktopRenderElement<R>>
+    where
+        R: Renderer + ImportAll + ImportMem,
+        R::TextureId: Send + Clone + 'static,
+    {
+        let location = location.to_physical(scale).to_i32_round();
+        match status {
+            CursorImageStatus::Hidden => Vec::new(),
+            CursorImageStatus::Surface(surface) => {
+                let hotspot = with_states(surface, |states| {
+                    states
+                        .data_map
+                        .get::<CursorImageSurfaceData>()
+                        .map(|data| data.lock().unwrap().hotspot)
+                        .unwrap_or_default()
+                });
+                render_elements_from_surface_tree(
+                    renderer,
+                    surface,
+                    location - hotspot.to_physical_precise_round(scale),
+                    scale,
+                    1.0,
+                    Kind::Cursor,
+                )
+            }
+            CursorImageStatus::Named(icon) => {
+                // Themes seldom have every cursor, the arrow is better than nothing
+                let icon = if self.get(*icon, scale).is_some() {
+                    *icon
+                } else {
+                    CursorIcon::Default
+                };
+                let Some(cursor) = self.get(icon, scale) else {
+                    return Vec::new();
+                };
+                match MemoryRenderBufferRenderElement::from_buffer(
+                    renderer,
+                    (location - cursor.hotspot).to_f64(),
+                    &cursor.buffer,
+                    None,
+                    None,
+                    Some(cursor.size),
+                    Kind::Cursor,
+                ) {
+                    Ok(element) => vec![element.into()],
+                    Err(e) => {
+                        log::info!("Failed to upload cursor: {}", e);
+                        Vec::new()
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Load the image of `icon` closest to `size`, scaled to `size` when the theme has no such size
+fn load_cursor(icons_dir: &Path, icon: CursorIcon, size: u32) -> Option<ThemedCursor> {
+    let names = iter::once(icon.name())
+        .chain(icon.alt_names().iter().copied())
+        .collect::<Vec<_>>();
+    let path = xcursor::find_cursor(icons_dir, THEME, &names)?;
+    let data = fs::read(&path)
+        .map_err(|e| log::info!("Failed to read cursor {}: {}", path.display(), e))
+        .ok()?;
+    let Some(images) = xcursor::parse_xcursor(&data) else {
+        log::info!("Invalid cursor file {}", path.display());
+        return None;
+    };
+    let image = xcursor::nearest_image(&images, size)?;
+
+    let ratio = size as f64 / image.size.max(1) as f64;
+    let scaled = |value: u32| (value as f64 * ratio).round() as i32;
+    Some(ThemedCursor {
+        buffer: MemoryRenderBuffer::from_slice(
+            &image.pixels,
+            Fourcc::Argb8888,
+            (image.width as i32, image.height as i32),
+            1,
+            Transform::Normal,
+            None,
+        ),
+        size: (scaled(image.width), scaled(image.height)).into(),
+        hotspot: (scaled(image.xhot), scaled(image.yhot)).into(),
+    })
+}
@@ -0,0 +1,460 @@
+use super::{
+    cursor::DesktopRenderElement,
+    grabs::{
+        MoveSurfaceGrab, ResizeSurfaceGrab, TouchMoveSurfaceGrab, TouchResizeSurfaceGrab, BTN_LEFT,
+    },
+    layer_shell::LAYERS_ABOVE,
+    state::{Compositor, State},
+};
+use crate::core::{
+    config::ARCH_FS_ROOT,
+    decoration::{
+        decoration_part_at, draw_titlebar, load_font, DecorationPart, Edges, TITLEBAR_HEIGHT,
+    },
+};
+use ab_glyph::FontVec;
+use smithay::{
+    backend::input::TouchSlot,
+    backend::{
+        allocator::Fourcc,
+        renderer::{
+            element::{
+                memory::{MemoryRenderBuffer, MemoryRenderBufferRenderElement},
+                Kind,
+            },
+            ImportAll, ImportMem, Renderer,
+        },
+    },
+    delegate_xdg_decoration,
+    desktop::{space::SpaceElement, Window, WindowSurface},
+    input::{
+        pointer::{CursorIcon, CursorImageStatus, Focus, GrabStartData as PointerGrabStartData},
+        touch::GrabStartData as TouchGrabStartData,
+    },
+    reexports::wayland_protocols::xdg::{
+        decoration::zv1::server::zxdg_toplevel_decoration_v1::Mode,
+        shell::server::xdg_toplevel::{self, ResizeEdge},
+    },
+    utils::{Logical, Point, Rectangle, Serial, Size, Transform},
+    wayland::{
+        compositor::with_states,
+        shell::xdg::{
+            decoration::XdgDecorationHandler, ToplevelState, ToplevelSurface,
+            XdgToplevelSurfaceData,
+        },
+    },
+    xwayland::{xwm::WmWindowType, X11Surface},
+};
+use std::{cell::RefCell, path::PathBuf};
+
+/// Clients draw their own decorations only when they insist, so that windows of toolkits that draw
+/// none (many Qt and SDL apps) are not left frameless
+impl XdgDecorationHandler for State {
+    fn new_decoration(&mut self, toplevel: ToplevelSurface) {
+        set_decoration_mode(&toplevel, Mode::ServerSide);
+    }
+
+    fn request_mode(&mut self, toplevel: ToplevelSurface, mode: Mode) {
+        set_decoration_mode(&toplevel, mode);
+    }
+
+    fn unset_mode(&mut self, toplevel: ToplevelSurface) {
+        set_decoration_mode(&toplevel, Mode::ServerSide);
+    }
+}
+
+delegate_xdg_decoration!(State);
+
+fn set_decoration_mode(toplevel: &ToplevelSurface, mode: Mode) {
+    toplevel.with_pending_state(|state| state.decoration_mode = Some(mode));
+    // Before that, the mode goes with the initial configure
+    if toplevel.is_initial_configure_sent() {
+        toplevel.send_pending_configure();
+    }
+}
+
+/// Whether the compositor draws the decorations of a toplevel in `state`. Fullscreen windows have none.
+pub(super) fn server_decorated(state: &ToplevelState) -> bool {
+    state.decoration_mode == Some(Mode::ServerSide)
+        && !state.states.contains(xdg_toplevel::State::Fullscreen)
+}
+
+/// Whether the compositor draws the decorations of an X11 window when it is not fullscreen. X11 has no
+/// way to ask for them, every normal window gets them unless its Motif hints turn them off. Panels, the
+/// desktop, menus and tooltips have none.
+pub(super) fn server_decorated_x11(surface: &X11Surface) -> bool {
+    !surface.is_override_redirect()
+        && !surface.is_decorated()
+        && matches!(
+            surface.window_type(),
+            None | Some(WmWindowType::Normal | WmWindowType::Dialog | WmWindowType::Utility)
+        )
+}
+
+/// What the decorations of a window show, for the windows the compositor decorates
+struct DecorationState {
+    focused: bool,
+    maximized: bool,
+}
+
+fn decoration_state(window: &Window) -> Option<DecorationState> {
+    match window.underlying_surface() {
+        WindowSurface::Wayland(toplevel) => {
+            let state = toplevel.current_state();
+            server_decorated(&state).then(|| DecorationState {
+                focused: state.states.contains(xdg_toplevel::State::Activated),
+                maximized: state.states.contains(xdg_toplevel::State::Maximized),
+            })
+        }
+        WindowSurface::X11(surface) => (server_decorated_x11(surface) && !surface.is_fullscreen())
+            .then(|| DecorationState {
+                focused: surface.is_activated(),
+                maximized: surface.is_maximized(),
+            }),
+    }
+}
+
+/// The font of the titles, loaded from the container on first use
+#[derive(Default)]
+pub struct TitlebarFont(Option<Option<FontVec>>);
+
+impl TitlebarFont {
+    fn get(&mut self) -> Option<&FontVec> {
+        self.0
+            .get_or_insert_with(|| {
+                let font = load_font(&PathBuf::from(ARCH_FS_ROOT).join("usr/share/fonts"));
+                if font.is_none() {
+                    log::info!("No font found for the window titles");
+                }
+                font
+            })
+            .as_ref()
+    }
+}
+
+/// What a title bar was drawn for, it is drawn again when any of it changes
+#[derive(PartialEq)]
+struct TitlebarKey {
+    title: String,
+    width: i32,
+    scale: f64,
+    focused: bool,
+}
+
+/// The title bar of a window as last drawn, kept in its user data
+#[derive(Default)]
+struct TitlebarBuffer(RefCell<Option<(TitlebarKey, MemoryRenderBuffer)>>);
+
+/// The title bar of `window` if it has server-side decorations, above `location` where the window
+/// itself is drawn, relative to the output
+pub(super) fn titlebar_element<R>(
+    renderer: &mut R,
+    window: &Window,
+    font: &mut TitlebarFont,
+    location: Point<i32, Logical>,
+    scale: f64,
+) -> Option<DesktopRenderElement<R>>
+where
+    R: Renderer + ImportAll + ImportMem,
+    R::TextureId: Send + Clone + 'static,
+{
+    let state = decoration_state(window)?;
+    let width = window.geometry().size.w;
+    if width <= 0 {
+        return None;
+    }
+
+    let key = TitlebarKey {
+        title: window_title(window),
+        width,
+        scale,
+        focused: state.focused,
+    };
+    let cache = window.user_data().get_or_insert(TitlebarBuffer::default);
+    let mut cache = cache.0.borrow_mut();
+    if cache.as_ref().is_none_or(|(drawn, _)| drawn != &key) {
+        let size = Size::<i32, Logical>::from((width, TITLEBAR_HEIGHT))
+            .to_physical_precise_round::<f64, i32>(scale);
+        let pixels = draw_titlebar(
+            size.w as u32,
+            size.h as u32,
+            scale,
+            &key.title,
+            key.focused,
+            font.get(),
+        );
+        let buffer = MemoryRenderBuffer::from_slice(
+            &pixels,
+            Fourcc::Argb8888,
+            (size.w, size.h),
+            1,
+            Transform::Normal,
+            None,
+        );
+        *cache = Some((key, buffer));
+    }
+    let (_, buffer) = cache.as_ref()?;
+
+    let location = location - Point::from((0, TITLEBAR_HEIGHT));
+    match MemoryRenderBufferRenderElement::from_buffer(
+        renderer,
+        location
+            .to_physical_precise_round::<f64, i32>(scale)
+            .to_f64(),
+        buffer,
+        None,
+        None,
+        Some((width, TITLEBAR_HEIGHT).into()),
+        Kind::Unspecified,
+    ) {
+        Ok(element) => Some(element.into()),
+        Err(e) => {
+            log::info!("Failed to upload title bar: {}", e);
+            None
+        }
+    }
+}
+
+fn window_title(window: &Window) -> String {
+    match window.underlying_surface() {
+        WindowSurface::Wayland(toplevel) => with_states(toplevel.wl_surface(), |states| {
+            states
+                .data_map
+                .get::<XdgToplevelSurfaceData>()
+                .and_then(|data| data.lock().unwrap().title.clone())
+                .unwrap_or_default()
+        }),
+        WindowSurface::X11(surface) => surface.title(),
+    }
+}
+
+fn resize_edge(edges: Edges) -> ResizeEdge {
+    let bits = edges.top as u32
+        | (edges.bottom as u32) << 1
+        | (edges.left as u32) << 2
+        | (edges.right as u32) << 3;
+    ResizeEdge::try_from(bits).unwrap_or(ResizeEdge::None)
+}
+
+/// The cursor shown over a part of the decorations
+fn decoration_cursor(part: DecorationPart) -> CursorIcon {
+    let DecorationPart::Border(edges) = part else {
+        return CursorIcon::Default;
+    };
+    match (edges.top, edges.bottom, edges.left, edges.right) {
+        (true, _, true, _) => CursorIcon::NwResize,
+        (true, _, _, true) => CursorIcon::NeResize,
+        (_, true, true, _) => CursorIcon::SwResize,
+        (_, true, _, true) => CursorIcon::SeResize,
+        (true, ..) => CursorIcon::NResize,
+        (_, true, ..) => CursorIcon::SResize,
+        (_, _, true, _) => CursorIcon::WResize,
+        _ => CursorIcon::EResize,
+    }
+}
+
+impl State {
+    /// The topmost window under `location`, and the part of its decorations if it is not on the window
+    /// itself. Decorations are hit before the windows below, like they are drawn.
+    pub(super) fn window_under(
+        &self,
+        location: Point<f64, Logical>,
+    ) -> Option<(Window, Option<DecorationPart>)> {
+        self.space.elements().rev().find_map(|window| {
+            let window_location = self.space.element_location(window)?;
+            if let Some(part) = self.decoration_part(window, location - window_location.to_f64()) {
+                return Some((window.clone(), Some(part)));
+            }
+            let render_location = window_location - window.geometry().loc;
+            let on_window = self.space.element_bbox(window)?.to_f64().contains(location)
+                && window.is_in_input_region(&(location - render_location.to_f64()));
+            on_window.then(|| (window.clone(), None))
+        })
+    }
+
+    /// The decorations under `location`, unless a window or a panel is above them
+    fn decoration_under(&self, location: Point<f64, Logical>) -> Option<(Window, DecorationPart)> {
+        if self.layer_under(location, &LAYERS_ABOVE).is_some() {
+            return None;
+        }
+        match self.window_under(location)? {
+            (window, Some(part)) => Some((window, part)),
+            (_, None) => None,
+        }
+    }
+
+    /// The part of the decorations of `window` at `location`, relative to the top left corner of its geometry
+    fn decoration_part(
+        &self,
+        window: &Window,
+        location: Point<f64, Logical>,
+    ) -> Option<DecorationPart> {
+        let resizable = !decoration_state(window)?.maximized;
+        let size = window.geometry().size;
+        decoration_part_at((size.w, size.h), (location.x, location.y), resizable)
+    }
+
+    /// Where `window` is, unless it is maximized and cannot be moved or resized
+    fn floating_location(&self, window: &Window) -> Option<Point<i32, Logical>> {
+        let maximized = match window.underlying_surface() {
+            WindowSurface::Wayland(toplevel) => toplevel
+                .current_state()
+                .states
+                .contains(xdg_toplevel::State::Maximized),
+            WindowSurface::X11(surface) => surface.is_maximized(),
+        };
+        if maximized {
+            return None;
+        }
+        self.space.element_location(window)
+    }
+
+    /// Act on a press of one of the buttons of the title bar
+    fn press_window_button(&mut self, window: &Window, part: DecorationPart) {
+        self.frames.schedule();
+        let toplevel = match window.underlying_surface() {
+            WindowSurface::Wayland(toplevel) => toplevel.clone(),
+            WindowSurface::X11(surface) => {
+                self.press_x11_window_button(window, &surface.clone(), part);
+                return;
+            }
+        };
+        match part {
+            DecorationPart::Close => toplevel.send_close(),
+            DecorationPart::Maximize => {
+                let maximized = toplevel.with_pending_state(|state| {
+                    state.states.contains(xdg_toplevel::State::Maximized)
+                });
+                if maximized {
+                    self.uncover_output(&toplevel, xdg_toplevel::State::Maximized);
+                } else {
+                    self.cover_output(&toplevel, xdg_toplevel::State::Maximized);
+                }
+            }
+            DecorationPart::Minimize => self.minimize_window(window),
+            DecorationPart::Titlebar | DecorationPart::Border(_) => {}
+        }
+    }
+
+    fn press_x11_window_button(
+        &mut self,
+        window: &Window,
+        surface: &X11Surface,
+        part: DecorationPart,
+    ) {
+        match part {
+            DecorationPart::Close => {
+                if let Err(e) = surface.close() {
+                    log::info!("Failed to close X11 window: {}", e);
+                }
+            }
+            DecorationPart::Maximize => {
+                if surface.is_maximized() {
+                    let _ = surface.set_maximized(false);
+                    self.uncover_output_x11(surface);
+                } else {
+                    self.cover_output_x11(surface, false);
+                    let _ = surface.set_maximized(true);
+                }
+            }
+            DecorationPart::Minimize => self.minimize_window(window),
+            DecorationPart::Titlebar | DecorationPart::Border(_) => {}
+        }
+    }
+
+    /// Show the cursor of the decorations under `location`, if any, as no client sets one there
+    pub(super) fn update_decoration_cursor(&mut self, location: Point<f64, Logical>) {
+        if let Some((_, part)) = self.decoration_under(location) {
+            self.cursor_status = CursorImageStatus::Named(decoration_cursor(part));
+        }
+    }
+}
+
+/// The title bar and border drawn around windows with server-side decorations: drag the title bar to
+/// move a window, its border to resize it, or press its buttons
+impl Compositor {
+    /// Press the decorations under the pointer with its left button
+    pub(super) fn pointer_press_decoration(
+        &mut self,
+        location: Point<f64, Logical>,
+        serial: Serial,
+    ) {
+        let Some((window, part)) = self.state.decoration_under(location) else {
+            return;
+        };
+        let start_data = PointerGrabStartData {
+            focus: None,
+            button: BTN_LEFT,
+            location,
+        };
+        let pointer = self.pointer.clone();
+        match part {
+            DecorationPart::Titlebar => {
+                let Some(initial_window_location) = self.state.floating_location(&window) else {
+                    return;
+                };
+                let grab = MoveSurfaceGrab {
+                    start_data,
+                    window,
+                    initial_window_location,
+                };
+                pointer.set_grab(&mut self.state, grab, serial, Focus::Clear);
+            }
+            DecorationPart::Border(edges) => {
+                let Some(location) = self.state.floating_location(&window) else {
+                    return;
+                };
+                let initial_rect = Rectangle::new(location, window.geometry().size);
+                let grab =
+                    ResizeSurfaceGrab::start(start_data, window, resize_edge(edges), initial_rect);
+                pointer.set_grab(&mut self.state, grab, serial, Focus::Clear);
+            }
+            _ => self.state.press_window_button(&window, part),
+        }
+    }
+
+    /// Touch the decorations under a finger that just went down
+    pub(super) fn touch_down_decoration(
+        &mut self,
+        slot: TouchSlot,
+        location: Point<f64, Logical>,
+        serial: Serial,
+    ) {
+        let Some((window, part)) = self.state.decoration_under(location) else {
+            return;
+        };
+        let start_data = TouchGrabStartData {
+            focus: None,
+            slot,
+            location,
+        };
+        let touch = self.touch.clone();
+        match part {
+            DecorationPart::Titlebar => {
+                let Some(initial_window_location) = self.state.floating_location(&window) else {
+                    return;
+                };
+                let grab = TouchMoveSurfaceGrab {
+                    start_data,
+                    window,
+                    initial_window_location,
+                };
+                touch.set_grab(&mut self.state, grab, serial);
+            }
+            DecorationPart::Border(edges) => {
+                let Some(location) = self.state.floating_location(&window) else {
+                    return;
+                };
+                let initial_rect = Rectangle::new(location, window.geometry().size);
+                let grab = TouchResizeSurfaceGrab::start(
+                    start_data,
+                    window,
+                    resize_edge(edges),
+                    initial_rect,
+                );
+                touch.set_grab(&mut self.state, grab, serial);
+            }
+            _ => self.state.press_window_button(&window, part),
+        }
+    }
+}
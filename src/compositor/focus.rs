@@ -0,0 +1,130 @@
+use super::State;
+use smithay::{
+    backend::input::KeyState,
+    desktop::{PopupKind, Window, WindowSurface},
+    input::{
+        keyboard::{KeyboardTarget, KeysymHandle, ModifiersState},
+        Seat,
+    },
+    reexports::wayland_server::protocol::wl_surface::WlSurface,
+    utils::{IsAlive, Serial},
+    wayland::seat::WaylandFocus,
+    xwayland::X11Surface,
+};
+use std::borrow::Cow;
+
+/// What the keyboard is sent to. X11 windows need the X server to move its input focus as well,
+/// so they can't be focused through their `WlSurface` alone. X11 surfaces are boxed, being several times
+/// the size of a `WlSurface`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum KeyboardFocusTarget {
+    Surface(WlSurface),
+    X11(Box<X11Surface>),
+}
+
+impl KeyboardFocusTarget {
+    pub fn from_window(window: &Window) -> Self {
+        match window.underlying_surface() {
+            WindowSurface::Wayland(toplevel) => Self::Surface(toplevel.wl_surface().clone()),
+            WindowSurface::X11(surface) => Self::X11(Box::new(surface.clone())),
+        }
+    }
+}
+
+impl From<WlSurface> for KeyboardFocusTarget {
+    fn from(surface: WlSurface) -> Self {
+        Self::Surface(surface)
+    }
+}
+
+impl From<PopupKind> for KeyboardFocusTarget {
+    fn from(popup: PopupKind) -> Self {
+        Self::Surface(popup.wl_surface().clone())
+    }
+}
+
+/// Used by the popup grabs, which only ever focus popups
+impl From<KeyboardFocusTarget> for WlSurface {
+    fn from(target: KeyboardFocusTarget) -> Self {
+        target
+            .wl_surface()
+            .expect("X11 window without a surface")
+            .into_owned()
+    }
+}
+
+impl IsAlive for KeyboardFocusTarget {
+    fn alive(&self) -> bool {
+        match self {
+            Self::Surface(surface) => surface.alive(),
+            Self::X11(surface) => surface.alive(),
+        }
+    }
+}
+
+impl WaylandFocus for KeyboardFocusTarget {
+    fn wl_surface(&self) -> Option<Cow<'_, WlSurface>> {
+        match self {
+            Self::Surface(surface) => Some(Cow::Borrowed(surface)),
+            Self::X11(surface) => surface.wl_surface().map(Cow::Owned),
+        }
+    }
+}
+
+impl KeyboardTarget<State> for KeyboardFocusTarget {
+    fn enter(
+        &self,
+        seat: &Seat<State>,
+        data: &mut State,
+        keys: Vec<KeysymHandle<'_>>,
+        serial: Serial,
+    ) {
+        match self {
+            Self::Surface(surface) => KeyboardTarget::enter(surface, seat, data, keys, serial),
+            Self::X11(surface) => KeyboardTarget::enter(&**surface, seat, data, keys, serial),
+        }
+    }
+
+    fn leave(&self, seat: &Seat<State>, data: &mut State, serial: Serial) {
+        match self {
+            Self::Surface(surface) => KeyboardTarget::leave(surface, seat, data, serial),
+            Self::X11(surface) => KeyboardTarget::leave(&**surface, seat, data, serial),
+        }
+    }
+
+    fn key(
+        &self,
+        seat: &Seat<State>,
+        data: &mut State,
+        key: KeysymHandle<'_>,
+        state: KeyState,
+        serial: Serial,
+        time: u32,
+    ) {
+        match self {
+            Self::Surface(surface) => {
+                KeyboardTarget::key(surface, seat, data, key, state, serial, time)
+            }
+            Self::X11(surface) => {
+                KeyboardTarget::key(&**surface, seat, data, key, state, serial, time)
+            }
+        }
+    }
+
+    fn modifiers(
+        &self,
+        seat: &Seat<State>,
+        data: &mut State,
+        modifiers: ModifiersState,
+        serial: Serial,
+    ) {
+        match self {
+            Self::Surface(surface) => {
+                KeyboardTarget::modifiers(surface, seat, data, modifiers, serial)
+            }
+            Self::X11(surface) => {
+                KeyboardTarget::modifiers(&**surface, seat, data, modifiers, serial)
+            }
+        }
+    }
+}
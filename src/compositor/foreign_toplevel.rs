@@ -0,0 +1,414 @@
+use super::state::State;
+use smithay::{
+    delegate_foreign_toplevel_list,
+    desktop::Window,
+    output::Output,
+    reexports::{
+        wayland_protocols::xdg::shell::server::xdg_toplevel,
+        wayland_protocols_wlr::foreign_toplevel::v1::server::{
+            zwlr_foreign_toplevel_handle_v1::{self, ZwlrForeignToplevelHandleV1},
+            zwlr_foreign_toplevel_manager_v1::{self, ZwlrForeignToplevelManagerV1},
+        },
+        wayland_server::{
+            backend::ClientId, Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New,
+            Resource,
+        },
+    },
+    wayland::{
+        compositor::with_states,
+        foreign_toplevel_list::{
+            ForeignToplevelHandle, ForeignToplevelListHandler, ForeignToplevelListState,
+        },
+        shell::xdg::XdgToplevelSurfaceData,
+    },
+};
+
+/// The windows listed to task bars and docks, with ext-foreign-toplevel-list to only show them and
+/// wlr-foreign-toplevel-management to also activate, minimize, maximize and close them
+pub struct ForeignToplevelState {
+    list: ForeignToplevelListState,
+    managers: Vec<ZwlrForeignToplevelManagerV1>,
+    toplevels: Vec<ForeignToplevel>,
+}
+
+impl ForeignToplevelState {
+    pub fn new(display_handle: &DisplayHandle) -> Self {
+        display_handle.create_global::<State, ZwlrForeignToplevelManagerV1, _>(3, ());
+        Self {
+            list: ForeignToplevelListState::new::<State>(display_handle),
+            managers: Vec::new(),
+            toplevels: Vec::new(),
+        }
+    }
+
+    /// How many task bars are listening, and how many handles of windows they hold
+    #[cfg(test)]
+    pub(super) fn resource_counts(&self) -> (usize, usize) {
+        let handles = self.toplevels.iter().map(|it| it.handles.len()).sum();
+        (self.managers.len(), handles)
+    }
+}
+
+/// A window as last told to the clients
+struct ForeignToplevel {
+    window: Window,
+    info: ToplevelInfo,
+    list_handle: ForeignToplevelHandle,
+    handles: Vec<ZwlrForeignToplevelHandleV1>,
+}
+
+/// What task bars are told about a window
+#[derive(Clone, PartialEq)]
+struct ToplevelInfo {
+    title: String,
+    app_id: String,
+    maximized: bool,
+    minimized: bool,
+    activated: bool,
+    fullscreen: bool,
+    /// Outputs the window is shown on
+    outputs: Vec<Output>,
+}
+
+impl ToplevelInfo {
+    /// The state of the window in wlr-foreign-toplevel-management, an array of native endian `u32`
+    fn states(&self) -> Vec<u8> {
+        use zwlr_foreign_toplevel_handle_v1::State as S;
+        [
+            (self.maximized, S::Maximized),
+            (self.minimized, S::Minimized),
+            (self.activated, S::Activated),
+            (self.fullscreen, S::Fullscreen),
+        ]
+        .into_iter()
+        .filter(|(set, _)| *set)
+        .flat_map(|(_, state)| (state as u32).to_ne_bytes())
+        .collect()
+    }
+}
+
+/// Tell `handle` what changed since `previous`, everything without it
+fn send_info(
+    handle: &ZwlrForeignToplevelHandleV1,
+    info: &ToplevelInfo,
+    previous: Option<&ToplevelInfo>,
+) {
+    if previous.is_none_or(|previous| previous.title != info.title) {
+        handle.title(info.title.clone());
+    }
+    if previous.is_none_or(|previous| previous.app_id != info.app_id) {
+        handle.app_id(info.app_id.clone());
+    }
+    if previous.is_none_or(|previous| previous.states() != info.states()) {
+        handle.state(info.states());
+    }
+    if let Some(client) = handle.client() {
+        let no_outputs = Vec::new();
+        let previous_outputs = previous.map_or(&no_outputs, |previous| &previous.outputs);
+        for output in info
+            .outputs
+            .iter()
+            .filter(|it| !previous_outputs.contains(it))
+        {
+            for wl_output in output.client_outputs(&client) {
+                handle.output_enter(&wl_output);
+            }
+        }
+        for output in previous_outputs
+            .iter()
+            .filter(|it| !info.outputs.contains(it))
+        {
+            for wl_output in output.client_outputs(&client) {
+                handle.output_leave(&wl_output);
+            }
+        }
+    }
+    handle.done();
+}
+
+/// A handle of `manager` for a new window
+fn new_handle(
+    display_handle: &DisplayHandle,
+    manager: &ZwlrForeignToplevelManagerV1,
+    info: &ToplevelInfo,
+) -> Option<ZwlrForeignToplevelHandleV1> {
+    let client = manager.client()?;
+    let handle = client
+        .create_resource::<ZwlrForeignToplevelHandleV1, _, State>(
+            display_handle,
+            manager.version(),
+            (),
+        )
+        .ok()?;
+    manager.toplevel(&handle);
+    send_info(&handle, info, None);
+    Some(handle)
+}
+
+impl ForeignToplevelListHandler for State {
+    fn foreign_toplevel_list_state(&mut self) -> &mut ForeignToplevelListState {
+        &mut self.foreign_toplevel_state.list
+    }
+}
+
+delegate_foreign_toplevel_list!(State);
+
+impl GlobalDispatch<ZwlrForeignToplevelManagerV1, ()> for State {
+    fn bind(
+        state: &mut Self,
+        handle: &DisplayHandle,
+        _client: &Client,
+        resource: New<ZwlrForeignToplevelManagerV1>,
+        _global_data: &(),
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        let manager = data_init.init(resource, ());
+        let foreign_toplevels = &mut state.foreign_toplevel_state;
+        for toplevel in &mut foreign_toplevels.toplevels {
+            toplevel
+                .handles
+                .extend(new_handle(handle, &manager, &toplevel.info));
+        }
+        foreign_toplevels.managers.push(manager);
+    }
+}
+
+impl Dispatch<ZwlrForeignToplevelManagerV1, ()> for State {
+    fn request(
+        state: &mut Self,
+        _client: &Client,
+        resource: &ZwlrForeignToplevelManagerV1,
+        request: zwlr_foreign_toplevel_manager_v1::Request,
+        _data: &(),
+        _dhandle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, Self>,
+    ) {
+        if let zwlr_foreign_toplevel_manager_v1::Request::Stop = request {
+            state
+                .foreign_toplevel_state
+                .managers
+                .retain(|manager| manager != resource);
+            resource.finished();
+        }
+    }
+
+    // Also when the task bar disconnects without stopping
+    fn destroyed(
+        state: &mut Self,
+        _client: ClientId,
+        resource: &ZwlrForeignToplevelManagerV1,
+        _data: &(),
+    ) {
+        state
+            .foreign_toplevel_state
+            .managers
+            .retain(|manager| manager != resource);
+    }
+}
+
+impl Dispatch<ZwlrForeignToplevelHandleV1, ()> for State {
+    fn request(
+        state: &mut Self,
+        _client: &Client,
+        resource: &ZwlrForeignToplevelHandleV1,
+        request: zwlr_foreign_toplevel_handle_v1::Request,
+        _data: &(),
+        _dhandle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, Self>,
+    ) {
+        use zwlr_foreign_toplevel_handle_v1::Request;
+
+        let toplevels = &state.foreign_toplevel_state.toplevels;
+        let Some(toplevel) = toplevels
+            .iter()
+            .find(|toplevel| toplevel.handles.contains(resource))
+        else {
+            return;
+        };
+        let window = toplevel.window.clone();
+        match request {
+            Request::Activate { .. } | Request::UnsetMinimized => state.activate_window(&window),
+            Request::SetMinimized => state.minimize_window(&window),
+            Request::SetMaximized => state.set_window_state(&window, WindowState::Maximized, true),
+            Request::UnsetMaximized => {
+                state.set_window_state(&window, WindowState::Maximized, false)
+            }
+            Request::SetFullscreen { .. } => {
+                state.set_window_state(&window, WindowState::Fullscreen, true)
+            }
+            Request::UnsetFullscreen => {
+                state.set_window_state(&window, WindowState::Fullscreen, false)
+            }
+            Request::Close => {
+                if let Some(toplevel) = window.toplevel() {
+                    toplevel.send_close();
+                } else if let Some(surface) = window.x11_surface() {
+                    let _ = surface.close();
+                }
+            }
+            // Where the task bar shows the window, for minimize animations there are none of
+            _ => {}
+        }
+    }
+
+    // Either destroyed by the task bar, or with it when it disconnects
+    fn destroyed(
+        state: &mut Self,
+        _client: ClientId,
+        resource: &ZwlrForeignToplevelHandleV1,
+        _data: &(),
+    ) {
+        for toplevel in &mut state.foreign_toplevel_state.toplevels {
+            toplevel.handles.retain(|handle| handle != resource);
+        }
+    }
+}
+
+enum WindowState {
+    Maximized,
+    Fullscreen,
+}
+
+impl State {
+    /// What task bars are told about `window`
+    fn toplevel_info(&self, window: &Window) -> ToplevelInfo {
+        let minimized = self.minimized.iter().any(|(it, _)| it == window);
+        let outputs = if minimized {
+            Vec::new()
+        } else {
+            self.space.outputs_for_element(window)
+        };
+        if let Some(surface) = window.x11_surface() {
+            return ToplevelInfo {
+                title: surface.title(),
+                app_id: surface.class(),
+                maximized: surface.is_maximized(),
+                minimized,
+                activated: surface.is_activated(),
+                fullscreen: surface.is_fullscreen(),
+                outputs,
+            };
+        }
+
+        let toplevel = window.toplevel();
+        let (title, app_id) = toplevel
+            .map(|toplevel| {
+                with_states(toplevel.wl_surface(), |states| {
+                    let data = states.data_map.get::<XdgToplevelSurfaceData>();
+                    let data = data.map(|data| data.lock().unwrap());
+                    (
+                        data.as_ref().and_then(|data| data.title.clone()),
+                        data.as_ref().and_then(|data| data.app_id.clone()),
+                    )
+                })
+            })
+            .unwrap_or_default();
+        let states = toplevel
+            .map(|toplevel| toplevel.current_state().states)
+            .unwrap_or_default();
+        ToplevelInfo {
+            title: title.unwrap_or_default(),
+            app_id: app_id.unwrap_or_default(),
+            maximized: states.contains(xdg_toplevel::State::Maximized),
+            minimized,
+            activated: states.contains(xdg_toplevel::State::Activated),
+            fullscreen: states.contains(xdg_toplevel::State::Fullscreen),
+            outputs,
+        }
+    }
+
+    /// Tell the task bars which windows were opened, changed or closed since the last time
+    pub(super) fn refresh_foreign_toplevels(&mut self) {
+        let windows = self
+            .space
+            .elements()
+            .filter(|window| {
+                // Menus and tooltips of X11 apps are no windows of their own
+                window
+                    .x11_surface()
+                    .is_none_or(|surface| !surface.is_override_redirect())
+            })
+            .chain(self.minimized.iter().map(|(window, _)| window))
+            .map(|window| (window.clone(), self.toplevel_info(window)))
+            .collect::<Vec<_>>();
+
+        let state = &mut self.foreign_toplevel_state;
+        let list = &mut state.list;
+        state.toplevels.retain(|toplevel| {
+            let alive = windows.iter().any(|(window, _)| window == &toplevel.window);
+            if !alive {
+                list.remove_toplevel(&toplevel.list_handle);
+                for handle in &toplevel.handles {
+                    handle.closed();
+                }
+            }
+            alive
+        });
+
+        for (window, info) in windows {
+            let existing = state
+                .toplevels
+                .iter_mut()
+                .find(|toplevel| toplevel.window == window);
+            let Some(toplevel) = existing else {
+                let list_handle = state
+                    .list
+                    .new_toplevel::<State>(info.title.clone(), info.app_id.clone());
+                let handles = state
+                    .managers
+                    .iter()
+                    .filter_map(|manager| new_handle(&self.display_handle, manager, &info))
+                    .collect();
+                state.toplevels.push(ForeignToplevel {
+                    window,
+                    info,
+                    list_handle,
+                    handles,
+                });
+                continue;
+            };
+            if toplevel.info == info {
+                continue;
+            }
+            if toplevel.info.title != info.title || toplevel.info.app_id != info.app_id {
+                toplevel.list_handle.send_title(&info.title);
+                toplevel.list_handle.send_app_id(&info.app_id);
+                toplevel.list_handle.send_done();
+            }
+            for handle in &toplevel.handles {
+                send_info(handle, &info, Some(&toplevel.info));
+            }
+            toplevel.info = info;
+        }
+    }
+
+    /// Maximize or make a window fullscreen, or restore it, as asked by a task bar
+    fn set_window_state(&mut self, window: &Window, state: WindowState, enabled: bool) {
+        self.restore_window(window);
+        if let Some(surface) = window.x11_surface() {
+            let fullscreen = matches!(state, WindowState::Fullscreen);
+            if enabled {
+                self.cover_output_x11(surface, fullscreen);
+            }
+            let _ = if fullscreen {
+                surface.set_fullscreen(enabled)
+            } else {
+                surface.set_maximized(enabled)
+            };
+            if !enabled {
+                self.uncover_output_x11(surface);
+            }
+        } else if let Some(toplevel) = window.toplevel() {
+            let state = match state {
+                WindowState::Maximized => xdg_toplevel::State::Maximized,
+                WindowState::Fullscreen => xdg_toplevel::State::Fullscreen,
+            };
+            if enabled {
+                self.cover_output(toplevel, state);
+            } else {
+                self.uncover_output(toplevel, state);
+            }
+        }
+        self.frames.schedule();
+    }
+}
@@ -0,0 +1,606 @@
+use super::state::State;
+use smithay::{
+    desktop::{Space, Window, WindowSurface},
+    input::{
+        pointer::{
+            AxisFrame, ButtonEvent, GestureHoldBeginEvent, GestureHoldEndEvent,
+            GesturePinchBeginEvent, GesturePinchEndEvent, GesturePinchUpdateEvent,
+            GestureSwipeBeginEvent, GestureSwipeEndEvent, GestureSwipeUpdateEvent,
+            GrabStartData as PointerGrabStartData, MotionEvent, PointerGrab, PointerInnerHandle,
+            RelativeMotionEvent,
+        },
+        touch::{
+            self, GrabStartData as TouchGrabStartData, OrientationEvent, ShapeEvent, TouchGrab,
+            TouchInnerHandle,
+        },
+    },
+    reexports::{
+        wayland_protocols::xdg::shell::server::xdg_toplevel::{self, ResizeEdge},
+        wayland_server::protocol::wl_surface::WlSurface,
+    },
+    utils::{Logical, Point, Rectangle, Serial, Size},
+    wayland::{compositor, shell::xdg::SurfaceCachedState},
+};
+use std::cell::RefCell;
+
+/// The button code of the left mouse button, see linux/input-event-codes.h
+pub(super) const BTN_LEFT: u32 = 0x110;
+
+/// The remaining `PointerGrab` methods are only forwarded to the client
+macro_rules! forward_pointer_gestures {
+    () => {
+        fn relative_motion(
+            &mut self,
+            data: &mut State,
+            handle: &mut PointerInnerHandle<'_, State>,
+            focus: Option<(WlSurface, Point<f64, Logical>)>,
+            event: &RelativeMotionEvent,
+        ) {
+            handle.relative_motion(data, focus, event);
+        }
+
+        fn axis(
+            &mut self,
+            data: &mut State,
+            handle: &mut PointerInnerHandle<'_, State>,
+            details: AxisFrame,
+        ) {
+            handle.axis(data, details)
+        }
+
+        fn frame(&mut self, data: &mut State, handle: &mut PointerInnerHandle<'_, State>) {
+            handle.frame(data);
+        }
+
+        fn gesture_swipe_begin(
+            &mut self,
+            data: &mut State,
+            handle: &mut PointerInnerHandle<'_, State>,
+            event: &GestureSwipeBeginEvent,
+        ) {
+            handle.gesture_swipe_begin(data, event)
+        }
+
+        fn gesture_swipe_update(
+            &mut self,
+            data: &mut State,
+            handle: &mut PointerInnerHandle<'_, State>,
+            event: &GestureSwipeUpdateEvent,
+        ) {
+            handle.gesture_swipe_update(data, event)
+        }
+
+        fn gesture_swipe_end(
+            &mut self,
+            data: &mut State,
+            handle: &mut PointerInnerHandle<'_, State>,
+            event: &GestureSwipeEndEvent,
+        ) {
+            handle.gesture_swipe_end(data, event)
+        }
+
+        fn gesture_pinch_begin(
+            &mut self,
+            data: &mut State,
+            handle: &mut PointerInnerHandle<'_, State>,
+            event: &GesturePinchBeginEvent,
+        ) {
+            handle.gesture_pinch_begin(data, event)
+        }
+
+        fn gesture_pinch_update(
+            &mut self,
+            data: &mut State,
+            handle: &mut PointerInnerHandle<'_, State>,
+            event: &GesturePinchUpdateEvent,
+        ) {
+            handle.gesture_pinch_update(data, event)
+        }
+
+        fn gesture_pinch_end(
+            &mut self,
+            data: &mut State,
+            handle: &mut PointerInnerHandle<'_, State>,
+            event: &GesturePinchEndEvent,
+        ) {
+            handle.gesture_pinch_end(data, event)
+        }
+
+        fn gesture_hold_begin(
+            &mut self,
+            data: &mut State,
+            handle: &mut PointerInnerHandle<'_, State>,
+            event: &GestureHoldBeginEvent,
+        ) {
+            handle.gesture_hold_begin(data, event)
+        }
+
+        fn gesture_hold_end(
+            &mut self,
+            data: &mut State,
+            handle: &mut PointerInnerHandle<'_, State>,
+            event: &GestureHoldEndEvent,
+        ) {
+            handle.gesture_hold_end(data, event)
+        }
+    };
+}
+
+/// Interactive move with the pointer, started by a client or by dragging the title bar drawn above it
+pub struct MoveSurfaceGrab {
+    pub start_data: PointerGrabStartData<State>,
+    pub window: Window,
+    pub initial_window_location: Point<i32, Logical>,
+}
+
+impl PointerGrab<State> for MoveSurfaceGrab {
+    fn motion(
+        &mut self,
+        data: &mut State,
+        handle: &mut PointerInnerHandle<'_, State>,
+        _focus: Option<(WlSurface, Point<f64, Logical>)>,
+        event: &MotionEvent,
+    ) {
+        // While the grab is active, no client has pointer focus
+        handle.motion(data, None, event);
+
+        let delta = event.location - self.start_data.location;
+        let new_location = self.initial_window_location.to_f64() + delta;
+        data.move_window(&self.window, new_location.to_i32_round());
+    }
+
+    fn button(
+        &mut self,
+        data: &mut State,
+        handle: &mut PointerInnerHandle<'_, State>,
+        event: &ButtonEvent,
+    ) {
+        handle.button(data, event);
+        if !handle.current_pressed().contains(&BTN_LEFT) {
+            handle.unset_grab(self, data, event.serial, event.time, true);
+        }
+    }
+
+    forward_pointer_gestures!();
+
+    fn start_data(&self) -> &PointerGrabStartData<State> {
+        &self.start_data
+    }
+
+    fn unset(&mut self, _data: &mut State) {}
+}
+
+/// Interactive move with a finger, which is how most windows are moved on a phone. Started by a client
+/// or by dragging the title bar drawn above it.
+pub struct TouchMoveSurfaceGrab {
+    pub start_data: TouchGrabStartData<State>,
+    pub window: Window,
+    pub initial_window_location: Point<i32, Logical>,
+}
+
+impl TouchGrab<State> for TouchMoveSurfaceGrab {
+    fn down(
+        &mut self,
+        _data: &mut State,
+        _handle: &mut TouchInnerHandle<'_, State>,
+        _focus: Option<(WlSurface, Point<f64, Logical>)>,
+        _event: &touch::DownEvent,
+        _seq: Serial,
+    ) {
+    }
+
+    fn up(
+        &mut self,
+        data: &mut State,
+        handle: &mut TouchInnerHandle<'_, State>,
+        event: &touch::UpEvent,
+        seq: Serial,
+    ) {
+        if event.slot == self.start_data.slot {
+            // Let the client see the finger lifting, it saw it going down before the move started
+            handle.up(data, event, seq);
+            handle.unset_grab(self, data);
+        }
+    }
+
+    fn motion(
+        &mut self,
+        data: &mut State,
+        _handle: &mut TouchInnerHandle<'_, State>,
+        _focus: Option<(WlSurface, Point<f64, Logical>)>,
+        event: &touch::MotionEvent,
+        _seq: Serial,
+    ) {
+        if event.slot != self.start_data.slot {
+            return;
+        }
+        let delta = event.location - self.start_data.location;
+        let new_location = self.initial_window_location.to_f64() + delta;
+        data.move_window(&self.window, new_location.to_i32_round());
+    }
+
+    fn frame(
+        &mut self,
+        _data: &mut State,
+        _handle: &mut TouchInnerHandle<'_, State>,
+        _seq: Serial,
+    ) {
+    }
+
+    fn cancel(&mut self, data: &mut State, handle: &mut TouchInnerHandle<'_, State>, _seq: Serial) {
+        handle.unset_grab(self, data);
+    }
+
+    fn shape(
+        &mut self,
+        _data: &mut State,
+        _handle: &mut TouchInnerHandle<'_, State>,
+        _event: &ShapeEvent,
+        _seq: Serial,
+    ) {
+    }
+
+    fn orientation(
+        &mut self,
+        _data: &mut State,
+        _handle: &mut TouchInnerHandle<'_, State>,
+        _event: &OrientationEvent,
+        _seq: Serial,
+    ) {
+    }
+
+    fn start_data(&self) -> &TouchGrabStartData<State> {
+        &self.start_data
+    }
+
+    fn unset(&mut self, _data: &mut State) {}
+}
+
+fn has_edge(edges: ResizeEdge, edge: ResizeEdge) -> bool {
+    edges as u32 & edge as u32 != 0
+}
+
+/// A window being resized by some of its edges, by the pointer or a finger
+struct Resize {
+    window: Window,
+    edges: ResizeEdge,
+    initial_rect: Rectangle<i32, Logical>,
+    last_window_size: Size<i32, Logical>,
+}
+
+impl Resize {
+    fn start(window: Window, edges: ResizeEdge, initial_rect: Rectangle<i32, Logical>) -> Self {
+        if let Some(toplevel) = window.toplevel() {
+            toplevel.with_pending_state(|state| {
+                state.states.set(xdg_toplevel::State::Resizing);
+            });
+            toplevel.send_pending_configure();
+            ResizeSurfaceState::with(toplevel.wl_surface(), |state| {
+                *state = ResizeSurfaceState::Resizing {
+                    edges,
+                    initial_rect,
+                };
+            });
+        }
+
+        Self {
+            window,
+            edges,
+            initial_rect,
+            last_window_size: initial_rect.size,
+        }
+    }
+
+    /// Resize the window by `delta`, how far the pointer or finger moved since the start
+    fn motion(&mut self, data: &mut State, mut delta: Point<f64, Logical>) {
+        let mut width = self.initial_rect.size.w;
+        let mut height = self.initial_rect.size.h;
+
+        if has_edge(self.edges, ResizeEdge::Left) {
+            delta.x = -delta.x;
+        }
+        if has_edge(self.edges, ResizeEdge::Left) || has_edge(self.edges, ResizeEdge::Right) {
+            width = (self.initial_rect.size.w as f64 + delta.x) as i32;
+        }
+        if has_edge(self.edges, ResizeEdge::Top) {
+            delta.y = -delta.y;
+        }
+        if has_edge(self.edges, ResizeEdge::Top) || has_edge(self.edges, ResizeEdge::Bottom) {
+            height = (self.initial_rect.size.h as f64 + delta.y) as i32;
+        }
+
+        let (min_size, max_size) = match self.window.underlying_surface() {
+            WindowSurface::Wayland(toplevel) => {
+                compositor::with_states(toplevel.wl_surface(), |states| {
+                    let mut guard = states.cached_state.get::<SurfaceCachedState>();
+                    let data = guard.current();
+                    (data.min_size, data.max_size)
+                })
+            }
+            WindowSurface::X11(surface) => (
+                surface.min_size().unwrap_or_default(),
+                surface.max_size().unwrap_or_default(),
+            ),
+        };
+        // A max size of 0 means unlimited
+        let max_width = if max_size.w == 0 {
+            i32::MAX
+        } else {
+            max_size.w
+        };
+        let max_height = if max_size.h == 0 {
+            i32::MAX
+        } else {
+            max_size.h
+        };
+
+        self.last_window_size = Size::from((
+            width.max(min_size.w.max(1)).min(max_width),
+            height.max(min_size.h.max(1)).min(max_height),
+        ));
+
+        match self.window.underlying_surface() {
+            WindowSurface::Wayland(toplevel) => {
+                toplevel.with_pending_state(|state| {
+                    state.states.set(xdg_toplevel::State::Resizing);
+                    state.size = Some(self.last_window_size);
+                });
+                toplevel.send_pending_configure();
+            }
+            WindowSurface::X11(surface) => {
+                // X11 windows take the new size right away, no need to wait for their commit to move them
+                let mut location = self.initial_rect.loc;
+                if has_edge(self.edges, ResizeEdge::Left) {
+                    location.x += self.initial_rect.size.w - self.last_window_size.w;
+                }
+                if has_edge(self.edges, ResizeEdge::Top) {
+                    location.y += self.initial_rect.size.h - self.last_window_size.h;
+                }
+                let _ = surface.configure(Rectangle::new(location, self.last_window_size));
+                data.space.map_element(self.window.clone(), location, true);
+            }
+        }
+    }
+
+    /// The pointer button or the finger was released, the window takes its final size
+    fn finish(&self) {
+        let Some(toplevel) = self.window.toplevel() else {
+            return;
+        };
+        toplevel.with_pending_state(|state| {
+            state.states.unset(xdg_toplevel::State::Resizing);
+            state.size = Some(self.last_window_size);
+        });
+        toplevel.send_pending_configure();
+
+        ResizeSurfaceState::with(toplevel.wl_surface(), |state| {
+            *state = ResizeSurfaceState::WaitingForLastCommit {
+                edges: self.edges,
+                initial_rect: self.initial_rect,
+            };
+        });
+    }
+}
+
+/// Interactive resize with the pointer, started by a client or by dragging the border drawn around it
+pub struct ResizeSurfaceGrab {
+    start_data: PointerGrabStartData<State>,
+    resize: Resize,
+}
+
+impl ResizeSurfaceGrab {
+    pub fn start(
+        start_data: PointerGrabStartData<State>,
+        window: Window,
+        edges: ResizeEdge,
+        initial_rect: Rectangle<i32, Logical>,
+    ) -> Self {
+        Self {
+            start_data,
+            resize: Resize::start(window, edges, initial_rect),
+        }
+    }
+}
+
+impl PointerGrab<State> for ResizeSurfaceGrab {
+    fn motion(
+        &mut self,
+        data: &mut State,
+        handle: &mut PointerInnerHandle<'_, State>,
+        _focus: Option<(WlSurface, Point<f64, Logical>)>,
+        event: &MotionEvent,
+    ) {
+        // While the grab is active, no client has pointer focus
+        handle.motion(data, None, event);
+        self.resize
+            .motion(data, event.location - self.start_data.location);
+    }
+
+    fn button(
+        &mut self,
+        data: &mut State,
+        handle: &mut PointerInnerHandle<'_, State>,
+        event: &ButtonEvent,
+    ) {
+        handle.button(data, event);
+        if handle.current_pressed().contains(&BTN_LEFT) {
+            return;
+        }
+        handle.unset_grab(self, data, event.serial, event.time, true);
+        self.resize.finish();
+    }
+
+    forward_pointer_gestures!();
+
+    fn start_data(&self) -> &PointerGrabStartData<State> {
+        &self.start_data
+    }
+
+    fn unset(&mut self, _data: &mut State) {}
+}
+
+/// Interactive resize with a finger, started by dragging the border drawn around the window
+pub struct TouchResizeSurfaceGrab {
+    start_data: TouchGrabStartData<State>,
+    resize: Resize,
+}
+
+impl TouchResizeSurfaceGrab {
+    pub fn start(
+        start_data: TouchGrabStartData<State>,
+        window: Window,
+        edges: ResizeEdge,
+        initial_rect: Rectangle<i32, Logical>,
+    ) -> Self {
+        Self {
+            start_data,
+            resize: Resize::start(window, edges, initial_rect),
+        }
+    }
+}
+
+impl TouchGrab<State> for TouchResizeSurfaceGrab {
+    fn down(
+        &mut self,
+        _data: &mut State,
+        _handle: &mut TouchInnerHandle<'_, State>,
+        _focus: Option<(WlSurface, Point<f64, Logical>)>,
+        _event: &touch::DownEvent,
+        _seq: Serial,
+    ) {
+    }
+
+    fn up(
+        &mut self,
+        data: &mut State,
+        handle: &mut TouchInnerHandle<'_, State>,
+        event: &touch::UpEvent,
+        _seq: Serial,
+    ) {
+        if event.slot == self.start_data.slot {
+            handle.unset_grab(self, data);
+            self.resize.finish();
+        }
+    }
+
+    fn motion(
+        &mut self,
+        data: &mut State,
+        _handle: &mut TouchInnerHandle<'_, State>,
+        _focus: Option<(WlSurface, Point<f64, Logical>)>,
+        event: &touch::MotionEvent,
+        _seq: Serial,
+    ) {
+        if event.slot == self.start_data.slot {
+            self.resize
+                .motion(data, event.location - self.start_data.location);
+        }
+    }
+
+    fn frame(
+        &mut self,
+        _data: &mut State,
+        _handle: &mut TouchInnerHandle<'_, State>,
+        _seq: Serial,
+    ) {
+    }
+
+    fn cancel(&mut self, data: &mut State, handle: &mut TouchInnerHandle<'_, State>, _seq: Serial) {
+        handle.unset_grab(self, data);
+        self.resize.finish();
+    }
+
+    fn shape(
+        &mut self,
+        _data: &mut State,
+        _handle: &mut TouchInnerHandle<'_, State>,
+        _event: &ShapeEvent,
+        _seq: Serial,
+    ) {
+    }
+
+    fn orientation(
+        &mut self,
+        _data: &mut State,
+        _handle: &mut TouchInnerHandle<'_, State>,
+        _event: &OrientationEvent,
+        _seq: Serial,
+    ) {
+    }
+
+    fn start_data(&self) -> &TouchGrabStartData<State> {
+        &self.start_data
+    }
+
+    fn unset(&mut self, _data: &mut State) {}
+}
+
+/// State of a resize, stored in the surface data so the commit handler can find it
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default)]
+enum ResizeSurfaceState {
+    #[default]
+    Idle,
+    Resizing {
+        edges: ResizeEdge,
+        initial_rect: Rectangle<i32, Logical>,
+    },
+    /// The grab is released, the window still has to commit its final size
+    WaitingForLastCommit {
+        edges: ResizeEdge,
+        initial_rect: Rectangle<i32, Logical>,
+    },
+}
+
+impl ResizeSurfaceState {
+    fn with<F, T>(surface: &WlSurface, f: F) -> T
+    where
+        F: FnOnce(&mut Self) -> T,
+    {
+        compositor::with_states(surface, |states| {
+            states.data_map.insert_if_missing(RefCell::<Self>::default);
+            let state = states.data_map.get::<RefCell<Self>>().unwrap();
+            f(&mut state.borrow_mut())
+        })
+    }
+
+    fn commit(&mut self) -> Option<(ResizeEdge, Rectangle<i32, Logical>)> {
+        match *self {
+            Self::Resizing {
+                edges,
+                initial_rect,
+            } => Some((edges, initial_rect)),
+            Self::WaitingForLastCommit {
+                edges,
+                initial_rect,
+            } => {
+                *self = Self::Idle;
+                Some((edges, initial_rect))
+            }
+            Self::Idle => None,
+        }
+    }
+}
+
+/// Windows resized by their top or left edge have to move so that the opposite edge stays in place.
+/// Called on every commit, as that is when the new size is known.
+pub fn handle_resize_commit(space: &mut Space<Window>, surface: &WlSurface) -> Option<()> {
+    let window = space
+        .elements()
+        .find(|window| window.toplevel().map(|it| it.wl_surface()) == Some(surface))
+        .cloned()?;
+    let mut location = space.element_location(&window)?;
+    let size = window.geometry().size;
+
+    let (edges, initial_rect) = ResizeSurfaceState::with(surface, |state| state.commit())?;
+    if has_edge(edges, ResizeEdge::Left) {
+        location.x = initial_rect.loc.x + (initial_rect.size.w - size.w);
+    }
+    if has_edge(edges, ResizeEdge::Top) {
+        location.y = initial_rect.loc.y + (initial_rect.size.h - size.h);
+    }
+    if has_edge(edges, ResizeEdge::Left) || has_edge(edges, ResizeEdge::Top) {
+        space.map_element(window, location, false);
+    }
+
+    Some(())
+}
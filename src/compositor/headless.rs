@@ -0,0 +1,960 @@
+//! Draws the desktop off screen with pixman, so that the compositor runs on any Linux machine, without a GPU
+//! nor an Android device. Used by the tests, which drive it with real Wayland clients.
+use super::{Compositor, CursorTheme, State, CLEAR_COLOR};
+use crate::core::frame_scheduler::DEFAULT_REFRESH_MHZ;
+use smithay::{
+    backend::{
+        allocator::Fourcc,
+        renderer::{
+            damage::OutputDamageTracker, pixman::PixmanRenderer, Bind, ExportMem, Offscreen,
+        },
+    },
+    output::{Mode, Output, PhysicalProperties, Scale, Subpixel},
+    reexports::{pixman::Image, wayland_server::ListeningSocket},
+    utils::{Physical, Rectangle, Size, Transform},
+};
+use std::{error::Error, path::PathBuf, time::Instant};
+
+pub struct HeadlessBackend {
+    pub compositor: Compositor,
+    pub cursor_theme: CursorTheme,
+    renderer: PixmanRenderer,
+    /// The screen, kept between frames so that only the damage is redrawn
+    buffer: Image<'static, 'static>,
+    damage_tracker: OutputDamageTracker,
+}
+
+impl HeadlessBackend {
+    /// A compositor accepting clients on `socket_path`, showing them on an output of `size` pixels
+    pub fn new(
+        socket_path: impl Into<PathBuf>,
+        size: Size<i32, Physical>,
+    ) -> Result<Self, Box<dyn Error>> {
+        let listener = ListeningSocket::bind_absolute(socket_path.into())?;
+        let mut compositor = Compositor::new(listener)?;
+
+        let output = Output::new(
+            "Headless".into(),
+            PhysicalProperties {
+                size: (0, 0).into(),
+                subpixel: Subpixel::Unknown,
+                make: "Local Desktop".into(),
+                model: "Headless".into(),
+            },
+        );
+        let _global = output.create_global::<State>(&compositor.display.handle());
+        output.change_current_state(
+            Some(Mode {
+                size,
+                refresh: DEFAULT_REFRESH_MHZ,
+            }),
+            Some(Transform::Normal),
+            Some(Scale::Integer(1)),
+            Some((0, 0).into()),
+        );
+        compositor.state.space.map_output(&output, (0, 0));
+        compositor.output = Some(output);
+        compositor.state.size = (size.w, size.h).into();
+
+        let mut renderer = PixmanRenderer::new()?;
+        let buffer: Image<'static, 'static> =
+            renderer.create_buffer(Fourcc::Argb8888, (size.w, size.h).into())?;
+        Ok(Self {
+            compositor,
+            cursor_theme: CursorTheme::default(),
+            renderer,
+            buffer,
+            damage_tracker: OutputDamageTracker::new(size, 1.0, Transform::Normal),
+        })
+    }
+
+    /// Draw what changed since the last frame, then let the clients draw their next one.
+    /// Returns whether anything was drawn.
+    pub fn render(&mut self) -> Result<bool, Box<dyn Error>> {
+        let compositor = &mut self.compositor;
+        let output = compositor.output.clone().ok_or("The output is gone")?;
+        let elements =
+            compositor
+                .state
+                .render_elements(&mut self.renderer, &output, &mut self.cursor_theme);
+
+        let mut framebuffer = self.renderer.bind(&mut self.buffer)?;
+        // The buffer always holds the previous frame
+        let result = self.damage_tracker.render_output(
+            &mut self.renderer,
+            &mut framebuffer,
+            1,
+            &elements,
+            CLEAR_COLOR,
+        )?;
+        let damaged = result.damage.is_some();
+        if damaged {
+            // Off screen, the frame is shown as soon as it is drawn
+            let feedback = compositor
+                .state
+                .take_presentation_feedback(&output, &result.states);
+            compositor.state.frame_presented(feedback, None);
+        }
+        compositor
+            .state
+            .render_screencopies::<_, Image<'static, 'static>>(
+                &mut self.renderer,
+                &output,
+                &mut self.cursor_theme,
+                Transform::Normal,
+            );
+
+        compositor.state.frames.frame_done(Instant::now(), damaged);
+        compositor.state.send_frames(compositor.time());
+        compositor.dispatch();
+        Ok(damaged)
+    }
+
+    /// The pixel at `x`, `y` of the last frame, as ARGB
+    pub fn pixel(&mut self, x: i32, y: i32) -> Result<u32, Box<dyn Error>> {
+        let framebuffer = self.renderer.bind(&mut self.buffer)?;
+        let mapping = self.renderer.copy_framebuffer(
+            &framebuffer,
+            Rectangle::new((x, y).into(), (1, 1).into()),
+            Fourcc::Argb8888,
+        )?;
+        let data = self.renderer.map_texture(&mapping)?;
+        Ok(u32::from_le_bytes(data[..4].try_into()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use smithay::backend::input::{ButtonState, KeyState, TouchSlot};
+    use std::{
+        fs::File,
+        io::Write,
+        os::{
+            fd::AsFd,
+            unix::{fs::FileExt, net::UnixStream},
+        },
+        path::Path,
+        sync::mpsc::{self, Receiver, RecvTimeoutError, Sender},
+        thread,
+        time::Duration,
+    };
+    use wayland_client::{
+        delegate_noop, event_created_child,
+        globals::{registry_queue_init, GlobalListContents},
+        protocol::{
+            wl_buffer, wl_compositor, wl_keyboard, wl_output, wl_pointer, wl_registry, wl_seat,
+            wl_shm, wl_shm_pool, wl_surface, wl_touch,
+        },
+        Connection, Dispatch, QueueHandle, WEnum,
+    };
+    use wayland_protocols::xdg::shell::client::{xdg_surface, xdg_toplevel, xdg_wm_base};
+    use wayland_protocols_wlr::{
+        foreign_toplevel::v1::client::{
+            zwlr_foreign_toplevel_handle_v1, zwlr_foreign_toplevel_manager_v1,
+        },
+        screencopy::v1::client::{zwlr_screencopy_frame_v1, zwlr_screencopy_manager_v1},
+        virtual_pointer::v1::client::{zwlr_virtual_pointer_manager_v1, zwlr_virtual_pointer_v1},
+    };
+
+    const WIDTH: i32 = 100;
+    const HEIGHT: i32 = 80;
+    const GREEN: u32 = 0x00ff00;
+
+    #[derive(Debug, PartialEq)]
+    enum ClientEvent {
+        /// The window has its buffer attached and committed
+        Committed,
+        PointerEnter(f64, f64),
+        KeyboardEnter,
+        /// Evdev code of a pressed key
+        Key(u32),
+        /// The window asked to be resized by the finger that touched it
+        ResizeRequested,
+        /// The window was asked to take this size
+        Configured(i32, i32),
+        /// A screenshot was copied, with its flags, damage and the RGB of the given pixels
+        Copied {
+            flags: u32,
+            damage: Vec<(u32, u32, u32, u32)>,
+            pixels: Vec<u32>,
+        },
+        /// A task bar was told about a window, or that it changed
+        Toplevel {
+            title: String,
+            minimized: bool,
+        },
+        ToplevelClosed,
+    }
+
+    /// A client with a single window, filled with one color, resized from its bottom right corner when touched
+    struct TestClient {
+        seat: wl_seat::WlSeat,
+        surface: wl_surface::WlSurface,
+        toplevel: xdg_toplevel::XdgToplevel,
+        buffer: Option<wl_buffer::WlBuffer>,
+        events: Sender<ClientEvent>,
+    }
+
+    /// Connect a client to `socket_path` and open its window, from a thread of its own
+    fn spawn_client(socket_path: &Path, color: u32) -> Receiver<ClientEvent> {
+        let stream = UnixStream::connect(socket_path).unwrap();
+        let (events, received) = mpsc::channel();
+        thread::spawn(move || {
+            let connection = Connection::from_socket(stream).unwrap();
+            let (globals, mut queue) = registry_queue_init::<TestClient>(&connection).unwrap();
+            let qh = queue.handle();
+            let compositor: wl_compositor::WlCompositor = globals.bind(&qh, 1..=6, ()).unwrap();
+            let shm: wl_shm::WlShm = globals.bind(&qh, 1..=1, ()).unwrap();
+            let wm_base: xdg_wm_base::XdgWmBase = globals.bind(&qh, 1..=6, ()).unwrap();
+            let seat: wl_seat::WlSeat = globals.bind(&qh, 1..=7, ()).unwrap();
+
+            let mut file = tempfile::tempfile().unwrap();
+            let pixels = color.to_le_bytes().repeat((WIDTH * HEIGHT) as usize);
+            file.write_all(&pixels).unwrap();
+            let pool = shm.create_pool(file.as_fd(), pixels.len() as i32, &qh, ());
+            let buffer = pool.create_buffer(
+                0,
+                WIDTH,
+                HEIGHT,
+                WIDTH * 4,
+                wl_shm::Format::Xrgb8888,
+                &qh,
+                (),
+            );
+
+            let surface = compositor.create_surface(&qh, ());
+            let xdg_surface = wm_base.get_xdg_surface(&surface, &qh, ());
+            let toplevel = xdg_surface.get_toplevel(&qh, ());
+            toplevel.set_title("test".into());
+            surface.commit();
+
+            let mut client = TestClient {
+                seat,
+                surface,
+                toplevel,
+                buffer: Some(buffer),
+                events,
+            };
+            // Until the compositor goes away at the end of the test
+            while queue.blocking_dispatch(&mut client).is_ok() {}
+        });
+        received
+    }
+
+    /// A client taking a screenshot of the whole output, with the damage since the last one
+    struct ScreencopyClient {
+        shm: wl_shm::WlShm,
+        file: File,
+        buffer: Option<wl_buffer::WlBuffer>,
+        stride: u32,
+        flags: u32,
+        damage: Vec<(u32, u32, u32, u32)>,
+        /// Where to read the pixels sent with `ClientEvent::Copied`
+        samples: Vec<(u32, u32)>,
+        events: Sender<ClientEvent>,
+    }
+
+    /// Connect a client to `socket_path` and capture the output from a thread of its own
+    fn spawn_screencopy(socket_path: &Path, samples: Vec<(u32, u32)>) -> Receiver<ClientEvent> {
+        let stream = UnixStream::connect(socket_path).unwrap();
+        let (events, received) = mpsc::channel();
+        thread::spawn(move || {
+            let connection = Connection::from_socket(stream).unwrap();
+            let (globals, mut queue) =
+                registry_queue_init::<ScreencopyClient>(&connection).unwrap();
+            let qh = queue.handle();
+            let shm: wl_shm::WlShm = globals.bind(&qh, 1..=1, ()).unwrap();
+            let output: wl_output::WlOutput = globals.bind(&qh, 1..=4, ()).unwrap();
+            let manager: zwlr_screencopy_manager_v1::ZwlrScreencopyManagerV1 =
+                globals.bind(&qh, 3..=3, ()).unwrap();
+            manager.capture_output(0, &output, &qh, ());
+
+            let mut client = ScreencopyClient {
+                shm,
+                file: tempfile::tempfile().unwrap(),
+                buffer: None,
+                stride: 0,
+                flags: 0,
+                damage: Vec::new(),
+                samples,
+                events,
+            };
+            while queue.blocking_dispatch(&mut client).is_ok() {}
+        });
+        received
+    }
+
+    /// A task bar minimizing the windows it is told about, then closing them
+    struct TaskBarClient {
+        title: String,
+        minimized: bool,
+        /// Disconnect once the window is closed
+        done: bool,
+        events: Sender<ClientEvent>,
+    }
+
+    /// Connect a task bar to `socket_path`, from a thread of its own
+    fn spawn_task_bar(socket_path: &Path) -> Receiver<ClientEvent> {
+        let stream = UnixStream::connect(socket_path).unwrap();
+        let (events, received) = mpsc::channel();
+        thread::spawn(move || {
+            let connection = Connection::from_socket(stream).unwrap();
+            let (globals, mut queue) = registry_queue_init::<TaskBarClient>(&connection).unwrap();
+            let qh = queue.handle();
+            let _manager: zwlr_foreign_toplevel_manager_v1::ZwlrForeignToplevelManagerV1 =
+                globals.bind(&qh, 3..=3, ()).unwrap();
+
+            let mut client = TaskBarClient {
+                title: String::new(),
+                minimized: false,
+                done: false,
+                events,
+            };
+            while !client.done && queue.blocking_dispatch(&mut client).is_ok() {}
+        });
+        received
+    }
+
+    /// A touch overlay moving the pointer to `x`, `y` and clicking there, from a thread of its own
+    fn spawn_virtual_pointer(socket_path: &Path, x: u32, y: u32) {
+        let stream = UnixStream::connect(socket_path).unwrap();
+        thread::spawn(move || {
+            let connection = Connection::from_socket(stream).unwrap();
+            let (globals, mut queue) =
+                registry_queue_init::<VirtualPointerClient>(&connection).unwrap();
+            let qh = queue.handle();
+            let manager: zwlr_virtual_pointer_manager_v1::ZwlrVirtualPointerManagerV1 =
+                globals.bind(&qh, 1..=2, ()).unwrap();
+            let pointer = manager.create_virtual_pointer(None, &qh, ());
+
+            // Like wlrctl, the extents are the size of the output
+            pointer.motion_absolute(0, x, y, 320, 240);
+            pointer.frame();
+            for state in [
+                wl_pointer::ButtonState::Pressed,
+                wl_pointer::ButtonState::Released,
+            ] {
+                pointer.button(0, 0x110, state);
+                pointer.frame();
+            }
+            while queue.blocking_dispatch(&mut VirtualPointerClient).is_ok() {}
+        });
+    }
+
+    /// Dispatch the compositor until the client got `expected`
+    fn wait_for(
+        backend: &mut HeadlessBackend,
+        events: &Receiver<ClientEvent>,
+        expected: ClientEvent,
+    ) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            backend.compositor.dispatch();
+            match events.recv_timeout(Duration::from_millis(10)) {
+                Ok(event) if event == expected => return,
+                Ok(_) => {}
+                Err(RecvTimeoutError::Timeout) => {
+                    assert!(
+                        Instant::now() < deadline,
+                        "Timed out waiting for {:?}",
+                        expected
+                    );
+                }
+                Err(e) => panic!("The client stopped: {}", e),
+            }
+        }
+    }
+
+    /// Draw frames until the screencopy client copied one
+    fn wait_for_copy(backend: &mut HeadlessBackend, copies: &Receiver<ClientEvent>) -> ClientEvent {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            backend.render().unwrap();
+            match copies.recv_timeout(Duration::from_millis(10)) {
+                Ok(event) => return event,
+                Err(RecvTimeoutError::Timeout) => {
+                    assert!(Instant::now() < deadline, "Timed out waiting for the copy")
+                }
+                Err(e) => panic!("The client stopped: {}", e),
+            }
+        }
+    }
+
+    fn headless(socket_dir: &Path) -> HeadlessBackend {
+        HeadlessBackend::new(socket_dir.join("wayland-test"), (320, 240).into()).unwrap()
+    }
+
+    #[test]
+    fn should_draw_client_windows() {
+        let socket_dir = tempfile::tempdir().unwrap();
+        let mut backend = headless(socket_dir.path());
+        let events = spawn_client(&socket_dir.path().join("wayland-test"), GREEN);
+        wait_for(&mut backend, &events, ClientEvent::Committed);
+        backend.compositor.dispatch();
+
+        assert!(backend.render().unwrap());
+        // New windows go to the top left corner
+        assert_eq!(
+            backend.pixel(WIDTH / 2, HEIGHT / 2).unwrap() & 0xffffff,
+            GREEN
+        );
+        let [blue, green, red, _] = backend
+            .pixel(WIDTH + 10, HEIGHT + 10)
+            .unwrap()
+            .to_le_bytes();
+        assert_eq!((blue, green), (0, 0));
+        assert!(red.abs_diff(26) <= 1, "Unexpected background {}", red);
+
+        // Nothing changed, nothing is drawn
+        assert!(!backend.render().unwrap());
+        let frames = &backend.compositor.state.frames;
+        assert_eq!((frames.rendered, frames.skipped), (1, 1));
+        assert!(frames.is_idle());
+    }
+
+    #[test]
+    fn should_send_input_to_the_window_under_the_pointer() {
+        let socket_dir = tempfile::tempdir().unwrap();
+        let mut backend = headless(socket_dir.path());
+        let events = spawn_client(&socket_dir.path().join("wayland-test"), GREEN);
+        wait_for(&mut backend, &events, ClientEvent::Committed);
+        backend.compositor.dispatch();
+        backend.render().unwrap();
+
+        let time = backend.compositor.time();
+        backend.compositor.pointer_motion((30.0, 20.0).into(), time);
+        assert!(!backend.compositor.state.frames.is_idle());
+        wait_for(&mut backend, &events, ClientEvent::PointerEnter(30.0, 20.0));
+
+        // Focus follows click
+        backend
+            .compositor
+            .pointer_button(0x110, ButtonState::Pressed, time);
+        backend
+            .compositor
+            .pointer_button(0x110, ButtonState::Released, time);
+        wait_for(&mut backend, &events, ClientEvent::KeyboardEnter);
+
+        // Xkb keycodes are evdev ones shifted by 8, 30 is the A key
+        backend.compositor.key(38u32.into(), KeyState::Pressed);
+        wait_for(&mut backend, &events, ClientEvent::Key(30));
+        backend.compositor.key(38u32.into(), KeyState::Released);
+    }
+
+    #[test]
+    fn should_resize_windows_by_touch() {
+        let socket_dir = tempfile::tempdir().unwrap();
+        let mut backend = headless(socket_dir.path());
+        let events = spawn_client(&socket_dir.path().join("wayland-test"), GREEN);
+        wait_for(&mut backend, &events, ClientEvent::Committed);
+        backend.compositor.dispatch();
+        backend.render().unwrap();
+
+        let slot = TouchSlot::from(Some(0));
+        backend.compositor.touch_down(slot, (50.0, 40.0).into());
+        // The client answers the touch with a resize request, the finger then drags the corner
+        wait_for(&mut backend, &events, ClientEvent::ResizeRequested);
+        backend.compositor.dispatch();
+        backend.compositor.touch_motion(slot, (70.0, 50.0).into());
+        wait_for(
+            &mut backend,
+            &events,
+            ClientEvent::Configured(WIDTH + 20, HEIGHT + 10),
+        );
+        backend.compositor.touch_up(slot);
+    }
+
+    #[test]
+    fn should_keep_minimized_windows_when_tapping_the_desktop() {
+        let socket_dir = tempfile::tempdir().unwrap();
+        let mut backend = headless(socket_dir.path());
+        let events = spawn_client(&socket_dir.path().join("wayland-test"), GREEN);
+        wait_for(&mut backend, &events, ClientEvent::Committed);
+        backend.compositor.dispatch();
+
+        let state = &mut backend.compositor.state;
+        let window = state.space.elements().next().unwrap().clone();
+        state.minimize_window(&window);
+        let slot = TouchSlot::from(Some(0));
+        backend.compositor.touch_down(slot, (300.0, 200.0).into());
+        backend.compositor.touch_up(slot);
+
+        let state = &backend.compositor.state;
+        assert_eq!(state.space.elements().count(), 0);
+        assert_eq!(state.minimized.len(), 1);
+    }
+
+    #[test]
+    fn should_cascade_unmaximized_windows_that_were_never_floating() {
+        use smithay::reexports::wayland_protocols::xdg::shell::server::xdg_toplevel::State;
+
+        let socket_dir = tempfile::tempdir().unwrap();
+        let mut backend = headless(socket_dir.path());
+        let events = spawn_client(&socket_dir.path().join("wayland-test"), GREEN);
+        wait_for(&mut backend, &events, ClientEvent::Committed);
+        backend.compositor.dispatch();
+
+        // No geometry from before it was maximized to go back to
+        let state = &mut backend.compositor.state;
+        let window = state.space.elements().next().unwrap().clone();
+        state.uncover_output(window.toplevel().unwrap(), State::Maximized);
+        // Cascaded by one step from the first window, rather than at the corner under the panels
+        assert_eq!(state.space.element_location(&window), Some((32, 32).into()));
+    }
+
+    #[test]
+    fn should_copy_the_output_with_its_damage() {
+        let socket_dir = tempfile::tempdir().unwrap();
+        let socket_path = socket_dir.path().join("wayland-test");
+        let mut backend = headless(socket_dir.path());
+        let events = spawn_client(&socket_path, GREEN);
+        wait_for(&mut backend, &events, ClientEvent::Committed);
+        backend.compositor.dispatch();
+        backend.render().unwrap();
+
+        // Inside the window, and below it in the same column
+        let (x, y) = ((WIDTH / 2) as u32, (HEIGHT / 2) as u32);
+        let copies = spawn_screencopy(&socket_path, vec![(x, y), (x, y + HEIGHT as u32)]);
+        let copied = wait_for_copy(&mut backend, &copies);
+
+        let ClientEvent::Copied {
+            flags,
+            damage,
+            pixels,
+        } = copied
+        else {
+            panic!("Unexpected {:?}", copied);
+        };
+        // Drawn top-down off screen, and everything changed since nothing was copied before
+        assert_eq!(flags, 0);
+        assert_eq!(damage, [(0, 0, 320, 240)]);
+        assert_eq!(pixels[0], GREEN);
+        assert_ne!(pixels[1], GREEN);
+    }
+
+    #[test]
+    fn should_track_the_damage_of_each_screencopy_client() {
+        let socket_dir = tempfile::tempdir().unwrap();
+        let socket_path = socket_dir.path().join("wayland-test");
+        let mut backend = headless(socket_dir.path());
+        let events = spawn_client(&socket_path, GREEN);
+        wait_for(&mut backend, &events, ClientEvent::Committed);
+        backend.compositor.dispatch();
+        backend.render().unwrap();
+
+        // Nothing changes between the copies, yet the second client never copied anything before
+        for _ in 0..2 {
+            let copies = spawn_screencopy(&socket_path, Vec::new());
+            let ClientEvent::Copied { damage, .. } = wait_for_copy(&mut backend, &copies) else {
+                panic!("Unexpected event");
+            };
+            assert_eq!(damage, [(0, 0, 320, 240)]);
+        }
+    }
+
+    #[test]
+    fn should_let_task_bars_minimize_and_close_windows() {
+        let socket_dir = tempfile::tempdir().unwrap();
+        let socket_path = socket_dir.path().join("wayland-test");
+        let mut backend = headless(socket_dir.path());
+        let events = spawn_client(&socket_path, GREEN);
+        wait_for(&mut backend, &events, ClientEvent::Committed);
+
+        let task_bar = spawn_task_bar(&socket_path);
+        let toplevel = |minimized| ClientEvent::Toplevel {
+            title: "test".into(),
+            minimized,
+        };
+        wait_for(&mut backend, &task_bar, toplevel(false));
+        assert_eq!(
+            backend
+                .compositor
+                .state
+                .foreign_toplevel_state
+                .resource_counts(),
+            (1, 1)
+        );
+        wait_for(&mut backend, &task_bar, toplevel(true));
+        assert_eq!(backend.compositor.state.space.elements().count(), 0);
+        wait_for(&mut backend, &task_bar, ClientEvent::ToplevelClosed);
+
+        // The task bar disconnected, leaving nothing behind
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while backend
+            .compositor
+            .state
+            .foreign_toplevel_state
+            .resource_counts()
+            != (0, 0)
+        {
+            assert!(
+                Instant::now() < deadline,
+                "The task bar was never forgotten"
+            );
+            backend.compositor.dispatch();
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn should_move_the_pointer_of_virtual_pointers() {
+        let socket_dir = tempfile::tempdir().unwrap();
+        let socket_path = socket_dir.path().join("wayland-test");
+        let mut backend = headless(socket_dir.path());
+        let events = spawn_client(&socket_path, GREEN);
+        wait_for(&mut backend, &events, ClientEvent::Committed);
+        backend.compositor.dispatch();
+
+        spawn_virtual_pointer(&socket_path, 30, 20);
+        wait_for(&mut backend, &events, ClientEvent::PointerEnter(30.0, 20.0));
+        // Clicking focuses the window like a real mouse
+        wait_for(&mut backend, &events, ClientEvent::KeyboardEnter);
+    }
+
+    struct VirtualPointerClient;
+
+    impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for VirtualPointerClient {
+        fn event(
+            _: &mut Self,
+            _: &wl_registry::WlRegistry,
+            _: wl_registry::Event,
+            _: &GlobalListContents,
+            _: &Connection,
+            _: &QueueHandle<Self>,
+        ) {
+        }
+    }
+
+    impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for TaskBarClient {
+        fn event(
+            _: &mut Self,
+            _: &wl_registry::WlRegistry,
+            _: wl_registry::Event,
+            _: &GlobalListContents,
+            _: &Connection,
+            _: &QueueHandle<Self>,
+        ) {
+        }
+    }
+
+    impl Dispatch<zwlr_foreign_toplevel_manager_v1::ZwlrForeignToplevelManagerV1, ()>
+        for TaskBarClient
+    {
+        fn event(
+            _: &mut Self,
+            _: &zwlr_foreign_toplevel_manager_v1::ZwlrForeignToplevelManagerV1,
+            _: zwlr_foreign_toplevel_manager_v1::Event,
+            _: &(),
+            _: &Connection,
+            _: &QueueHandle<Self>,
+        ) {
+        }
+
+        event_created_child!(
+            TaskBarClient,
+            zwlr_foreign_toplevel_manager_v1::ZwlrForeignToplevelManagerV1,
+            [
+                zwlr_foreign_toplevel_manager_v1::EVT_TOPLEVEL_OPCODE => (
+                    zwlr_foreign_toplevel_handle_v1::ZwlrForeignToplevelHandleV1,
+                    ()
+                ),
+            ]
+        );
+    }
+
+    impl Dispatch<zwlr_foreign_toplevel_handle_v1::ZwlrForeignToplevelHandleV1, ()> for TaskBarClient {
+        fn event(
+            client: &mut Self,
+            handle: &zwlr_foreign_toplevel_handle_v1::ZwlrForeignToplevelHandleV1,
+            event: zwlr_foreign_toplevel_handle_v1::Event,
+            _: &(),
+            _: &Connection,
+            _: &QueueHandle<Self>,
+        ) {
+            use zwlr_foreign_toplevel_handle_v1::{Event, State};
+            match event {
+                Event::Title { title } => client.title = title,
+                Event::State { state } => {
+                    client.minimized = state
+                        .chunks_exact(4)
+                        .any(|it| it == (State::Minimized as u32).to_ne_bytes());
+                }
+                Event::Done => {
+                    if client.minimized {
+                        handle.close();
+                    } else {
+                        handle.set_minimized();
+                    }
+                    let _ = client.events.send(ClientEvent::Toplevel {
+                        title: client.title.clone(),
+                        minimized: client.minimized,
+                    });
+                }
+                Event::Closed => {
+                    handle.destroy();
+                    client.done = true;
+                    let _ = client.events.send(ClientEvent::ToplevelClosed);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for ScreencopyClient {
+        fn event(
+            _: &mut Self,
+            _: &wl_registry::WlRegistry,
+            _: wl_registry::Event,
+            _: &GlobalListContents,
+            _: &Connection,
+            _: &QueueHandle<Self>,
+        ) {
+        }
+    }
+
+    impl Dispatch<zwlr_screencopy_frame_v1::ZwlrScreencopyFrameV1, ()> for ScreencopyClient {
+        fn event(
+            client: &mut Self,
+            frame: &zwlr_screencopy_frame_v1::ZwlrScreencopyFrameV1,
+            event: zwlr_screencopy_frame_v1::Event,
+            _: &(),
+            _: &Connection,
+            qh: &QueueHandle<Self>,
+        ) {
+            match event {
+                zwlr_screencopy_frame_v1::Event::Buffer {
+                    format: WEnum::Value(format),
+                    width,
+                    height,
+                    stride,
+                } => {
+                    client.stride = stride;
+                    let len = stride * height;
+                    client.file.set_len(len as u64).unwrap();
+                    let pool = client
+                        .shm
+                        .create_pool(client.file.as_fd(), len as i32, qh, ());
+                    client.buffer = Some(pool.create_buffer(
+                        0,
+                        width as i32,
+                        height as i32,
+                        stride as i32,
+                        format,
+                        qh,
+                        (),
+                    ));
+                }
+                zwlr_screencopy_frame_v1::Event::BufferDone => {
+                    frame.copy_with_damage(client.buffer.as_ref().unwrap());
+                }
+                zwlr_screencopy_frame_v1::Event::Flags {
+                    flags: WEnum::Value(flags),
+                } => client.flags = flags.bits(),
+                zwlr_screencopy_frame_v1::Event::Damage {
+                    x,
+                    y,
+                    width,
+                    height,
+                } => client.damage.push((x, y, width, height)),
+                zwlr_screencopy_frame_v1::Event::Ready { .. } => {
+                    let pixels = client
+                        .samples
+                        .iter()
+                        .map(|(x, y)| {
+                            let mut pixel = [0; 4];
+                            let offset = y * client.stride + x * 4;
+                            client
+                                .file
+                                .read_exact_at(&mut pixel, offset as u64)
+                                .unwrap();
+                            u32::from_le_bytes(pixel) & 0xffffff
+                        })
+                        .collect();
+                    let _ = client.events.send(ClientEvent::Copied {
+                        flags: client.flags,
+                        damage: std::mem::take(&mut client.damage),
+                        pixels,
+                    });
+                }
+                zwlr_screencopy_frame_v1::Event::Failed => panic!("The copy failed"),
+                _ => {}
+            }
+        }
+    }
+
+    impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for TestClient {
+        fn event(
+            _: &mut Self,
+            _: &wl_registry::WlRegistry,
+            _: wl_registry::Event,
+            _: &GlobalListContents,
+            _: &Connection,
+            _: &QueueHandle<Self>,
+        ) {
+        }
+    }
+
+    impl Dispatch<xdg_wm_base::XdgWmBase, ()> for TestClient {
+        fn event(
+            _: &mut Self,
+            wm_base: &xdg_wm_base::XdgWmBase,
+            event: xdg_wm_base::Event,
+            _: &(),
+            _: &Connection,
+            _: &QueueHandle<Self>,
+        ) {
+            if let xdg_wm_base::Event::Ping { serial } = event {
+                wm_base.pong(serial);
+            }
+        }
+    }
+
+    impl Dispatch<xdg_surface::XdgSurface, ()> for TestClient {
+        fn event(
+            client: &mut Self,
+            xdg_surface: &xdg_surface::XdgSurface,
+            event: xdg_surface::Event,
+            _: &(),
+            connection: &Connection,
+            _: &QueueHandle<Self>,
+        ) {
+            let xdg_surface::Event::Configure { serial } = event else {
+                return;
+            };
+            xdg_surface.ack_configure(serial);
+            // The buffer is attached once, on the initial configure
+            let Some(buffer) = client.buffer.take() else {
+                return;
+            };
+            client.surface.attach(Some(&buffer), 0, 0);
+            client.surface.damage_buffer(0, 0, WIDTH, HEIGHT);
+            client.surface.commit();
+            connection.flush().unwrap();
+            let _ = client.events.send(ClientEvent::Committed);
+        }
+    }
+
+    impl Dispatch<xdg_toplevel::XdgToplevel, ()> for TestClient {
+        fn event(
+            client: &mut Self,
+            toplevel: &xdg_toplevel::XdgToplevel,
+            event: xdg_toplevel::Event,
+            _: &(),
+            _: &Connection,
+            _: &QueueHandle<Self>,
+        ) {
+            match event {
+                xdg_toplevel::Event::Configure { width, height, .. } if width > 0 => {
+                    let _ = client.events.send(ClientEvent::Configured(width, height));
+                }
+                // Unmap the window when asked to close it
+                xdg_toplevel::Event::Close => toplevel.destroy(),
+                _ => {}
+            }
+        }
+    }
+
+    impl Dispatch<wl_seat::WlSeat, ()> for TestClient {
+        fn event(
+            _: &mut Self,
+            seat: &wl_seat::WlSeat,
+            event: wl_seat::Event,
+            _: &(),
+            _: &Connection,
+            qh: &QueueHandle<Self>,
+        ) {
+            let wl_seat::Event::Capabilities {
+                capabilities: WEnum::Value(capabilities),
+            } = event
+            else {
+                return;
+            };
+            if capabilities.contains(wl_seat::Capability::Pointer) {
+                seat.get_pointer(qh, ());
+            }
+            if capabilities.contains(wl_seat::Capability::Keyboard) {
+                seat.get_keyboard(qh, ());
+            }
+            if capabilities.contains(wl_seat::Capability::Touch) {
+                seat.get_touch(qh, ());
+            }
+        }
+    }
+
+    impl Dispatch<wl_touch::WlTouch, ()> for TestClient {
+        fn event(
+            client: &mut Self,
+            _: &wl_touch::WlTouch,
+            event: wl_touch::Event,
+            _: &(),
+            connection: &Connection,
+            _: &QueueHandle<Self>,
+        ) {
+            if let wl_touch::Event::Down { serial, .. } = event {
+                client
+                    .toplevel
+                    .resize(&client.seat, serial, xdg_toplevel::ResizeEdge::BottomRight);
+                connection.flush().unwrap();
+                let _ = client.events.send(ClientEvent::ResizeRequested);
+            }
+        }
+    }
+
+    impl Dispatch<wl_pointer::WlPointer, ()> for TestClient {
+        fn event(
+            client: &mut Self,
+            _: &wl_pointer::WlPointer,
+            event: wl_pointer::Event,
+            _: &(),
+            _: &Connection,
+            _: &QueueHandle<Self>,
+        ) {
+            if let wl_pointer::Event::Enter {
+                surface_x,
+                surface_y,
+                ..
+            } = event
+            {
+                let _ = client
+                    .events
+                    .send(ClientEvent::PointerEnter(surface_x, surface_y));
+            }
+        }
+    }
+
+    impl Dispatch<wl_keyboard::WlKeyboard, ()> for TestClient {
+        fn event(
+            client: &mut Self,
+            _: &wl_keyboard::WlKeyboard,
+            event: wl_keyboard::Event,
+            _: &(),
+            _: &Connection,
+            _: &QueueHandle<Self>,
+        ) {
+            let event = match event {
+                wl_keyboard::Event::Enter { .. } => ClientEvent::KeyboardEnter,
+                wl_keyboard::Event::Key {
+                    key,
+                    state: WEnum::Value(wl_keyboard::KeyState::Pressed),
+                    ..
+                } => ClientEvent::Key(key),
+                _ => return,
+            };
+            let _ = client.events.send(event);
+        }
+    }
+
+    delegate_noop!(TestClient: wl_compositor::WlCompositor);
+    delegate_noop!(TestClient: wl_shm_pool::WlShmPool);
+    delegate_noop!(TestClient: ignore wl_shm::WlShm);
+    delegate_noop!(TestClient: ignore wl_buffer::WlBuffer);
+    delegate_noop!(TestClient: ignore wl_surface::WlSurface);
+    delegate_noop!(VirtualPointerClient: zwlr_virtual_pointer_manager_v1::ZwlrVirtualPointerManagerV1);
+    delegate_noop!(VirtualPointerClient: zwlr_virtual_pointer_v1::ZwlrVirtualPointerV1);
+    delegate_noop!(ScreencopyClient: wl_shm_pool::WlShmPool);
+    delegate_noop!(ScreencopyClient: ignore wl_shm::WlShm);
+    delegate_noop!(ScreencopyClient: ignore wl_buffer::WlBuffer);
+    delegate_noop!(ScreencopyClient: ignore wl_output::WlOutput);
+    delegate_noop!(ScreencopyClient: zwlr_screencopy_manager_v1::ZwlrScreencopyManagerV1);
+}
@@ -0,0 +1,98 @@
+use super::state::State;
+use smithay::{
+    delegate_content_type, delegate_idle_inhibit,
+    desktop::{find_popup_root_surface, layer_map_for_output, WindowSurfaceType},
+    reexports::{
+        wayland_protocols::wp::content_type::v1::server::wp_content_type_v1::Type as ContentType,
+        wayland_server::{protocol::wl_surface::WlSurface, Resource},
+    },
+    wayland::{
+        compositor::{get_parent, with_surface_tree_downward, TraversalAction},
+        content_type::ContentTypeSurfaceCachedState,
+        idle_inhibit::IdleInhibitHandler,
+        seat::WaylandFocus,
+    },
+};
+
+/// Video players and presentations keep the screen on while their surface is shown
+impl IdleInhibitHandler for State {
+    fn inhibit(&mut self, surface: WlSurface) {
+        if !self.idle_inhibitors.contains(&surface) {
+            self.idle_inhibitors.push(surface);
+        }
+    }
+
+    fn uninhibit(&mut self, surface: WlSurface) {
+        self.idle_inhibitors
+            .retain(|inhibitor| inhibitor != &surface);
+    }
+}
+
+delegate_idle_inhibit!(State);
+// Clients tell what they show: videos and games are drawn at the display rate, see
+// `realtime_content_shown`. The screen is kept on by idle inhibitors alone.
+delegate_content_type!(State);
+
+impl State {
+    /// Whether the screen should be kept on, i.e. a client inhibits idling with a surface that is shown
+    pub fn idle_inhibited(&mut self) -> bool {
+        self.idle_inhibitors.retain(Resource::is_alive);
+        self.idle_inhibitors
+            .iter()
+            .any(|surface| self.surface_shown(surface))
+    }
+
+    /// Whether a window or layer surface shown, or one of its subsurfaces, is a video or a game
+    pub fn realtime_content_shown(&self) -> bool {
+        let layers = self.space.outputs().flat_map(|output| {
+            layer_map_for_output(output)
+                .layers()
+                .map(|layer| layer.wl_surface().clone())
+                .collect::<Vec<_>>()
+        });
+        self.space
+            .elements()
+            .filter_map(|window| window.wl_surface().map(|surface| surface.into_owned()))
+            .chain(layers)
+            .any(|surface| shows_realtime_content(&surface))
+    }
+
+    /// Whether `surface` belongs to a mapped window, popup or layer surface
+    fn surface_shown(&self, surface: &WlSurface) -> bool {
+        let mut root = surface.clone();
+        while let Some(parent) = get_parent(&root) {
+            root = parent;
+        }
+        if let Some(popup) = self.popups.find_popup(&root) {
+            let Ok(popup_root) = find_popup_root_surface(&popup) else {
+                return false;
+            };
+            root = popup_root;
+        }
+        self.window_for_surface(&root).is_some()
+            || self.space.outputs().any(|output| {
+                layer_map_for_output(output)
+                    .layer_for_surface(&root, WindowSurfaceType::TOPLEVEL)
+                    .is_some()
+            })
+    }
+}
+
+/// Whether `surface` or one of its subsurfaces tells it shows a video or a game
+fn shows_realtime_content(surface: &WlSurface) -> bool {
+    let mut realtime = false;
+    with_surface_tree_downward(
+        surface,
+        (),
+        |_, _, _| TraversalAction::DoChildren(()),
+        |_, states, _| {
+            let mut content_type = states.cached_state.get::<ContentTypeSurfaceCachedState>();
+            realtime |= matches!(
+                content_type.current().content_type(),
+                ContentType::Video | ContentType::Game
+            );
+        },
+        |_, _, _| true,
+    );
+    realtime
+}
@@ -0,0 +1,152 @@
+use super::{grabs::BTN_LEFT, state::Compositor};
+use smithay::{
+    backend::input::{ButtonState, KeyState, TouchSlot},
+    input::{
+        keyboard::{FilterResult, Keycode},
+        pointer::{self, AxisFrame},
+        touch,
+    },
+    utils::{Logical, Physical, Point, SERIAL_COUNTER},
+};
+
+/// Input coming from whatever shows the desktop, the Android activity or a test
+impl Compositor {
+    /// Milliseconds since the compositor started, the clock of the input events sent to clients
+    pub fn time(&self) -> u32 {
+        self.start_time.elapsed().as_millis() as u32
+    }
+
+    /// Where in the space a point `x` and `y` pixels away from the top left corner of the built-in output is
+    pub fn output_location(&self, x: f64, y: f64) -> Point<f64, Logical> {
+        let location = Point::<f64, Physical>::from((x, y));
+        match &self.output {
+            Some(output) => {
+                location.to_logical(output.current_scale().fractional_scale())
+                    + output.current_location().to_f64()
+            }
+            None => location.to_logical(1.0),
+        }
+    }
+
+    pub fn key(&mut self, keycode: Keycode, state: KeyState) {
+        self.state.frames.schedule();
+        let serial = SERIAL_COUNTER.next_serial();
+        let time = self.time();
+        self.keyboard
+            .input::<(), _>(&mut self.state, keycode, state, serial, time, |_, _, _| {
+                FilterResult::Forward
+            });
+    }
+
+    pub fn touch_down(&mut self, slot: TouchSlot, location: Point<f64, Logical>) {
+        let serial = SERIAL_COUNTER.next_serial();
+        let time = self.time();
+        let state = &mut self.state;
+        state.frames.schedule();
+        state.cursor_visible = false;
+
+        if !self.touch.is_grabbed() {
+            state.dismiss_popups_outside(location);
+            state.focus_window_under(location, serial);
+            self.touch_down_decoration(slot, location, serial);
+        }
+        let state = &mut self.state;
+        let focus = state.surface_under(location);
+        self.touch.down(
+            state,
+            focus,
+            &touch::DownEvent {
+                slot,
+                location,
+                serial,
+                time,
+            },
+        );
+    }
+
+    pub fn touch_up(&mut self, slot: TouchSlot) {
+        self.state.frames.schedule();
+        let serial = SERIAL_COUNTER.next_serial();
+        let time = self.time();
+        self.touch
+            .up(&mut self.state, &touch::UpEvent { slot, serial, time });
+    }
+
+    pub fn touch_motion(&mut self, slot: TouchSlot, location: Point<f64, Logical>) {
+        self.state.frames.schedule();
+        let time = self.time();
+        let focus = self.state.surface_under(location);
+        self.touch.motion(
+            &mut self.state,
+            focus,
+            &touch::MotionEvent {
+                slot,
+                location,
+                time,
+            },
+        );
+    }
+
+    /// Move the pointer to `location`, in global coordinates
+    pub fn pointer_motion(&mut self, location: Point<f64, Logical>, time: u32) {
+        self.state.frames.schedule();
+        let pointer = self.pointer.clone();
+        let serial = SERIAL_COUNTER.next_serial();
+        let focus = self.state.surface_under(location);
+        self.state.cursor_visible = true;
+
+        // A locked or confined pointer only follows absolute motion where the client allows it
+        let constraint = self.state.active_pointer_constraint(&pointer);
+        if constraint
+            .is_some_and(|constraint| !self.state.pointer_may_move_to(&constraint, location))
+        {
+            return;
+        }
+
+        pointer.motion(
+            &mut self.state,
+            focus,
+            &pointer::MotionEvent {
+                location,
+                serial,
+                time,
+            },
+        );
+        pointer.frame(&mut self.state);
+        self.state.activate_pointer_constraint(&pointer);
+        if !pointer.is_grabbed() {
+            self.state.update_decoration_cursor(location);
+        }
+    }
+
+    pub fn pointer_button(&mut self, button: u32, state: ButtonState, time: u32) {
+        self.state.frames.schedule();
+        let serial = SERIAL_COUNTER.next_serial();
+        let pointer = self.pointer.clone();
+
+        if state == ButtonState::Pressed && !pointer.is_grabbed() {
+            let location = pointer.current_location();
+            self.state.focus_window_under(location, serial);
+            if button == BTN_LEFT {
+                self.pointer_press_decoration(location, serial);
+            }
+        }
+        pointer.button(
+            &mut self.state,
+            &pointer::ButtonEvent {
+                button,
+                state,
+                serial,
+                time,
+            },
+        );
+        pointer.frame(&mut self.state);
+    }
+
+    pub fn pointer_axis(&mut self, frame: AxisFrame) {
+        self.state.frames.schedule();
+        let pointer = self.pointer.clone();
+        pointer.axis(&mut self.state, frame);
+        pointer.frame(&mut self.state);
+    }
+}
@@ -0,0 +1,242 @@
+use super::{
+    cursor::DesktopRenderElement,
+    state::{send_frames_surface_tree, State},
+};
+use smithay::{
+    backend::renderer::{element::AsRenderElements, ImportAll, ImportMem, Renderer},
+    delegate_layer_shell,
+    desktop::{layer_map_for_output, LayerSurface, PopupManager, WindowSurfaceType},
+    output::Output,
+    reexports::wayland_server::protocol::{wl_output::WlOutput, wl_surface::WlSurface},
+    utils::{Logical, Point, Rectangle, SERIAL_COUNTER},
+    wayland::{
+        compositor::with_states,
+        shell::wlr_layer::{
+            KeyboardInteractivity, Layer, LayerSurface as WlrLayerSurface, LayerSurfaceCachedState,
+            LayerSurfaceData, WlrLayerShellHandler, WlrLayerShellState,
+        },
+    },
+};
+
+/// Layers drawn and hit above the windows
+pub(super) const LAYERS_ABOVE: [Layer; 2] = [Layer::Overlay, Layer::Top];
+/// Layers drawn and hit below the windows
+pub(super) const LAYERS_BELOW: [Layer; 2] = [Layer::Bottom, Layer::Background];
+
+/// Panels, docks, wallpapers and notifications of native Wayland shells, e.g. waybar or swaybg
+impl WlrLayerShellHandler for State {
+    fn shell_state(&mut self) -> &mut WlrLayerShellState {
+        &mut self.layer_shell_state
+    }
+
+    fn new_layer_surface(
+        &mut self,
+        surface: WlrLayerSurface,
+        output: Option<WlOutput>,
+        _layer: Layer,
+        namespace: String,
+    ) {
+        // Without an output asked for, layer surfaces go on the built-in display
+        let output = output
+            .as_ref()
+            .and_then(Output::from_resource)
+            .or_else(|| self.space.outputs().next().cloned());
+        let Some(output) = output else {
+            surface.send_close();
+            return;
+        };
+        // The initial configure is sent on the first commit, see `handle_layer_commit`
+        let layer = LayerSurface::new(surface, namespace);
+        let mapped = layer_map_for_output(&output).map_layer(&layer);
+        if let Err(e) = mapped {
+            log::info!("Failed to map layer surface: {}", e);
+        }
+    }
+
+    fn layer_destroyed(&mut self, surface: WlrLayerSurface) {
+        let outputs = self.space.outputs().cloned().collect::<Vec<_>>();
+        for output in outputs {
+            let mut map = layer_map_for_output(&output);
+            let layer = map
+                .layers()
+                .find(|layer| layer.layer_surface() == &surface)
+                .cloned();
+            if let Some(layer) = layer {
+                map.unmap_layer(&layer);
+            }
+        }
+        self.frames.schedule();
+        self.reconfigure_covering_windows();
+        self.focus_top_window();
+    }
+}
+
+delegate_layer_shell!(State);
+
+/// Whether a layer surface asked for every key while it is shown, like a lock screen or a launcher
+fn keyboard_exclusive(layer: &LayerSurface) -> bool {
+    with_states(layer.wl_surface(), |states| {
+        states
+            .cached_state
+            .get::<LayerSurfaceCachedState>()
+            .current()
+            .keyboard_interactivity
+            == KeyboardInteractivity::Exclusive
+    })
+}
+
+impl State {
+    /// The output and layer surface of `surface`
+    pub(super) fn layer_for_surface(&self, surface: &WlSurface) -> Option<(Output, LayerSurface)> {
+        self.space.outputs().find_map(|output| {
+            let layer = layer_map_for_output(output)
+                .layer_for_surface(surface, WindowSurfaceType::TOPLEVEL)
+                .cloned()?;
+            Some((output.clone(), layer))
+        })
+    }
+
+    /// Lay the layer surfaces out again once one changed its anchor, margin or exclusive zone, and
+    /// send the initial configure after their first commit
+    pub(super) fn handle_layer_commit(&mut self, surface: &WlSurface) {
+        let Some((output, layer)) = self.layer_for_surface(surface) else {
+            return;
+        };
+        let initial_configure_sent = with_states(surface, |states| {
+            states
+                .data_map
+                .get::<LayerSurfaceData>()
+                .is_some_and(|data| data.lock().unwrap().initial_configure_sent)
+        });
+
+        let usable_area = self.usable_area();
+        layer_map_for_output(&output).arrange();
+        if !initial_configure_sent {
+            layer.layer_surface().send_configure();
+        }
+        if self.usable_area() != usable_area {
+            self.reconfigure_covering_windows();
+        }
+        if keyboard_exclusive(&layer) && LAYERS_ABOVE.contains(&layer.layer()) {
+            if let Some(keyboard) = self.seat.get_keyboard() {
+                let focus = Some(layer.wl_surface().clone().into());
+                if keyboard.current_focus() != focus {
+                    keyboard.set_focus(self, focus, SERIAL_COUNTER.next_serial());
+                }
+            }
+        }
+    }
+
+    /// The area of the built-in display left to windows by the exclusive zones of panels, which
+    /// maximized windows cover
+    pub fn usable_area(&self) -> Rectangle<i32, Logical> {
+        let Some(output) = self.space.outputs().next() else {
+            return Rectangle::from_size(self.size);
+        };
+        let map = layer_map_for_output(output);
+        if map.layers().next().is_none() {
+            return Rectangle::from_size(self.size);
+        }
+        let zone = map.non_exclusive_zone();
+        Rectangle::new(zone.loc + output.current_location(), zone.size)
+    }
+
+    /// The topmost layer surface on one of `layers` under `location`, in global coordinates
+    pub(super) fn layer_under(
+        &self,
+        location: Point<f64, Logical>,
+        layers: &[Layer],
+    ) -> Option<(LayerSurface, Point<i32, Logical>)> {
+        self.space.outputs().find_map(|output| {
+            let output_location = output.current_location();
+            let map = layer_map_for_output(output);
+            layers.iter().find_map(|layer| {
+                let found = map.layer_under(*layer, location - output_location.to_f64())?;
+                let geometry = map.layer_geometry(found)?;
+                Some((found.clone(), geometry.loc + output_location))
+            })
+        })
+    }
+
+    /// The surface of a layer surface on one of `layers` under `location`, and its location
+    pub(super) fn layer_surface_under(
+        &self,
+        location: Point<f64, Logical>,
+        layers: &[Layer],
+    ) -> Option<(WlSurface, Point<f64, Logical>)> {
+        let (layer, layer_location) = self.layer_under(location, layers)?;
+        layer
+            .surface_under(location - layer_location.to_f64(), WindowSurfaceType::ALL)
+            .map(|(surface, surface_location)| {
+                (surface, (surface_location + layer_location).to_f64())
+            })
+    }
+
+    /// The layer surface above the windows that asked for every key, if one is shown
+    pub(super) fn exclusive_layer(&self) -> Option<LayerSurface> {
+        self.space.outputs().find_map(|output| {
+            let map = layer_map_for_output(output);
+            LAYERS_ABOVE.iter().find_map(|layer| {
+                map.layers_on(*layer)
+                    .rev()
+                    .find(|surface| keyboard_exclusive(surface))
+                    .cloned()
+            })
+        })
+    }
+
+    /// Lay the layer surfaces of every output out again, e.g. after it was resized
+    pub(super) fn arrange_layers(&self) {
+        for output in self.space.outputs() {
+            layer_map_for_output(output).arrange();
+        }
+    }
+
+    /// Tell the layer surfaces that their last commit is on screen
+    pub(super) fn send_layer_frames(&self, time: u32) {
+        for output in self.space.outputs() {
+            for layer in layer_map_for_output(output).layers() {
+                send_frames_surface_tree(layer.wl_surface(), time);
+                for (popup, _) in PopupManager::popups_for_surface(layer.wl_surface()) {
+                    send_frames_surface_tree(popup.wl_surface(), time);
+                }
+            }
+        }
+    }
+}
+
+/// The layer surfaces on `layers` of `output` to draw, front to back
+pub(super) fn layer_render_elements<R>(
+    renderer: &mut R,
+    output: &Output,
+    layers: &[Layer],
+    scale: f64,
+) -> Vec<DesktopRenderElement<R>>
+where
+    R: Renderer + ImportAll + ImportMem,
+    R::TextureId: Send + Clone + 'static,
+{
+    let map = layer_map_for_output(output);
+    layers
+        .iter()
+        .flat_map(|layer| map.layers_on(*layer).rev())
+        .flat_map(|layer| {
+            let location = map.layer_geometry(layer).unwrap_or_default().loc;
+            layer.render_elements::<DesktopRenderElement<R>>(
+                renderer,
+                location.to_physical_precise_round(scale),
+                scale.into(),
+                1.0,
+            )
+        })
+        .collect()
+}
+
+/// Close the layer surfaces of an output that is gone, their clients may open them on another one
+pub(super) fn close_layers(output: &Output) {
+    let mut map = layer_map_for_output(output);
+    for layer in map.layers().cloned().collect::<Vec<_>>() {
+        layer.layer_surface().send_close();
+        map.unmap_layer(&layer);
+    }
+}
@@ -0,0 +1,32 @@
+//! The Wayland compositor itself: clients, windows, input and what to draw. It knows nothing of Android,
+//! the activity showing it lives in `android::backend::wayland`, and `headless` draws it off screen.
+mod activation;
+pub mod bind;
+mod cursor;
+mod decoration;
+mod focus;
+mod foreign_toplevel;
+mod grabs;
+#[cfg(not(target_os = "android"))]
+pub mod headless;
+mod idle_inhibit;
+mod input;
+mod layer_shell;
+mod output;
+mod pointer_constraints;
+mod presentation;
+mod render;
+mod screencopy;
+mod selection;
+mod shortcuts_inhibit;
+mod state;
+mod tablet;
+mod text_input;
+mod virtual_input;
+mod xwayland;
+
+pub use cursor::{CursorTheme, DesktopRenderElement};
+pub use output::{resize_output, set_output_modes};
+pub use render::CLEAR_COLOR;
+pub use state::{Compositor, State};
+pub use tablet::StylusInput;
@@ -0,0 +1,166 @@
+use super::{layer_shell::close_layers, state::State};
+use crate::core::{config::OutputArrangement, frame_scheduler::DEFAULT_REFRESH_MHZ, output_layout};
+use smithay::{
+    output::{Mode, Output},
+    reexports::wayland_server::protocol::wl_surface::WlSurface,
+    utils::{Logical, Physical, Rectangle, Size, Transform},
+    wayland::{
+        compositor::{get_parent, with_states},
+        fractional_scale::{with_fractional_scale, FractionalScaleHandler},
+    },
+};
+
+/// Advertise one mode of `size` for each of the `refresh_rates` of the display, `refresh` being the current
+/// and preferred one. The geometry is sent again as well, for a changed physical size to reach clients.
+pub fn set_output_modes(
+    output: &Output,
+    size: Size<i32, Physical>,
+    refresh: i32,
+    refresh_rates: &[i32],
+) {
+    let current = Mode { size, refresh };
+    let modes = refresh_rates
+        .iter()
+        .map(|&refresh| Mode { size, refresh })
+        .chain([current])
+        .collect::<Vec<_>>();
+    for mode in output.modes() {
+        if !modes.contains(&mode) {
+            output.delete_mode(mode);
+        }
+    }
+    for mode in modes {
+        output.add_mode(mode);
+    }
+    output.set_preferred(current);
+    output.change_current_state(Some(current), Some(Transform::Normal), None, None);
+}
+
+/// The window showing `output` was resized, its refresh rates stay the same
+pub fn resize_output(output: &Output, size: Size<i32, Physical>) {
+    let refresh = output
+        .current_mode()
+        .map_or(DEFAULT_REFRESH_MHZ, |mode| mode.refresh);
+    let mut refresh_rates = output
+        .modes()
+        .iter()
+        .map(|mode| mode.refresh)
+        .collect::<Vec<_>>();
+    refresh_rates.sort_unstable_by(|a, b| b.cmp(a));
+    refresh_rates.dedup();
+    set_output_modes(output, size, refresh, &refresh_rates);
+}
+
+impl State {
+    /// Lay `outputs` out next to each other, the first one at the origin. Windows left on no output,
+    /// e.g. after an external display was unplugged, are brought back to the first one.
+    pub fn arrange_outputs(&mut self, outputs: &[Output], arrangement: OutputArrangement) {
+        let sizes = outputs
+            .iter()
+            .map(|output| {
+                let size = output_size(output);
+                (size.w, size.h)
+            })
+            .collect::<Vec<_>>();
+        let locations = output_layout::arrange(arrangement, &sizes);
+
+        let gone = self
+            .space
+            .outputs()
+            .filter(|output| !outputs.contains(output))
+            .cloned()
+            .collect::<Vec<_>>();
+        for output in gone {
+            close_layers(&output);
+            self.space.unmap_output(&output);
+        }
+        let usable_area = self.usable_area();
+        for (output, location) in outputs.iter().zip(&locations) {
+            output.change_current_state(None, None, None, Some((*location).into()));
+            self.space.map_output(output, *location);
+        }
+        self.arrange_layers();
+
+        // Maximized and fullscreen windows cover the first output, but its panels
+        if let Some(&size) = sizes.first() {
+            if self.size != size.into() || self.usable_area() != usable_area {
+                self.size = size.into();
+                self.reconfigure_covering_windows();
+            }
+        }
+
+        let areas = locations
+            .iter()
+            .zip(&sizes)
+            .map(|(location, size)| {
+                Rectangle::<i32, Logical>::new((*location).into(), (*size).into())
+            })
+            .collect::<Vec<_>>();
+        let lost = self
+            .space
+            .elements()
+            .filter(|window| {
+                self.space
+                    .element_geometry(window)
+                    .is_some_and(|geometry| !areas.iter().any(|area| area.overlaps(geometry)))
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        for window in lost {
+            let location = self.next_window_location();
+            self.move_window(&window, location);
+        }
+    }
+
+    /// The scale of the output `surface` is shown on, the first one when it is on none
+    fn surface_scale(&self, surface: &WlSurface) -> f64 {
+        self.window_for_surface(surface)
+            .and_then(|window| self.space.outputs_for_element(&window).into_iter().next())
+            .or_else(|| self.space.outputs().next().cloned())
+            .map_or(1.0, |output| output.current_scale().fractional_scale())
+    }
+
+    /// Tell the clients the scale to draw each window at, the scale of the output it is mostly on
+    pub fn update_surface_scales(&self) {
+        for window in self.space.elements() {
+            let Some(scale) = self
+                .space
+                .outputs_for_element(window)
+                .first()
+                .map(|output| output.current_scale().fractional_scale())
+            else {
+                continue;
+            };
+            window.with_surfaces(|_, states| {
+                with_fractional_scale(states, |fractional_scale| {
+                    fractional_scale.set_preferred_scale(scale)
+                });
+            });
+        }
+    }
+}
+
+/// Size of `output` in the coordinates of the space, that windows are laid out in
+fn output_size(output: &Output) -> Size<i32, Logical> {
+    output.current_mode().map_or_else(Size::default, |mode| {
+        mode.size
+            .to_f64()
+            .to_logical(output.current_scale().fractional_scale())
+            .to_i32_round()
+    })
+}
+
+impl FractionalScaleHandler for State {
+    fn new_fractional_scale(&mut self, surface: WlSurface) {
+        let mut root = surface.clone();
+        while let Some(parent) = get_parent(&root) {
+            root = parent;
+        }
+        let scale = self.surface_scale(&root);
+        with_states(&surface, |states| {
+            with_fractional_scale(states, |fractional_scale| {
+                fractional_scale.set_preferred_scale(scale)
+            });
+        });
+    }
+}
@@ -0,0 +1,182 @@
+use super::state::{Compositor, State};
+use smithay::{
+    delegate_pointer_constraints, delegate_relative_pointer,
+    input::pointer::{self, PointerHandle, RelativeMotionEvent},
+    reexports::wayland_server::protocol::wl_surface::WlSurface,
+    utils::{Logical, Point, Rectangle, SERIAL_COUNTER},
+    wayland::{
+        compositor::RegionAttributes,
+        pointer_constraints::{
+            with_pointer_constraint, PointerConstraint, PointerConstraintsHandler,
+        },
+        seat::WaylandFocus,
+    },
+};
+
+/// What an active pointer constraint allows the pointer to do
+pub(super) enum ActiveConstraint {
+    /// The pointer stays where it is, only relative motion is sent
+    Locked,
+    /// The pointer stays within the region of the surface, or the whole surface without a region
+    Confined(WlSurface, Option<RegionAttributes>),
+}
+
+impl PointerConstraintsHandler for State {
+    fn new_constraint(&mut self, _surface: &WlSurface, pointer: &PointerHandle<Self>) {
+        self.activate_pointer_constraint(pointer);
+    }
+
+    fn cursor_position_hint(
+        &mut self,
+        surface: &WlSurface,
+        pointer: &PointerHandle<Self>,
+        location: Point<f64, Logical>,
+    ) {
+        // Where the client drew the cursor while it was locked, so that it does not jump once unlocked
+        if !with_pointer_constraint(surface, pointer, |constraint| {
+            constraint.is_some_and(|constraint| constraint.is_active())
+        }) {
+            return;
+        }
+        let origin = self
+            .space
+            .elements()
+            .find(|window| window.wl_surface().as_deref() == Some(surface))
+            .and_then(|window| {
+                let location = self.space.element_location(window)?;
+                Some(location - window.geometry().loc)
+            });
+        if let Some(origin) = origin {
+            pointer.set_location(origin.to_f64() + location);
+        }
+    }
+}
+
+delegate_pointer_constraints!(State);
+delegate_relative_pointer!(State);
+
+impl State {
+    /// The constraint of the surface the pointer is on, if it is active
+    pub(super) fn active_pointer_constraint(
+        &self,
+        pointer: &PointerHandle<Self>,
+    ) -> Option<ActiveConstraint> {
+        let surface = pointer.current_focus()?;
+        with_pointer_constraint(&surface, pointer, |constraint| {
+            let constraint = constraint.filter(|constraint| constraint.is_active())?;
+            Some(match &*constraint {
+                PointerConstraint::Locked(_) => ActiveConstraint::Locked,
+                PointerConstraint::Confined(confined) => {
+                    ActiveConstraint::Confined(surface.clone(), confined.region().cloned())
+                }
+            })
+        })
+    }
+
+    /// Whether the pointer may move to `location` with the constraint of the surface it is on
+    pub(super) fn pointer_may_move_to(
+        &self,
+        constraint: &ActiveConstraint,
+        location: Point<f64, Logical>,
+    ) -> bool {
+        match constraint {
+            ActiveConstraint::Locked => false,
+            ActiveConstraint::Confined(surface, region) => match self.surface_under(location) {
+                Some((under, origin)) if &under == surface => region
+                    .as_ref()
+                    .is_none_or(|region| region.contains((location - origin).to_i32_round())),
+                _ => false,
+            },
+        }
+    }
+
+    /// Activate the constraint of the surface under the pointer, once the pointer is within its region.
+    /// Constraints are deactivated when the pointer leaves their surface.
+    pub(super) fn activate_pointer_constraint(&self, pointer: &PointerHandle<Self>) {
+        let location = pointer.current_location();
+        let Some((surface, origin)) = self.surface_under(location) else {
+            return;
+        };
+        if pointer.current_focus().as_ref() != Some(&surface) {
+            return;
+        }
+        with_pointer_constraint(&surface, pointer, |constraint| {
+            let Some(constraint) = constraint.filter(|constraint| !constraint.is_active()) else {
+                return;
+            };
+            let within = constraint
+                .region()
+                .is_none_or(|region| region.contains((location - origin).to_i32_round()));
+            if within {
+                constraint.activate();
+            }
+        });
+    }
+
+    /// The smallest rectangle around all outputs, the pointer cannot leave it
+    pub(super) fn outputs_bounds(&self) -> Option<Rectangle<i32, Logical>> {
+        self.space
+            .outputs()
+            .filter_map(|output| self.space.output_geometry(output))
+            .reduce(|bounds, geometry| bounds.merge(geometry))
+    }
+}
+
+/// Mouse motion with no notion of where the pointer is, e.g. of a captured mouse. Clients get it
+/// through relative-pointer, which games and remote desktops need once they locked the pointer.
+impl Compositor {
+    /// Whether a client locked or confined the pointer, so that raw mouse motion should be captured
+    pub fn pointer_constrained(&self) -> bool {
+        self.state
+            .active_pointer_constraint(&self.pointer)
+            .is_some()
+    }
+
+    /// Move the pointer by `delta`, unless a client locked it
+    pub fn pointer_motion_relative(&mut self, delta: Point<f64, Logical>, time: u32) {
+        self.state.frames.schedule();
+        self.state.cursor_visible = true;
+        let pointer = self.pointer.clone();
+        let location = pointer.current_location();
+        let focus = self.state.surface_under(location);
+        pointer.relative_motion(
+            &mut self.state,
+            focus,
+            &RelativeMotionEvent {
+                delta,
+                delta_unaccel: delta,
+                utime: time as u64 * 1000,
+            },
+        );
+
+        let mut target = location + delta;
+        if let Some(bounds) = self.state.outputs_bounds() {
+            let bounds = bounds.to_f64();
+            target.x = target
+                .x
+                .clamp(bounds.loc.x, bounds.loc.x + bounds.size.w - 1.0);
+            target.y = target
+                .y
+                .clamp(bounds.loc.y, bounds.loc.y + bounds.size.h - 1.0);
+        }
+        let constraint = self.state.active_pointer_constraint(&pointer);
+        if constraint.is_some_and(|constraint| !self.state.pointer_may_move_to(&constraint, target))
+        {
+            pointer.frame(&mut self.state);
+            return;
+        }
+
+        let focus = self.state.surface_under(target);
+        pointer.motion(
+            &mut self.state,
+            focus,
+            &pointer::MotionEvent {
+                location: target,
+                serial: SERIAL_COUNTER.next_serial(),
+                time,
+            },
+        );
+        pointer.frame(&mut self.state);
+        self.state.activate_pointer_constraint(&pointer);
+    }
+}
@@ -0,0 +1,75 @@
+use super::state::State;
+use smithay::{
+    backend::renderer::element::RenderElementStates,
+    delegate_presentation,
+    desktop::{
+        layer_map_for_output,
+        utils::{surface_presentation_feedback_flags_from_states, OutputPresentationFeedback},
+    },
+    output::Output,
+    reexports::wayland_protocols::wp::presentation_time::server::wp_presentation_feedback,
+    utils::Monotonic,
+    wayland::presentation::Refresh,
+};
+use std::time::Duration;
+
+delegate_presentation!(State);
+
+impl State {
+    /// The presentation feedback of the windows and layer surfaces on `output` that were just drawn,
+    /// to be sent with `frame_presented` once the frame is on screen.
+    /// `states` are those of the elements as rendered by the damage tracker.
+    pub fn take_presentation_feedback(
+        &self,
+        output: &Output,
+        states: &RenderElementStates,
+    ) -> OutputPresentationFeedback {
+        let mut feedback = OutputPresentationFeedback::new(output);
+        let on_output = |_: &_, _: &_| Some(output.clone());
+        let flags =
+            |surface: &_, _: &_| surface_presentation_feedback_flags_from_states(surface, states);
+        for window in self.space.elements() {
+            if self.space.outputs_for_element(window).contains(output) {
+                window.take_presentation_feedback(&mut feedback, on_output, flags);
+            }
+        }
+        for layer in layer_map_for_output(output).layers() {
+            layer.take_presentation_feedback(&mut feedback, on_output, flags);
+        }
+        feedback
+    }
+
+    /// Tell the clients that the frame of `feedback` was put on screen, so that video players
+    /// can pace their frames on the refresh rate of its output.
+    /// `presented_at` is the monotonic time the display reported showing it at. Without it, e.g. when
+    /// the driver does not report it, the time the frame was submitted is sent instead.
+    pub fn frame_presented(
+        &self,
+        mut feedback: OutputPresentationFeedback,
+        presented_at: Option<Duration>,
+    ) {
+        let refresh = feedback
+            .output()
+            .and_then(|output| output.current_mode())
+            .filter(|mode| mode.refresh > 0)
+            .map_or(Refresh::Unknown, |mode| {
+                Refresh::Fixed(Duration::from_secs_f64(1000.0 / mode.refresh as f64))
+            });
+        match presented_at {
+            Some(time) => feedback.presented::<_, Monotonic>(
+                time,
+                refresh,
+                0,
+                wp_presentation_feedback::Kind::Vsync
+                    | wp_presentation_feedback::Kind::HwClock
+                    | wp_presentation_feedback::Kind::HwCompletion,
+            ),
+            None => feedback.presented(
+                self.clock.now(),
+                refresh,
+                0,
+                wp_presentation_feedback::Kind::empty(),
+            ),
+        }
+    }
+}
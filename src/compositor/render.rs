@@ -0,0 +1,126 @@
+use super::{
+    cursor::{CursorTheme, DesktopRenderElement},
+    decoration::titlebar_element,
+    layer_shell::{layer_render_elements, LAYERS_ABOVE, LAYERS_BELOW},
+    state::{send_frames_surface_tree, State},
+};
+use smithay::{
+    backend::renderer::{element::AsRenderElements, Color32F, ImportAll, ImportMem, Renderer},
+    desktop::PopupManager,
+    input::pointer::CursorImageStatus,
+    output::Output,
+    utils::IsAlive,
+    wayland::seat::WaylandFocus,
+};
+
+/// Drawn where there is no window
+pub const CLEAR_COLOR: Color32F = Color32F::new(0.1, 0.0, 0.0, 1.0);
+
+impl State {
+    /// Everything to draw on `output` at its scale, front to back: the cursor first, then the panels
+    /// above the windows, the topmost window, and the wallpaper last.
+    /// Windows, popups and cursor surfaces that went away since the last frame are dropped first.
+    pub fn render_elements<R>(
+        &mut self,
+        renderer: &mut R,
+        output: &Output,
+        cursor_theme: &mut CursorTheme,
+    ) -> Vec<DesktopRenderElement<R>>
+    where
+        R: Renderer + ImportAll + ImportMem,
+        R::TextureId: Send + Clone + 'static,
+    {
+        self.space.refresh();
+        self.popups.cleanup();
+        self.update_surface_scales();
+        if let CursorImageStatus::Surface(surface) = &self.cursor_status {
+            if !surface.alive() {
+                self.cursor_status = CursorImageStatus::default_named();
+            }
+        }
+
+        let mut elements = self.cursor_elements(renderer, output, cursor_theme);
+        elements.extend(self.desktop_elements(renderer, output));
+        elements
+    }
+
+    /// The cursor on `output`, if it is shown
+    pub(super) fn cursor_elements<R>(
+        &self,
+        renderer: &mut R,
+        output: &Output,
+        cursor_theme: &mut CursorTheme,
+    ) -> Vec<DesktopRenderElement<R>>
+    where
+        R: Renderer + ImportAll + ImportMem,
+        R::TextureId: Send + Clone + 'static,
+    {
+        let (true, Some(pointer)) = (self.cursor_visible, self.seat.get_pointer()) else {
+            return Vec::new();
+        };
+        // Elements are placed relative to the top left corner of the output
+        cursor_theme.render_elements(
+            renderer,
+            &self.cursor_status,
+            pointer.current_location() - output.current_location().to_f64(),
+            output.current_scale().fractional_scale(),
+        )
+    }
+
+    /// Everything on `output` but the cursor, front to back
+    pub(super) fn desktop_elements<R>(
+        &mut self,
+        renderer: &mut R,
+        output: &Output,
+    ) -> Vec<DesktopRenderElement<R>>
+    where
+        R: Renderer + ImportAll + ImportMem,
+        R::TextureId: Send + Clone + 'static,
+    {
+        let origin = output.current_location();
+        let scale = output.current_scale().fractional_scale();
+        let mut elements = layer_render_elements(renderer, output, &LAYERS_ABOVE, scale);
+        let (space, titlebar_font) = (&self.space, &mut self.titlebar_font);
+        elements.extend(space.elements().rev().flat_map(|window| {
+            let location = space.element_location(window).unwrap_or_default() - origin;
+            let mut elements = window.render_elements::<DesktopRenderElement<R>>(
+                renderer,
+                (location - window.geometry().loc).to_physical_precise_round(scale),
+                scale.into(),
+                1.0,
+            );
+            // Behind the popups of the window, which may cover it
+            elements.extend(titlebar_element(
+                renderer,
+                window,
+                titlebar_font,
+                location,
+                scale,
+            ));
+            elements
+        }));
+        elements.extend(layer_render_elements(
+            renderer,
+            output,
+            &LAYERS_BELOW,
+            scale,
+        ));
+        elements
+    }
+
+    /// Tell the clients that their last commit is on screen, so they can draw the next frame
+    pub fn send_frames(&self, time: u32) {
+        for window in self.space.elements() {
+            if let Some(surface) = window.wl_surface() {
+                send_frames_surface_tree(&surface, time);
+                for (popup, _) in PopupManager::popups_for_surface(&surface) {
+                    send_frames_surface_tree(popup.wl_surface(), time);
+                }
+            }
+        }
+        self.send_layer_frames(time);
+        if let CursorImageStatus::Surface(surface) = &self.cursor_status {
+            send_frames_surface_tree(surface, time);
+        }
+    }
+}
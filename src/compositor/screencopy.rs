@@ -0,0 +1,483 @@
+use super::{render::CLEAR_COLOR, CursorTheme, State};
+use crate::core::screencopy::copy_rgba_to_argb;
+use smithay::{
+    backend::{
+        allocator::Fourcc,
+        renderer::{
+            damage::OutputDamageTracker, element::RenderElement, Bind, ExportMem, ImportAll,
+            ImportMem, Offscreen, Renderer,
+        },
+    },
+    output::Output,
+    reexports::{
+        wayland_protocols_wlr::screencopy::v1::server::{
+            zwlr_screencopy_frame_v1::{self, Flags, ZwlrScreencopyFrameV1},
+            zwlr_screencopy_manager_v1::{self, ZwlrScreencopyManagerV1},
+        },
+        wayland_server::{
+            protocol::{wl_buffer::WlBuffer, wl_output::WlOutput, wl_shm},
+            Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource,
+        },
+    },
+    utils::{Logical, Physical, Rectangle, Size, Transform},
+    wayland::shm::{with_buffer_contents, with_buffer_contents_mut},
+};
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+/// Screenshots and recordings of the outputs, by grim, wf-recorder or xdg-desktop-portal-wlr
+pub struct ScreencopyState {
+    /// Frames with a buffer to copy the output into, waiting for the output to be drawn
+    frames: Vec<PendingFrame>,
+    /// What changed on each output since each manager last copied it. Every client binds a manager of
+    /// its own, so that the copies of one do not take the damage of another.
+    damage_trackers: Vec<DamageTracker>,
+}
+
+/// What changed on `output` since a frame of `manager` was last copied, tracked at `size`
+struct DamageTracker {
+    manager: ZwlrScreencopyManagerV1,
+    output: Output,
+    size: Size<i32, Physical>,
+    tracker: OutputDamageTracker,
+}
+
+impl ScreencopyState {
+    pub fn new(display_handle: &DisplayHandle) -> Self {
+        display_handle.create_global::<State, ZwlrScreencopyManagerV1, _>(3, ());
+        Self {
+            frames: Vec::new(),
+            damage_trackers: Vec::new(),
+        }
+    }
+}
+
+struct PendingFrame {
+    frame: ZwlrScreencopyFrameV1,
+    manager: ZwlrScreencopyManagerV1,
+    buffer: WlBuffer,
+    output: Output,
+    region: Rectangle<i32, Physical>,
+    overlay_cursor: bool,
+    /// Only copied once something changed in the region since the last copy
+    with_damage: bool,
+}
+
+/// What a frame captures, `None` if the output is gone or the region is outside of it
+pub struct FrameData {
+    manager: ZwlrScreencopyManagerV1,
+    target: Option<(Output, Rectangle<i32, Physical>)>,
+    overlay_cursor: bool,
+    copied: AtomicBool,
+}
+
+impl GlobalDispatch<ZwlrScreencopyManagerV1, ()> for State {
+    fn bind(
+        _state: &mut Self,
+        _handle: &DisplayHandle,
+        _client: &Client,
+        resource: New<ZwlrScreencopyManagerV1>,
+        _global_data: &(),
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        data_init.init(resource, ());
+    }
+}
+
+impl Dispatch<ZwlrScreencopyManagerV1, ()> for State {
+    fn request(
+        _state: &mut Self,
+        _client: &Client,
+        resource: &ZwlrScreencopyManagerV1,
+        request: zwlr_screencopy_manager_v1::Request,
+        _data: &(),
+        _dhandle: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        let (frame, overlay_cursor, output, region) = match request {
+            zwlr_screencopy_manager_v1::Request::CaptureOutput {
+                frame,
+                overlay_cursor,
+                output,
+            } => (frame, overlay_cursor, output, None),
+            zwlr_screencopy_manager_v1::Request::CaptureOutputRegion {
+                frame,
+                overlay_cursor,
+                output,
+                x,
+                y,
+                width,
+                height,
+            } => (
+                frame,
+                overlay_cursor,
+                output,
+                Some(Rectangle::new((x, y).into(), (width, height).into())),
+            ),
+            zwlr_screencopy_manager_v1::Request::Destroy => return,
+            _ => unreachable!(),
+        };
+
+        let target = capture_target(&output, region);
+        let frame = data_init.init(
+            frame,
+            FrameData {
+                manager: resource.clone(),
+                target: target.clone(),
+                overlay_cursor: overlay_cursor != 0,
+                copied: AtomicBool::new(false),
+            },
+        );
+        let Some((_, region)) = target else {
+            frame.failed();
+            return;
+        };
+        let (width, height) = (region.size.w as u32, region.size.h as u32);
+        frame.buffer(wl_shm::Format::Xrgb8888, width, height, width * 4);
+        if frame.version() >= 3 {
+            frame.buffer_done();
+        }
+    }
+}
+
+/// The output of `output` and the physical region of it to capture, the whole output without a
+/// `region` in logical coordinates
+fn capture_target(
+    output: &WlOutput,
+    region: Option<Rectangle<i32, Logical>>,
+) -> Option<(Output, Rectangle<i32, Physical>)> {
+    let output = Output::from_resource(output)?;
+    let full = Rectangle::from_size(output.current_mode()?.size);
+    let region = match region {
+        Some(region) => region
+            .to_physical_precise_round(output.current_scale().fractional_scale())
+            .intersection(full)?,
+        None => full,
+    };
+    (!region.is_empty()).then_some((output, region))
+}
+
+impl Dispatch<ZwlrScreencopyFrameV1, FrameData> for State {
+    fn request(
+        state: &mut Self,
+        _client: &Client,
+        resource: &ZwlrScreencopyFrameV1,
+        request: zwlr_screencopy_frame_v1::Request,
+        data: &FrameData,
+        _dhandle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, Self>,
+    ) {
+        let (buffer, with_damage) = match request {
+            zwlr_screencopy_frame_v1::Request::Copy { buffer } => (buffer, false),
+            zwlr_screencopy_frame_v1::Request::CopyWithDamage { buffer } => (buffer, true),
+            zwlr_screencopy_frame_v1::Request::Destroy => return,
+            _ => unreachable!(),
+        };
+        if data.copied.swap(true, Ordering::Relaxed) {
+            resource.post_error(
+                zwlr_screencopy_frame_v1::Error::AlreadyUsed,
+                "The frame was already copied",
+            );
+            return;
+        }
+        let Some((output, region)) = data.target.clone() else {
+            resource.failed();
+            return;
+        };
+
+        let valid = with_buffer_contents(&buffer, |_, _, buffer| {
+            matches!(
+                buffer.format,
+                wl_shm::Format::Argb8888 | wl_shm::Format::Xrgb8888
+            ) && buffer.width == region.size.w
+                && buffer.height == region.size.h
+                && buffer.stride >= region.size.w * 4
+        });
+        if !matches!(valid, Ok(true)) {
+            resource.post_error(
+                zwlr_screencopy_frame_v1::Error::InvalidBuffer,
+                "The buffer is not a shared memory buffer of the advertised size and format",
+            );
+            return;
+        }
+
+        state.screencopy_state.frames.push(PendingFrame {
+            frame: resource.clone(),
+            manager: data.manager.clone(),
+            buffer,
+            output,
+            region,
+            overlay_cursor: data.overlay_cursor,
+            with_damage,
+        });
+        // Frames waiting for damage are tracked from the next one on
+        state.frames.schedule();
+    }
+}
+
+impl State {
+    /// Copy `output` into the buffers of the screenshots and recordings waiting for it, once it was
+    /// drawn. `T` is an offscreen buffer of the renderer to draw the output again into, with the
+    /// `transform` the backend draws its outputs with, e.g. `Flipped180` for bottom-up GL surfaces.
+    pub fn render_screencopies<R, T>(
+        &mut self,
+        renderer: &mut R,
+        output: &Output,
+        cursor_theme: &mut CursorTheme,
+        transform: Transform,
+    ) where
+        R: Renderer + ImportAll + ImportMem + ExportMem + Offscreen<T> + Bind<T>,
+        R::TextureId: Send + Clone + 'static,
+    {
+        let outputs = self.space.outputs().cloned().collect::<Vec<_>>();
+        let screencopy = &mut self.screencopy_state;
+        screencopy.frames.retain(|pending| {
+            if !pending.frame.is_alive() {
+                return false;
+            }
+            // Their output was removed
+            if !outputs.contains(&pending.output) {
+                pending.frame.failed();
+                return false;
+            }
+            true
+        });
+        screencopy
+            .damage_trackers
+            .retain(|tracker| tracker.manager.is_alive() && outputs.contains(&tracker.output));
+        if !screencopy
+            .frames
+            .iter()
+            .any(|pending| &pending.output == output)
+        {
+            return;
+        }
+        let Some(size) = output.current_mode().map(|mode| mode.size) else {
+            return;
+        };
+
+        let cursor = self.cursor_elements(renderer, output, cursor_theme);
+        let desktop = self.desktop_elements(renderer, output);
+        let with_cursor = cursor.iter().chain(&desktop).collect::<Vec<_>>();
+
+        let screencopy = &mut self.screencopy_state;
+        let mut managers = Vec::new();
+        for pending in &screencopy.frames {
+            if &pending.output == output && !managers.contains(&pending.manager) {
+                managers.push(pending.manager.clone());
+            }
+        }
+        let mut ready = Vec::new();
+        for manager in managers {
+            let Some(damage) =
+                screencopy.damage_since_last_copy(&manager, output, size, &with_cursor)
+            else {
+                continue;
+            };
+            let (manager_ready, waiting) =
+                screencopy
+                    .frames
+                    .drain(..)
+                    .partition::<Vec<_>, _>(|pending| {
+                        pending.manager == manager
+                            && &pending.output == output
+                            && (!pending.with_damage
+                                || damage.iter().any(|rect| rect.overlaps(pending.region)))
+                    });
+            screencopy.frames = waiting;
+            ready.extend(
+                manager_ready
+                    .into_iter()
+                    .map(|pending| (pending, damage.clone())),
+            );
+        }
+        if ready.is_empty() {
+            return;
+        }
+
+        let time = Duration::from(self.clock.now());
+        for overlay_cursor in [true, false] {
+            let frames = ready
+                .iter()
+                .filter(|(pending, _)| pending.overlay_cursor == overlay_cursor)
+                .collect::<Vec<_>>();
+            if frames.is_empty() {
+                continue;
+            }
+            let elements = if overlay_cursor {
+                with_cursor.clone()
+            } else {
+                desktop.iter().collect()
+            };
+            copy_output::<R, T, _>(renderer, size, transform, &elements, &frames, time);
+        }
+    }
+}
+
+impl ScreencopyState {
+    /// What changed on `output`, drawn with `elements` at `size`, since a frame of `manager` was last
+    /// copied. All of it the first time.
+    fn damage_since_last_copy<R, E>(
+        &mut self,
+        manager: &ZwlrScreencopyManagerV1,
+        output: &Output,
+        size: Size<i32, Physical>,
+        elements: &[E],
+    ) -> Option<Vec<Rectangle<i32, Physical>>>
+    where
+        R: Renderer,
+        E: RenderElement<R>,
+    {
+        let index = match self.damage_trackers.iter().position(|tracker| {
+            &tracker.manager == manager && &tracker.output == output && tracker.size == size
+        }) {
+            Some(index) => index,
+            None => {
+                self.damage_trackers
+                    .retain(|tracker| &tracker.manager != manager || &tracker.output != output);
+                self.damage_trackers.push(DamageTracker {
+                    manager: manager.clone(),
+                    output: output.clone(),
+                    size,
+                    tracker: OutputDamageTracker::new(size, 1.0, Transform::Normal),
+                });
+                self.damage_trackers.len() - 1
+            }
+        };
+        match self.damage_trackers[index]
+            .tracker
+            .damage_output(1, elements)
+        {
+            Ok((damage, _)) => Some(damage.cloned().unwrap_or_default()),
+            Err(e) => {
+                log::info!("Failed to track the damage of {}: {:?}", output.name(), e);
+                None
+            }
+        }
+    }
+}
+
+/// Draw `elements` on a buffer of `size` and copy the region of each of the `frames` into theirs,
+/// telling each what changed since its client last copied the output
+fn copy_output<R, T, E>(
+    renderer: &mut R,
+    size: Size<i32, Physical>,
+    transform: Transform,
+    elements: &[E],
+    frames: &[&(PendingFrame, Vec<Rectangle<i32, Physical>>)],
+    time: Duration,
+) where
+    R: Renderer + ExportMem + Offscreen<T> + Bind<T>,
+    E: RenderElement<R>,
+{
+    let fail = |e: &dyn std::fmt::Debug| {
+        log::info!("Failed to copy the output: {:?}", e);
+        for (pending, _) in frames {
+            pending.frame.failed();
+        }
+    };
+    let mut target = match renderer.create_buffer(Fourcc::Abgr8888, (size.w, size.h).into()) {
+        Ok(target) => target,
+        Err(e) => return fail(&e),
+    };
+    let mut framebuffer = match renderer.bind(&mut target) {
+        Ok(framebuffer) => framebuffer,
+        Err(e) => return fail(&e),
+    };
+    if let Err(e) = OutputDamageTracker::new(size, 1.0, transform).render_output(
+        renderer,
+        &mut framebuffer,
+        0,
+        elements,
+        CLEAR_COLOR,
+    ) {
+        return fail(&e);
+    }
+
+    // The rows are read back bottom-up, which the clients are told to flip
+    let y_invert = transform == Transform::Flipped180;
+    for (pending, damage) in frames {
+        let region = pending.region;
+        let y = if y_invert {
+            size.h - region.loc.y - region.size.h
+        } else {
+            region.loc.y
+        };
+        let pixels = renderer
+            .copy_framebuffer(
+                &framebuffer,
+                Rectangle::new(
+                    (region.loc.x, y).into(),
+                    (region.size.w, region.size.h).into(),
+                ),
+                Fourcc::Abgr8888,
+            )
+            .and_then(|mapping| renderer.map_texture(&mapping).map(<[u8]>::to_vec));
+        let pixels = match pixels {
+            Ok(pixels) => pixels,
+            Err(e) => {
+                log::info!("Failed to read the output back: {:?}", e);
+                pending.frame.failed();
+                continue;
+            }
+        };
+        let copied = with_buffer_contents_mut(&pending.buffer, |ptr, len, buffer| {
+            let Some(available) = len.checked_sub(buffer.offset as usize) else {
+                return false;
+            };
+            // SAFETY: the pool is `len` bytes long and the buffer starts at `offset` in it
+            let dst = unsafe {
+                std::slice::from_raw_parts_mut(ptr.add(buffer.offset as usize), available)
+            };
+            copy_rgba_to_argb(
+                &pixels,
+                region.size.w as usize,
+                region.size.h as usize,
+                dst,
+                buffer.stride as usize,
+            )
+        });
+        if !matches!(copied, Ok(true)) {
+            pending.frame.failed();
+            continue;
+        }
+        send_ready(pending, damage, y_invert, time);
+    }
+}
+
+fn send_ready(
+    pending: &PendingFrame,
+    damage: &[Rectangle<i32, Physical>],
+    y_invert: bool,
+    time: Duration,
+) {
+    let frame = &pending.frame;
+    frame.flags(if y_invert {
+        Flags::YInvert
+    } else {
+        Flags::empty()
+    });
+    if pending.with_damage {
+        for rect in damage {
+            if let Some(rect) = rect.intersection(pending.region) {
+                let loc = rect.loc - pending.region.loc;
+                // Damage is in the coordinates of the buffer, as it was copied
+                let y = if y_invert {
+                    pending.region.size.h - loc.y - rect.size.h
+                } else {
+                    loc.y
+                };
+                frame.damage(
+                    loc.x as u32,
+                    y as u32,
+                    rect.size.w as u32,
+                    rect.size.h as u32,
+                );
+            }
+        }
+    }
+    let seconds = time.as_secs();
+    frame.ready((seconds >> 32) as u32, seconds as u32, time.subsec_nanos());
+}
@@ -0,0 +1,181 @@
+use super::state::State;
+use crate::core::clipboard::{self, ClipContent};
+use smithay::{
+    input::Seat,
+    wayland::selection::{
+        data_device::{
+            current_data_device_selection_userdata, request_data_device_client_selection,
+            set_data_device_selection,
+        },
+        primary_selection::{PrimarySelectionHandler, PrimarySelectionState},
+        SelectionHandler, SelectionSource, SelectionTarget,
+    },
+};
+use std::{
+    fs::File,
+    io::{self, Write},
+    os::unix::io::OwnedFd,
+    sync::Arc,
+    thread,
+};
+
+/// Who provides a selection set by the compositor
+#[derive(Debug, Clone)]
+pub enum ServerSelection {
+    /// Copied in an X11 window, the data comes from Xwayland
+    Xwayland,
+    /// Copied in an Android app
+    Android(Arc<ClipContent>),
+}
+
+impl State {
+    /// Offer what was copied on Android to the Linux clients, and hand what they copied over to Android
+    pub fn sync_clipboard(&mut self) {
+        if !self.clipboard.sync {
+            return;
+        }
+
+        if let Some(content) = clipboard::take_for_desktop() {
+            let mime_types = content.mime_types();
+            if let Some(xwm) = self.xwm.as_mut() {
+                if let Err(e) =
+                    xwm.new_selection(SelectionTarget::Clipboard, Some(mime_types.clone()))
+                {
+                    log::info!("Failed to set the Xwayland selection: {}", e);
+                }
+            }
+            set_data_device_selection(
+                &self.display_handle,
+                &self.seat,
+                mime_types,
+                ServerSelection::Android(Arc::new(content)),
+            );
+        }
+
+        if let Some(mime_types) = self.pending_clipboard_export.take() {
+            self.export_clipboard(mime_types);
+        }
+    }
+
+    /// Read the selection a Linux client just set and put it in the Android clipboard.
+    /// Only text is exported, Android apps seldom accept anything else.
+    fn export_clipboard(&mut self, mime_types: Vec<String>) {
+        let Some(mime_type) = clipboard::preferred_text_mime_type(&mime_types) else {
+            return;
+        };
+        let (reader, writer) = match io::pipe() {
+            Ok(it) => it,
+            Err(e) => {
+                log::info!("Failed to create a pipe for the clipboard: {}", e);
+                return;
+            }
+        };
+
+        let selection = current_data_device_selection_userdata(&self.seat).map(|it| it.clone());
+        match selection {
+            Some(ServerSelection::Xwayland) => {
+                let Some(xwm) = self.xwm.as_mut() else {
+                    return;
+                };
+                if let Err(e) = xwm.send_selection(
+                    SelectionTarget::Clipboard,
+                    mime_type.to_string(),
+                    writer.into(),
+                    self.loop_handle.clone(),
+                ) {
+                    log::info!("Failed to read the Xwayland clipboard: {}", e);
+                    return;
+                }
+            }
+            // Came from Android in the first place
+            Some(ServerSelection::Android(_)) => return,
+            None => {
+                if let Err(e) = request_data_device_client_selection(
+                    &self.seat,
+                    mime_type.to_string(),
+                    writer.into(),
+                ) {
+                    log::info!("Failed to read the Wayland clipboard: {}", e);
+                    return;
+                }
+            }
+        }
+
+        // The client writes at its own pace, and may never close its end
+        let limit = self.clipboard.max_size_kb * 1024;
+        thread::spawn(move || match clipboard::read_limited(reader, limit) {
+            Ok(Some(data)) => {
+                let text = String::from_utf8_lossy(&data).into_owned();
+                clipboard::send_to_android(ClipContent::Text(text));
+            }
+            Ok(None) => log::info!("Not copying to Android, over {} bytes", limit),
+            Err(e) => log::info!("Failed to read the clipboard: {}", e),
+        });
+    }
+}
+
+/// Write what was copied on Android to a Linux client pasting it
+pub fn send_android_selection(content: Arc<ClipContent>, mime_type: String, fd: OwnedFd) {
+    thread::spawn(move || {
+        let Some(data) = content.data(&mime_type) else {
+            return;
+        };
+        if let Err(e) = File::from(fd).write_all(data) {
+            log::info!("Failed to paste the Android clipboard: {}", e);
+        }
+    });
+}
+
+impl SelectionHandler for State {
+    type SelectionUserData = ServerSelection;
+
+    /// A Wayland client copied something, offer it to the X11 clients and to Android
+    fn new_selection(
+        &mut self,
+        target: SelectionTarget,
+        source: Option<SelectionSource>,
+        _seat: Seat<Self>,
+    ) {
+        let mime_types = source.map(|it| it.mime_types());
+        if let Some(xwm) = self.xwm.as_mut() {
+            if let Err(e) = xwm.new_selection(target, mime_types.clone()) {
+                log::info!("Failed to set the Xwayland selection: {}", e);
+            }
+        }
+        // The seat only holds the new selection once this returns, so it is read after dispatching
+        if target == SelectionTarget::Clipboard {
+            self.pending_clipboard_export = mime_types;
+        }
+    }
+
+    /// A Wayland client pastes what was copied in an X11 window or on Android
+    fn send_selection(
+        &mut self,
+        target: SelectionTarget,
+        mime_type: String,
+        fd: OwnedFd,
+        _seat: Seat<Self>,
+        user_data: &ServerSelection,
+    ) {
+        match user_data {
+            ServerSelection::Xwayland => {
+                if let Some(xwm) = self.xwm.as_mut() {
+                    if let Err(e) =
+                        xwm.send_selection(target, mime_type, fd, self.loop_handle.clone())
+                    {
+                        log::info!("Failed to send the Xwayland selection: {}", e);
+                    }
+                }
+            }
+            ServerSelection::Android(content) => {
+                send_android_selection(content.clone(), mime_type, fd)
+            }
+        }
+    }
+}
+
+impl PrimarySelectionHandler for State {
+    fn primary_selection_state(&self) -> &PrimarySelectionState {
+        &self.primary_selection_state
+    }
+}
@@ -0,0 +1,44 @@
+use super::state::{Compositor, State};
+use smithay::{
+    delegate_keyboard_shortcuts_inhibit,
+    wayland::{
+        keyboard_shortcuts_inhibit::{
+            KeyboardShortcutsInhibitHandler, KeyboardShortcutsInhibitState,
+            KeyboardShortcutsInhibitor, KeyboardShortcutsInhibitorSeat,
+        },
+        seat::WaylandFocus,
+    },
+};
+
+/// Remote desktops and virtual machines get the key combinations of the system while they are focused
+impl KeyboardShortcutsInhibitHandler for State {
+    fn keyboard_shortcuts_inhibit_state(&mut self) -> &mut KeyboardShortcutsInhibitState {
+        &mut self.keyboard_shortcuts_inhibit_state
+    }
+
+    fn new_inhibitor(&mut self, inhibitor: KeyboardShortcutsInhibitor) {
+        // It only takes effect while its surface has the keyboard focus, see `shortcuts_inhibited`
+        inhibitor.activate();
+    }
+}
+
+delegate_keyboard_shortcuts_inhibit!(State);
+
+/// Key combinations Android or Samsung DeX would handle, like Alt+Tab, Meta or Ctrl+Esc, go to the
+/// focused client instead while it inhibits the shortcuts
+impl Compositor {
+    /// Whether the focused client asked for every key combination, so that the platform should
+    /// capture the keyboard
+    pub fn shortcuts_inhibited(&self) -> bool {
+        let Some(surface) = self
+            .keyboard
+            .current_focus()
+            .and_then(|focus| focus.wl_surface().map(|surface| surface.into_owned()))
+        else {
+            return false;
+        };
+        self.seat
+            .keyboard_shortcuts_inhibitor_for_surface(&surface)
+            .is_some_and(|inhibitor| inhibitor.is_active())
+    }
+}
@@ -0,0 +1,1145 @@
+use super::{
+    bind::bind_socket,
+    decoration::{server_decorated, server_decorated_x11, TitlebarFont},
+    focus::KeyboardFocusTarget,
+    foreign_toplevel::ForeignToplevelState,
+    grabs::{
+        handle_resize_commit, MoveSurfaceGrab, ResizeSurfaceGrab, TouchMoveSurfaceGrab,
+        TouchResizeSurfaceGrab,
+    },
+    layer_shell::{LAYERS_ABOVE, LAYERS_BELOW},
+    screencopy::ScreencopyState,
+    tablet::{tablet_descriptor, StylusInput},
+    virtual_input::VirtualPointerState,
+};
+use crate::core::{
+    config::ClipboardConfig, decoration::TITLEBAR_HEIGHT, frame_scheduler::FrameScheduler,
+};
+use smithay::{
+    backend::renderer::utils::on_commit_buffer_handler,
+    delegate_compositor, delegate_data_device, delegate_fractional_scale, delegate_output,
+    delegate_primary_selection, delegate_seat, delegate_shm, delegate_viewporter,
+    delegate_xdg_shell, delegate_xwayland_shell,
+    desktop::{
+        find_popup_root_surface, get_popup_toplevel_coords, PopupKeyboardGrab, PopupKind,
+        PopupManager, PopupPointerGrab, PopupUngrabStrategy, Space, Window, WindowSurfaceType,
+    },
+    input::{
+        keyboard::KeyboardHandle,
+        pointer::{CursorImageStatus, Focus},
+        touch::TouchHandle,
+        Seat, SeatHandler, SeatState,
+    },
+    output::Output,
+    reexports::{
+        calloop::{EventLoop, LoopHandle},
+        rustix::{
+            event::{poll, PollFd, PollFlags},
+            io::Errno,
+        },
+        wayland_protocols::xdg::{
+            decoration::zv1::server::zxdg_toplevel_decoration_v1, shell::server::xdg_toplevel,
+        },
+        wayland_server::{
+            protocol::{wl_output, wl_seat},
+            Display, DisplayHandle, Resource,
+        },
+    },
+    utils::{Clock, Logical, Monotonic, Point, Rectangle, Serial, Size, SERIAL_COUNTER},
+    wayland::{
+        buffer::BufferHandler,
+        compositor::{
+            get_parent, is_sync_subsurface, with_states, with_surface_tree_downward,
+            CompositorClientState, CompositorHandler, CompositorState, SurfaceAttributes,
+            TraversalAction,
+        },
+        content_type::ContentTypeState,
+        fractional_scale::FractionalScaleManagerState,
+        idle_inhibit::IdleInhibitManagerState,
+        input_method::{InputMethodManagerState, InputMethodSeat},
+        keyboard_shortcuts_inhibit::KeyboardShortcutsInhibitState,
+        output::{OutputHandler, OutputManagerState},
+        pointer_constraints::PointerConstraintsState,
+        presentation::PresentationState,
+        relative_pointer::RelativePointerManagerState,
+        seat::WaylandFocus,
+        selection::{
+            data_device::{
+                set_data_device_focus, ClientDndGrabHandler, DataDeviceHandler, DataDeviceState,
+                ServerDndGrabHandler,
+            },
+            primary_selection::{set_primary_focus, PrimarySelectionState},
+        },
+        shell::wlr_layer::WlrLayerShellState,
+        shell::xdg::{
+            decoration::XdgDecorationState, PopupSurface, PositionerState, ToplevelSurface,
+            XdgShellHandler, XdgShellState, XdgToplevelSurfaceData,
+        },
+        shm::{ShmHandler, ShmState},
+        tablet_manager::{TabletManagerState, TabletSeatTrait},
+        text_input::TextInputManagerState,
+        viewporter::ViewporterState,
+        virtual_keyboard::VirtualKeyboardManagerState,
+        xdg_activation::XdgActivationState,
+        xwayland_shell::XWaylandShellState,
+    },
+    xwayland::{X11Surface, X11Wm, XWaylandClientData},
+};
+use smithay::{
+    input::pointer::PointerHandle,
+    reexports::wayland_server::{
+        backend::{ClientData, ClientId, DisconnectReason},
+        protocol::{wl_buffer, wl_surface::WlSurface},
+        Client, ListeningSocket,
+    },
+};
+use std::{
+    cell::RefCell,
+    error::Error,
+    os::unix::io::{AsFd, OwnedFd},
+    sync::{
+        mpsc::{self, SyncSender},
+        Arc,
+    },
+    thread,
+    time::Duration,
+    time::Instant,
+};
+
+/// App id of the single toplevel of a rootful Xwayland, which hosts the whole X11 desktop
+const XWAYLAND_APP_ID: &str = "org.freedesktop.Xwayland";
+
+/// Offset between cascaded windows
+const CASCADE_STEP: i32 = 32;
+
+pub struct Compositor {
+    pub state: State,
+    pub display: Display<State>,
+    pub listener: ListeningSocket,
+    pub clients: Vec<Client>,
+    pub start_time: Instant,
+    pub seat: Seat<State>,
+    pub keyboard: KeyboardHandle<State>,
+    pub touch: TouchHandle<State>,
+    pub pointer: PointerHandle<State>,
+    pub output: Option<Output>,
+    /// Only drives Xwayland and the X11 window manager, the Wayland clients are dispatched by hand
+    pub event_loop: EventLoop<'static, State>,
+    /// Tells the client watcher that what woke it up was dispatched, see `watch_clients`
+    dispatched: Option<SyncSender<()>>,
+    /// Text being composed with the platform keyboard, see `ime_preedit`
+    pub(super) preedit: String,
+    /// The stylus in range, as it was last moved, see `stylus_motion`
+    pub(super) stylus: Option<StylusInput>,
+    /// The surface the tip of the stylus went down on, and where it is
+    pub(super) stylus_grab: Option<(WlSurface, Point<f64, Logical>)>,
+}
+
+pub struct State {
+    pub compositor_state: CompositorState,
+    pub xdg_shell_state: XdgShellState,
+    pub xdg_decoration_state: XdgDecorationState,
+    pub layer_shell_state: WlrLayerShellState,
+    pub shm_state: ShmState,
+    /// Logical geometry and names of the outputs, which screenshot and screencast tools rely on
+    pub output_manager_state: OutputManagerState,
+    pub data_device_state: DataDeviceState,
+    pub primary_selection_state: PrimarySelectionState,
+    pub fractional_scale_state: FractionalScaleManagerState,
+    pub viewporter_state: ViewporterState,
+    pub text_input_state: TextInputManagerState,
+    pub input_method_state: InputMethodManagerState,
+    pub tablet_manager_state: TabletManagerState,
+    pub relative_pointer_state: RelativePointerManagerState,
+    pub pointer_constraints_state: PointerConstraintsState,
+    pub screencopy_state: ScreencopyState,
+    pub idle_inhibit_state: IdleInhibitManagerState,
+    pub presentation_state: PresentationState,
+    pub content_type_state: ContentTypeState,
+    pub xdg_activation_state: XdgActivationState,
+    pub foreign_toplevel_state: ForeignToplevelState,
+    pub keyboard_shortcuts_inhibit_state: KeyboardShortcutsInhibitState,
+    pub virtual_keyboard_manager_state: VirtualKeyboardManagerState,
+    pub virtual_pointer_state: VirtualPointerState,
+    pub seat_state: SeatState<Self>,
+    pub seat: Seat<Self>,
+    /// Size of the output, maximized and fullscreen windows are configured to this size
+    pub size: Size<i32, Logical>,
+    /// Mapped windows, in stacking order
+    pub space: Space<Window>,
+    /// Windows unmapped until they are restored, and where they were, the last minimized last
+    pub minimized: Vec<(Window, Point<i32, Logical>)>,
+    /// Menus, tooltips and dropdowns of the windows
+    pub popups: PopupManager,
+    pub display_handle: DisplayHandle,
+    pub loop_handle: LoopHandle<'static, State>,
+    pub xwayland_shell_state: XWaylandShellState,
+    /// The window manager of the rootless Xwayland, once it is ready
+    pub xwm: Option<X11Wm>,
+    pub clipboard: ClipboardConfig,
+    /// MIME types of the clipboard a Linux client just set, to be sent to Android
+    pub pending_clipboard_export: Option<Vec<String>>,
+    /// Cursor requested by the client under the pointer
+    pub cursor_status: CursorImageStatus,
+    /// Whether a mouse or trackpad is in use. The cursor is hidden while using touch.
+    pub cursor_visible: bool,
+    pub frames: FrameScheduler,
+    /// Clock of the presentation and screenshot timestamps
+    pub clock: Clock<Monotonic>,
+    /// Surfaces that asked to keep the screen on while they are shown
+    pub idle_inhibitors: Vec<WlSurface>,
+    pub titlebar_font: TitlebarFont,
+}
+
+/// Geometry of a window before it was maximized or made fullscreen, to restore it afterwards
+#[derive(Default)]
+struct RestoreGeometry(RefCell<Option<Rectangle<i32, Logical>>>);
+
+impl State {
+    pub fn window_for_surface(&self, surface: &WlSurface) -> Option<Window> {
+        self.space
+            .elements()
+            .find(|window| window.wl_surface().as_deref() == Some(surface))
+            .cloned()
+    }
+
+    /// The surface under `location` and its location, both in global coordinates. Panels and
+    /// notifications are above the windows, wallpapers below.
+    pub fn surface_under(
+        &self,
+        location: Point<f64, Logical>,
+    ) -> Option<(WlSurface, Point<f64, Logical>)> {
+        if let Some(under) = self.layer_surface_under(location, &LAYERS_ABOVE) {
+            return Some(under);
+        }
+        let Some((window, part)) = self.window_under(location) else {
+            return self.layer_surface_under(location, &LAYERS_BELOW);
+        };
+        // Decorations are drawn by the compositor, no client is under them
+        if part.is_some() {
+            return None;
+        }
+        let window_location = self.space.element_location(&window)? - window.geometry().loc;
+        window
+            .surface_under(location - window_location.to_f64(), WindowSurfaceType::ALL)
+            .map(|(surface, surface_location)| {
+                (surface, (surface_location + window_location).to_f64())
+            })
+    }
+
+    /// Raise the window under `location` and give it the keyboard focus, i.e. focus follows click.
+    /// Minimized windows stay minimized, a task bar restores them.
+    pub fn focus_window_under(&mut self, location: Point<f64, Logical>, serial: Serial) {
+        // An open menu keeps the focus until it is dismissed, a lock screen or launcher while it is shown
+        if self.seat.get_keyboard().is_some_and(|it| it.is_grabbed())
+            || self.exclusive_layer().is_some()
+        {
+            return;
+        }
+
+        // Panels take the focus only when they ask for it, e.g. for a search field
+        let layer = match self.layer_under(location, &LAYERS_ABOVE) {
+            Some((layer, _)) => Some(layer),
+            None if self.window_under(location).is_none() => self
+                .layer_under(location, &LAYERS_BELOW)
+                .map(|(layer, _)| layer)
+                .filter(|layer| layer.can_receive_keyboard_focus()),
+            None => None,
+        };
+        if let Some(layer) = layer {
+            if layer.can_receive_keyboard_focus() {
+                if let Some(keyboard) = self.seat.get_keyboard() {
+                    keyboard.set_focus(self, Some(layer.wl_surface().clone().into()), serial);
+                }
+            }
+            return;
+        }
+
+        let window = self.window_under(location).map(|(window, _)| window);
+
+        if let Some(window) = &window {
+            self.raise_window(window);
+        }
+
+        let focus = window.map(|window| KeyboardFocusTarget::from_window(&window));
+        if let Some(keyboard) = self.seat.get_keyboard() {
+            keyboard.set_focus(self, focus, serial);
+        }
+    }
+
+    /// Put the window on top, for both the compositor and the X server, and mark it as the active one
+    fn raise_window(&mut self, window: &Window) {
+        self.space.raise_element(window, true);
+        for window in self.space.elements() {
+            if let Some(toplevel) = window.toplevel() {
+                toplevel.send_pending_configure();
+            }
+        }
+        if let (Some(xwm), Some(surface)) = (self.xwm.as_mut(), window.x11_surface()) {
+            if let Err(e) = xwm.raise_window(surface) {
+                log::info!("Failed to raise X11 window: {}", e);
+            }
+        }
+    }
+
+    /// Hand the focus over to the window that is now on top, after the focused one went away
+    pub fn focus_top_window(&mut self) {
+        if let Some(layer) = self.exclusive_layer() {
+            if let Some(keyboard) = self.seat.get_keyboard() {
+                keyboard.set_focus(
+                    self,
+                    Some(layer.wl_surface().clone().into()),
+                    SERIAL_COUNTER.next_serial(),
+                );
+            }
+            return;
+        }
+        let focus = self.space.elements().last().cloned();
+        if let Some(window) = &focus {
+            self.raise_window(window);
+        }
+        let focus = focus.map(|window| KeyboardFocusTarget::from_window(&window));
+        if let Some(keyboard) = self.seat.get_keyboard() {
+            keyboard.set_focus(self, focus, SERIAL_COUNTER.next_serial());
+        }
+    }
+
+    /// Hide a window until it is restored, the focus goes to the window below
+    pub fn minimize_window(&mut self, window: &Window) {
+        let Some(location) = self.space.element_location(window) else {
+            return;
+        };
+        self.space.unmap_elem(window);
+        self.minimized.push((window.clone(), location));
+        self.frames.schedule();
+        self.focus_top_window();
+    }
+
+    /// Map a minimized window again where it was, on top of the others
+    pub fn restore_window(&mut self, window: &Window) {
+        let Some(index) = self
+            .minimized
+            .iter()
+            .position(|(minimized, _)| minimized == window)
+        else {
+            return;
+        };
+        let (window, location) = self.minimized.remove(index);
+        self.space.map_element(window, location, true);
+        self.frames.schedule();
+    }
+
+    /// Restore, raise and focus a window picked in a task bar or asked for by another app. A lock
+    /// screen or an open menu keeps the focus.
+    pub fn activate_window(&mut self, window: &Window) {
+        if self.seat.get_keyboard().is_some_and(|it| it.is_grabbed())
+            || self.exclusive_layer().is_some()
+        {
+            return;
+        }
+        self.restore_window(window);
+        self.raise_window(window);
+        if let Some(keyboard) = self.seat.get_keyboard() {
+            let focus = Some(KeyboardFocusTarget::from_window(window));
+            keyboard.set_focus(self, focus, SERIAL_COUNTER.next_serial());
+        }
+        self.frames.schedule();
+    }
+
+    /// Move a window. X11 windows are told, as they place their menus relative to the root window.
+    pub fn move_window(&mut self, window: &Window, location: Point<i32, Logical>) {
+        if let Some(surface) = window.x11_surface() {
+            let _ = surface.configure(Rectangle::new(location, surface.geometry().size));
+        }
+        self.space.map_element(window.clone(), location, true);
+    }
+
+    /// Where a new window goes: cascaded from the top left corner of the area panels leave,
+    /// wrapping before it leaves the output
+    pub fn next_window_location(&self) -> Point<i32, Logical> {
+        let area = self.usable_area();
+        let steps = (area.size.w.min(area.size.h) / 2 / CASCADE_STEP).max(1);
+        let offset = (self.space.elements().len() as i32 % steps) * CASCADE_STEP;
+        area.loc + Point::from((offset, offset))
+    }
+
+    /// `location` moved down if needed to keep the title bar of a decorated window on the output, below the panels
+    pub(super) fn below_titlebar(&self, mut location: Point<i32, Logical>) -> Point<i32, Logical> {
+        location.y = location.y.max(self.usable_area().loc.y + TITLEBAR_HEIGHT);
+        location
+    }
+
+    /// Where a maximized or fullscreen toplevel goes, given its pending state: the whole output when
+    /// fullscreen, else the area panels leave, below its title bar when it has one
+    pub(super) fn covered_area(&self, toplevel: &ToplevelSurface) -> Rectangle<i32, Logical> {
+        let (fullscreen, decorated) = toplevel.with_pending_state(|state| {
+            (
+                state.states.contains(xdg_toplevel::State::Fullscreen),
+                server_decorated(state),
+            )
+        });
+        let mut area = if fullscreen {
+            Rectangle::from_size(self.size)
+        } else {
+            self.usable_area()
+        };
+        if decorated {
+            area.loc.y += TITLEBAR_HEIGHT;
+            area.size.h -= TITLEBAR_HEIGHT;
+        }
+        area
+    }
+
+    /// Configure the window to cover the whole output, with the given xdg state (maximized or fullscreen)
+    pub(super) fn cover_output(&mut self, surface: &ToplevelSurface, state: xdg_toplevel::State) {
+        let Some(window) = self.window_for_surface(surface.wl_surface()) else {
+            return;
+        };
+
+        let is_covering = surface.with_pending_state(|pending| {
+            pending.states.contains(xdg_toplevel::State::Maximized)
+                || pending.states.contains(xdg_toplevel::State::Fullscreen)
+        });
+        if !is_covering {
+            let geometry = self.space.element_geometry(&window);
+            let restore = window.user_data().get_or_insert(RestoreGeometry::default);
+            *restore.0.borrow_mut() = geometry;
+        }
+
+        surface.with_pending_state(|pending| pending.states.set(state));
+        let area = self.covered_area(surface);
+        surface.with_pending_state(|pending| pending.size = Some(area.size));
+        self.space.map_element(window, area.loc, true);
+        if surface.is_initial_configure_sent() {
+            surface.send_pending_configure();
+        }
+    }
+
+    /// Undo `cover_output`, going back to the geometry the window had before
+    pub(super) fn uncover_output(&mut self, surface: &ToplevelSurface, state: xdg_toplevel::State) {
+        let Some(window) = self.window_for_surface(surface.wl_surface()) else {
+            return;
+        };
+
+        let still_covering = surface.with_pending_state(|pending| {
+            pending.states.unset(state);
+            pending.states.contains(xdg_toplevel::State::Maximized)
+                || pending.states.contains(xdg_toplevel::State::Fullscreen)
+        });
+        if still_covering {
+            // From fullscreen back to maximized, below the title bar again
+            let area = self.covered_area(surface);
+            surface.with_pending_state(|pending| pending.size = Some(area.size));
+            self.space.map_element(window, area.loc, true);
+        } else {
+            let restore = window
+                .user_data()
+                .get::<RestoreGeometry>()
+                .and_then(|restore| restore.0.borrow_mut().take());
+            let decorated = surface.with_pending_state(|pending| {
+                pending.size = restore.map(|geometry| geometry.size);
+                server_decorated(pending)
+            });
+            // Windows that were never floating go where new windows do
+            let location = match restore {
+                Some(geometry) => geometry.loc,
+                None if decorated => self.below_titlebar(self.next_window_location()),
+                None => self.next_window_location(),
+            };
+            self.space.map_element(window, location, true);
+        }
+        if surface.is_initial_configure_sent() {
+            surface.send_pending_configure();
+        }
+    }
+
+    /// `covered_area` for X11 windows, which are maximized unless `fullscreen`
+    fn covered_area_x11(&self, surface: &X11Surface, fullscreen: bool) -> Rectangle<i32, Logical> {
+        if fullscreen {
+            return Rectangle::from_size(self.size);
+        }
+        let mut area = self.usable_area();
+        if server_decorated_x11(surface) {
+            area.loc.y += TITLEBAR_HEIGHT;
+            area.size.h -= TITLEBAR_HEIGHT;
+        }
+        area
+    }
+
+    /// `cover_output` for X11 windows, called before their maximized or fullscreen state is set
+    pub fn cover_output_x11(&mut self, surface: &X11Surface, fullscreen: bool) {
+        let Some(window) = self.window_for_x11_surface(surface) else {
+            return;
+        };
+
+        if !surface.is_maximized() && !surface.is_fullscreen() {
+            let geometry = self.space.element_geometry(&window);
+            let restore = window.user_data().get_or_insert(RestoreGeometry::default);
+            *restore.0.borrow_mut() = geometry;
+        }
+
+        let area = self.covered_area_x11(surface, fullscreen);
+        let _ = surface.configure(area);
+        self.space.map_element(window, area.loc, true);
+    }
+
+    /// `uncover_output` for X11 windows, called after their maximized or fullscreen state is unset
+    pub fn uncover_output_x11(&mut self, surface: &X11Surface) {
+        let Some(window) = self.window_for_x11_surface(surface) else {
+            return;
+        };
+        if surface.is_fullscreen() {
+            return;
+        }
+        if surface.is_maximized() {
+            // From fullscreen back to maximized, below the title bar again
+            let area = self.covered_area_x11(surface, false);
+            let _ = surface.configure(area);
+            self.space.map_element(window, area.loc, true);
+            return;
+        }
+
+        let restore = window
+            .user_data()
+            .get::<RestoreGeometry>()
+            .and_then(|restore| restore.0.borrow_mut().take());
+        if let Some(geometry) = restore {
+            let _ = surface.configure(geometry);
+            self.space.map_element(window, geometry.loc, true);
+        }
+    }
+
+    /// Resize every maximized and fullscreen window to the current output size and the area panels leave
+    pub fn reconfigure_covering_windows(&mut self) {
+        let windows = self.space.elements().cloned().collect::<Vec<_>>();
+        for window in windows {
+            if let Some(surface) = window.x11_surface() {
+                if !surface.is_maximized() && !surface.is_fullscreen() {
+                    continue;
+                }
+                let area = self.covered_area_x11(surface, surface.is_fullscreen());
+                let _ = surface.configure(area);
+                self.space.map_element(window, area.loc, false);
+                continue;
+            }
+            let Some(toplevel) = window.toplevel().cloned() else {
+                continue;
+            };
+            let covering = toplevel.with_pending_state(|pending| {
+                pending.states.contains(xdg_toplevel::State::Maximized)
+                    || pending.states.contains(xdg_toplevel::State::Fullscreen)
+            });
+            if !covering {
+                continue;
+            }
+            let area = self.covered_area(&toplevel);
+            toplevel.with_pending_state(|pending| pending.size = Some(area.size));
+            self.space.map_element(window, area.loc, false);
+            if toplevel.is_initial_configure_sent() {
+                toplevel.send_pending_configure();
+            }
+        }
+    }
+
+    /// Send the initial configure once the client has set up its toplevel (title, app id, size hints)
+    fn handle_toplevel_commit(&mut self, surface: &WlSurface) {
+        let Some(window) = self.window_for_surface(surface) else {
+            return;
+        };
+        let Some(toplevel) = window.toplevel().cloned() else {
+            return;
+        };
+        if toplevel.is_initial_configure_sent() {
+            return;
+        }
+
+        let app_id = with_states(surface, |states| {
+            states
+                .data_map
+                .get::<XdgToplevelSurfaceData>()
+                .and_then(|data| data.lock().unwrap().app_id.clone())
+        });
+        if app_id.as_deref() == Some(XWAYLAND_APP_ID) {
+            // The X11 desktop manages its own windows, give it the whole output
+            toplevel.with_pending_state(|pending| {
+                pending.decoration_mode = Some(zxdg_toplevel_decoration_v1::Mode::ClientSide);
+            });
+            self.cover_output(&toplevel, xdg_toplevel::State::Maximized);
+        } else if toplevel.with_pending_state(|pending| server_decorated(pending)) {
+            if let Some(location) = self.space.element_location(&window) {
+                let location = self.below_titlebar(location);
+                self.space.map_element(window, location, false);
+            }
+        }
+        toplevel.send_configure();
+    }
+
+    fn handle_popup_commit(&mut self, surface: &WlSurface) {
+        self.popups.commit(surface);
+        if let Some(PopupKind::Xdg(popup)) = self.popups.find_popup(surface) {
+            if !popup.is_initial_configure_sent() {
+                // The initial configure is always allowed
+                let _ = popup.send_configure();
+            }
+        }
+    }
+
+    /// Flip and slide the popup so that it stays inside the output
+    fn unconstrain_popup(&self, popup: &PopupSurface) {
+        let kind = PopupKind::Xdg(popup.clone());
+        let Ok(root) = find_popup_root_surface(&kind) else {
+            return;
+        };
+        let Some(window) = self.window_for_surface(&root) else {
+            return;
+        };
+        let Some(window_geometry) = self.space.element_geometry(&window) else {
+            return;
+        };
+
+        // The positioner works relative to the geometry of the parent
+        let mut target = Rectangle::from_size(self.size);
+        target.loc -= get_popup_toplevel_coords(&kind);
+        target.loc -= window_geometry.loc;
+
+        popup.with_pending_state(|state| {
+            state.geometry = state.positioner.get_unconstrained_geometry(target);
+        });
+    }
+
+    /// Dismiss the grabbed popups when touching outside of them.
+    /// Unlike the pointer, smithay has no touch grab for popups.
+    pub fn dismiss_popups_outside(&mut self, location: Point<f64, Logical>) {
+        let Some(keyboard) = self.seat.get_keyboard() else {
+            return;
+        };
+        let Some(focus) = keyboard
+            .current_focus()
+            .and_then(|focus| focus.wl_surface().map(|it| it.into_owned()))
+        else {
+            return;
+        };
+        let Some(focused_popup) = self.popups.find_popup(&focus) else {
+            return;
+        };
+        let touched_popup = self
+            .surface_under(location)
+            .and_then(|(surface, _)| self.popups.find_popup(&surface));
+        if touched_popup.is_some() {
+            return;
+        }
+
+        if let Ok(root) = find_popup_root_surface(&focused_popup) {
+            for (popup, _) in PopupManager::popups_for_surface(&root).collect::<Vec<_>>() {
+                let _ = PopupManager::dismiss_popup(&root, &popup);
+            }
+            keyboard.unset_grab(self);
+            keyboard.set_focus(self, Some(root.into()), SERIAL_COUNTER.next_serial());
+        }
+    }
+
+    /// The window of `surface` and where it is, to move or resize it.
+    /// Whether the client may start the grab is up to the serial of its request, checked by the caller.
+    fn grab_target(&self, surface: &ToplevelSurface) -> Option<(Window, Point<i32, Logical>)> {
+        let window = self.window_for_surface(surface.wl_surface())?;
+        let location = self.space.element_location(&window)?;
+        Some((window, location))
+    }
+}
+
+impl BufferHandler for State {
+    fn buffer_destroyed(&mut self, _buffer: &wl_buffer::WlBuffer) {}
+}
+
+impl XdgShellHandler for State {
+    fn xdg_shell_state(&mut self) -> &mut XdgShellState {
+        &mut self.xdg_shell_state
+    }
+
+    fn new_toplevel(&mut self, surface: ToplevelSurface) {
+        // The initial configure is sent on the first commit, see `handle_toplevel_commit`
+        let location = self.next_window_location();
+        let window = Window::new_wayland_window(surface);
+        self.space.map_element(window, location, true);
+    }
+
+    fn toplevel_destroyed(&mut self, surface: ToplevelSurface) {
+        self.minimized
+            .retain(|(window, _)| window.toplevel() != Some(&surface));
+        if let Some(window) = self.window_for_surface(surface.wl_surface()) {
+            self.space.unmap_elem(&window);
+            self.frames.schedule();
+        }
+        self.focus_top_window();
+    }
+
+    fn move_request(&mut self, surface: ToplevelSurface, seat: wl_seat::WlSeat, serial: Serial) {
+        let Some(seat) = Seat::<State>::from_resource(&seat) else {
+            return;
+        };
+        let Some((window, initial_window_location)) = self.grab_target(&surface) else {
+            return;
+        };
+
+        if let Some(pointer) = seat.get_pointer() {
+            if let Some(start_data) = pointer.grab_start_data() {
+                if pointer.has_grab(serial) && is_same_client(&start_data.focus, &surface) {
+                    let grab = MoveSurfaceGrab {
+                        start_data,
+                        window,
+                        initial_window_location,
+                    };
+                    pointer.set_grab(self, grab, serial, Focus::Clear);
+                    return;
+                }
+            }
+        }
+
+        if let Some(touch) = seat.get_touch() {
+            if let Some(start_data) = touch.grab_start_data() {
+                if touch.has_grab(serial) && is_same_client(&start_data.focus, &surface) {
+                    let grab = TouchMoveSurfaceGrab {
+                        start_data,
+                        window,
+                        initial_window_location,
+                    };
+                    touch.set_grab(self, grab, serial);
+                }
+            }
+        }
+    }
+
+    fn resize_request(
+        &mut self,
+        surface: ToplevelSurface,
+        seat: wl_seat::WlSeat,
+        serial: Serial,
+        edges: xdg_toplevel::ResizeEdge,
+    ) {
+        let Some(seat) = Seat::<State>::from_resource(&seat) else {
+            return;
+        };
+        let Some((window, initial_window_location)) = self.grab_target(&surface) else {
+            return;
+        };
+        let initial_rect = Rectangle::new(initial_window_location, window.geometry().size);
+
+        if let Some(pointer) = seat.get_pointer() {
+            if let Some(start_data) = pointer.grab_start_data() {
+                if pointer.has_grab(serial) && is_same_client(&start_data.focus, &surface) {
+                    let grab = ResizeSurfaceGrab::start(start_data, window, edges, initial_rect);
+                    pointer.set_grab(self, grab, serial, Focus::Clear);
+                    return;
+                }
+            }
+        }
+
+        if let Some(touch) = seat.get_touch() {
+            if let Some(start_data) = touch.grab_start_data() {
+                if touch.has_grab(serial) && is_same_client(&start_data.focus, &surface) {
+                    let grab =
+                        TouchResizeSurfaceGrab::start(start_data, window, edges, initial_rect);
+                    touch.set_grab(self, grab, serial);
+                }
+            }
+        }
+    }
+
+    fn maximize_request(&mut self, surface: ToplevelSurface) {
+        self.cover_output(&surface, xdg_toplevel::State::Maximized);
+    }
+
+    fn unmaximize_request(&mut self, surface: ToplevelSurface) {
+        self.uncover_output(&surface, xdg_toplevel::State::Maximized);
+    }
+
+    fn minimize_request(&mut self, surface: ToplevelSurface) {
+        if let Some(window) = self.window_for_surface(surface.wl_surface()) {
+            self.minimize_window(&window);
+        }
+    }
+
+    fn title_changed(&mut self, _surface: ToplevelSurface) {
+        // The title bar shows it
+        self.frames.schedule();
+    }
+
+    fn fullscreen_request(
+        &mut self,
+        surface: ToplevelSurface,
+        output: Option<wl_output::WlOutput>,
+    ) {
+        surface.with_pending_state(|state| state.fullscreen_output = output);
+        self.cover_output(&surface, xdg_toplevel::State::Fullscreen);
+    }
+
+    fn unfullscreen_request(&mut self, surface: ToplevelSurface) {
+        surface.with_pending_state(|state| state.fullscreen_output = None);
+        self.uncover_output(&surface, xdg_toplevel::State::Fullscreen);
+    }
+
+    fn new_popup(&mut self, surface: PopupSurface, _positioner: PositionerState) {
+        self.unconstrain_popup(&surface);
+        if let Err(e) = self.popups.track_popup(PopupKind::Xdg(surface)) {
+            log::info!("Failed to track popup: {}", e);
+        }
+    }
+
+    fn grab(&mut self, surface: PopupSurface, seat: wl_seat::WlSeat, serial: Serial) {
+        let Some(seat) = Seat::<State>::from_resource(&seat) else {
+            return;
+        };
+        let kind = PopupKind::Xdg(surface);
+        let Ok(root) = find_popup_root_surface(&kind) else {
+            return;
+        };
+        if self.window_for_surface(&root).is_none() && self.layer_for_surface(&root).is_none() {
+            return;
+        }
+        let Ok(mut grab) = self.popups.grab_popup(root.into(), kind, &seat, serial) else {
+            return;
+        };
+
+        // Only grab if the client is reacting to an input event it actually received (the menu button press)
+        if let Some(keyboard) = seat.get_keyboard() {
+            if keyboard.is_grabbed()
+                && !(keyboard.has_grab(serial)
+                    || keyboard.has_grab(grab.previous_serial().unwrap_or(serial)))
+            {
+                grab.ungrab(PopupUngrabStrategy::All);
+                return;
+            }
+            keyboard.set_focus(self, grab.current_grab(), serial);
+            keyboard.set_grab(self, PopupKeyboardGrab::new(&grab), serial);
+        }
+        if let Some(pointer) = seat.get_pointer() {
+            if pointer.is_grabbed()
+                && !(pointer.has_grab(serial)
+                    || pointer.has_grab(grab.previous_serial().unwrap_or_else(|| grab.serial())))
+            {
+                grab.ungrab(PopupUngrabStrategy::All);
+                return;
+            }
+            pointer.set_grab(self, PopupPointerGrab::new(&grab), serial, Focus::Keep);
+        }
+    }
+
+    fn reposition_request(
+        &mut self,
+        surface: PopupSurface,
+        positioner: PositionerState,
+        token: u32,
+    ) {
+        surface.with_pending_state(|state| {
+            state.geometry = positioner.get_geometry();
+            state.positioner = positioner;
+        });
+        self.unconstrain_popup(&surface);
+        surface.send_repositioned(token);
+    }
+}
+
+impl DataDeviceHandler for State {
+    fn data_device_state(&self) -> &DataDeviceState {
+        &self.data_device_state
+    }
+}
+
+impl ClientDndGrabHandler for State {}
+impl ServerDndGrabHandler for State {
+    fn send(&mut self, _mime_type: String, _fd: OwnedFd, _seat: Seat<Self>) {}
+}
+
+impl CompositorHandler for State {
+    fn compositor_state(&mut self) -> &mut CompositorState {
+        &mut self.compositor_state
+    }
+
+    fn client_compositor_state<'a>(&self, client: &'a Client) -> &'a CompositorClientState {
+        if let Some(state) = client.get_data::<XWaylandClientData>() {
+            return &state.compositor_state;
+        }
+        &client.get_data::<ClientState>().unwrap().compositor_state
+    }
+
+    fn commit(&mut self, surface: &WlSurface) {
+        on_commit_buffer_handler::<Self>(surface);
+        self.frames.schedule();
+
+        if !is_sync_subsurface(surface) {
+            let mut root = surface.clone();
+            while let Some(parent) = get_parent(&root) {
+                root = parent;
+            }
+            if let Some(window) = self.window_for_surface(&root) {
+                // Keep the bounding box used for hit testing up to date
+                window.on_commit();
+            }
+        }
+
+        self.handle_toplevel_commit(surface);
+        self.handle_layer_commit(surface);
+        self.handle_popup_commit(surface);
+        handle_resize_commit(&mut self.space, surface);
+    }
+}
+
+impl ShmHandler for State {
+    fn shm_state(&self) -> &ShmState {
+        &self.shm_state
+    }
+}
+
+impl SeatHandler for State {
+    type KeyboardFocus = KeyboardFocusTarget;
+    type PointerFocus = WlSurface;
+    type TouchFocus = WlSurface;
+
+    fn seat_state(&mut self) -> &mut SeatState<Self> {
+        &mut self.seat_state
+    }
+
+    /// Offer the selections to the client that has the keyboard focus, so it can paste
+    fn focus_changed(&mut self, seat: &Seat<Self>, focused: Option<&KeyboardFocusTarget>) {
+        let client = focused
+            .and_then(|focus| focus.wl_surface())
+            .and_then(|surface| self.display_handle.get_client(surface.id()).ok());
+        set_data_device_focus(&self.display_handle, seat, client.clone());
+        set_primary_focus(&self.display_handle, seat, client);
+    }
+    fn cursor_image(&mut self, _seat: &Seat<Self>, image: CursorImageStatus) {
+        self.cursor_status = image;
+        self.frames.schedule();
+    }
+}
+
+/// Whether a grab was started on a surface of the client that owns `surface`
+fn is_same_client(
+    focus: &Option<(WlSurface, Point<f64, Logical>)>,
+    surface: &ToplevelSurface,
+) -> bool {
+    focus
+        .as_ref()
+        .is_some_and(|(focus, _)| focus.id().same_client_as(&surface.wl_surface().id()))
+}
+
+pub fn send_frames_surface_tree(surface: &WlSurface, time: u32) {
+    with_surface_tree_downward(
+        surface,
+        (),
+        |_, _, &()| TraversalAction::DoChildren(()),
+        |_surf, states, &()| {
+            // the surface may not have any user_data if it is a subsurface and has not
+            // yet been commited
+            for callback in states
+                .cached_state
+                .get::<SurfaceAttributes>()
+                .current()
+                .frame_callbacks
+                .drain(..)
+            {
+                callback.done(time);
+            }
+        },
+        |_, _, &()| true,
+    );
+}
+
+#[derive(Default)]
+pub struct ClientState {
+    compositor_state: CompositorClientState,
+}
+
+impl ClientData for ClientState {
+    fn initialized(&self, _client_id: ClientId) {}
+
+    fn disconnected(&self, _client_id: ClientId, _reason: DisconnectReason) {}
+}
+
+impl OutputHandler for State {}
+
+// Macros used to delegate protocol handling to types in the app state.
+delegate_xdg_shell!(State);
+delegate_compositor!(State);
+delegate_shm!(State);
+delegate_seat!(State);
+delegate_data_device!(State);
+delegate_primary_selection!(State);
+delegate_output!(State);
+delegate_xwayland_shell!(State);
+delegate_fractional_scale!(State);
+delegate_viewporter!(State);
+
+impl Compositor {
+    /// A compositor listening on the socket of the container
+    pub fn build() -> Result<Compositor, Box<dyn Error>> {
+        Self::new(bind_socket()?)
+    }
+
+    /// A compositor accepting clients on `listener`
+    pub fn new(listener: ListeningSocket) -> Result<Compositor, Box<dyn Error>> {
+        let display = Display::new()?;
+        let dh = display.handle();
+        let event_loop = EventLoop::try_new()?;
+
+        let mut seat_state = SeatState::new();
+        let mut seat = seat_state.new_wl_seat(&dh, "Local Desktop");
+
+        let clients = Vec::new();
+
+        let start_time = Instant::now();
+        let clock = Clock::<Monotonic>::new();
+
+        // Key repeat rate and delay are in milliseconds: https://wayland-book.com/seat/keyboard.html
+        let keyboard = seat
+            .add_keyboard(Default::default(), 1000, 200)
+            .expect("Failed to add keyboard");
+        let touch = seat.add_touch();
+        let pointer = seat.add_pointer();
+        // Text inputs are served by the platform keyboard unless an input method runs in the container
+        seat.input_method().set_compositor_fallback(true);
+        seat.tablet_seat()
+            .add_tablet::<State>(&dh, &tablet_descriptor());
+
+        let state = State {
+            compositor_state: CompositorState::new::<State>(&dh),
+            xdg_shell_state: XdgShellState::new::<State>(&dh),
+            xdg_decoration_state: XdgDecorationState::new::<State>(&dh),
+            layer_shell_state: WlrLayerShellState::new::<State>(&dh),
+            shm_state: ShmState::new::<State>(&dh, vec![]),
+            output_manager_state: OutputManagerState::new_with_xdg_output::<State>(&dh),
+            data_device_state: DataDeviceState::new::<State>(&dh),
+            primary_selection_state: PrimarySelectionState::new::<State>(&dh),
+            fractional_scale_state: FractionalScaleManagerState::new::<State>(&dh),
+            viewporter_state: ViewporterState::new::<State>(&dh),
+            text_input_state: TextInputManagerState::new::<State>(&dh),
+            input_method_state: InputMethodManagerState::new::<State, _>(&dh, |_client| true),
+            tablet_manager_state: TabletManagerState::new::<State>(&dh),
+            relative_pointer_state: RelativePointerManagerState::new::<State>(&dh),
+            pointer_constraints_state: PointerConstraintsState::new::<State>(&dh),
+            screencopy_state: ScreencopyState::new(&dh),
+            idle_inhibit_state: IdleInhibitManagerState::new::<State>(&dh),
+            presentation_state: PresentationState::new::<State>(&dh, clock.id() as u32),
+            content_type_state: ContentTypeState::new::<State>(&dh),
+            xdg_activation_state: XdgActivationState::new::<State>(&dh),
+            foreign_toplevel_state: ForeignToplevelState::new(&dh),
+            keyboard_shortcuts_inhibit_state: KeyboardShortcutsInhibitState::new::<State>(&dh),
+            virtual_keyboard_manager_state: VirtualKeyboardManagerState::new::<State, _>(
+                &dh,
+                |_| true,
+            ),
+            virtual_pointer_state: VirtualPointerState::new(&dh),
+            seat_state,
+            seat: seat.clone(),
+            size: (1920, 1080).into(),
+            space: Space::default(),
+            minimized: Vec::new(),
+            popups: PopupManager::default(),
+            display_handle: dh.clone(),
+            loop_handle: event_loop.handle(),
+            xwayland_shell_state: XWaylandShellState::new::<State>(&dh),
+            xwm: None,
+            clipboard: ClipboardConfig::default(),
+            pending_clipboard_export: None,
+            cursor_status: CursorImageStatus::default_named(),
+            cursor_visible: false,
+            frames: FrameScheduler::default(),
+            clock,
+            idle_inhibitors: Vec::new(),
+            titlebar_font: TitlebarFont::default(),
+        };
+
+        Ok(Compositor {
+            state,
+            listener,
+            clients,
+            start_time,
+            display,
+            seat,
+            keyboard,
+            touch,
+            pointer,
+            output: None,
+            event_loop,
+            dispatched: None,
+            preedit: String::new(),
+            stylus: None,
+            stylus_grab: None,
+        })
+    }
+
+    /// Accept a pending client connection, then dispatch and flush the requests of all clients
+    pub fn dispatch(&mut self) {
+        if let Some(stream) = self.listener.accept().expect("Failed to accept listener") {
+            let client = self
+                .display
+                .handle()
+                .insert_client(stream, Arc::new(ClientState::default()))
+                .unwrap();
+            self.clients.push(client);
+        }
+
+        if let Err(e) = self
+            .event_loop
+            .dispatch(Some(Duration::ZERO), &mut self.state)
+        {
+            log::info!("Failed to dispatch the X11 events: {}", e);
+        }
+
+        self.display
+            .dispatch_clients(&mut self.state)
+            .expect("Failed to dispatch clients");
+        self.virtual_pointer_input();
+        self.state.sync_clipboard();
+        self.state.refresh_foreign_toplevels();
+        let continuous = self.state.realtime_content_shown();
+        self.state.frames.set_continuous(continuous);
+        self.display
+            .flush_clients()
+            .expect("Failed to flush clients");
+
+        if let Some(dispatched) = &self.dispatched {
+            let _ = dispatched.try_send(());
+        }
+    }
+
+    /// Call `wake` from another thread whenever a client, a new connection or Xwayland is waiting to be
+    /// dispatched, so that the main loop can sleep while the desktop is idle instead of polling
+    pub fn watch_clients(&mut self, wake: impl Fn() + Send + 'static) {
+        let fds = [
+            self.display.as_fd().try_clone_to_owned(),
+            self.listener.as_fd().try_clone_to_owned(),
+            self.event_loop.as_fd().try_clone_to_owned(),
+        ]
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>();
+        let fds = match fds {
+            Ok(fds) => fds,
+            Err(e) => {
+                log::error!("Failed to watch the clients: {}", e);
+                return;
+            }
+        };
+
+        let (dispatched, wait_dispatched) = mpsc::sync_channel(1);
+        self.dispatched = Some(dispatched);
+        thread::spawn(move || loop {
+            let mut poll_fds = fds
+                .iter()
+                .map(|fd| PollFd::new(fd, PollFlags::IN))
+                .collect::<Vec<_>>();
+            match poll(&mut poll_fds, -1) {
+                Ok(_) | Err(Errno::INTR) => {}
+                Err(e) => {
+                    log::error!("Failed to watch the clients: {}", e);
+                    return;
+                }
+            }
+            wake();
+            // The fds stay readable until the main loop dispatched them, waking it again would only spin
+            if wait_dispatched.recv().is_err() {
+                return;
+            }
+        });
+    }
+}
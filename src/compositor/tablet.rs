@@ -0,0 +1,170 @@
+use super::state::{Compositor, State};
+use smithay::{
+    backend::input::{ButtonState, TabletToolCapabilities, TabletToolDescriptor, TabletToolType},
+    delegate_tablet_manager,
+    utils::{Logical, Point, SERIAL_COUNTER},
+    wayland::tablet_manager::{TabletDescriptor, TabletSeatHandler, TabletSeatTrait},
+};
+
+/// Linux button codes of the stylus buttons, from `linux/input-event-codes.h`
+const BTN_STYLUS: u32 = 0x14b;
+const BTN_STYLUS2: u32 = 0x14c;
+
+impl TabletSeatHandler for State {}
+
+delegate_tablet_manager!(State);
+
+/// Where a stylus is and what it tells beside, in the units of tablet-v2
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StylusInput {
+    pub location: Point<f64, Logical>,
+    /// Whether it is the eraser end of the stylus, which is another tool for clients
+    pub eraser: bool,
+    /// From 0 to 1
+    pub pressure: f64,
+    /// Degrees along the X and Y axes, see `core::stylus::tilt_degrees`
+    pub tilt: (f64, f64),
+    /// From 0 when touching the screen to 1
+    pub distance: f64,
+    pub primary_button: bool,
+    pub secondary_button: bool,
+}
+
+/// The tablet the styluses of the platform belong to, e.g. the touchscreen of the phone
+pub(super) fn tablet_descriptor() -> TabletDescriptor {
+    TabletDescriptor {
+        name: "Local Desktop stylus".into(),
+        usb_id: None,
+        syspath: None,
+    }
+}
+
+fn tool_descriptor(eraser: bool) -> TabletToolDescriptor {
+    TabletToolDescriptor {
+        tool_type: if eraser {
+            TabletToolType::Eraser
+        } else {
+            TabletToolType::Pen
+        },
+        hardware_serial: 0,
+        hardware_id_wacom: 0,
+        capabilities: TabletToolCapabilities::PRESSURE
+            | TabletToolCapabilities::TILT
+            | TabletToolCapabilities::DISTANCE,
+    }
+}
+
+/// Stylus input, sent to clients through tablet-v2 so that drawing apps get pressure and tilt
+impl Compositor {
+    /// Move the stylus, hovering or touching the screen, or press or release its buttons
+    pub fn stylus_motion(&mut self, stylus: StylusInput) {
+        let time = self.time();
+        // Flipping the stylus over to its eraser end brings another tool into proximity
+        if self
+            .stylus
+            .is_some_and(|previous| previous.eraser != stylus.eraser)
+        {
+            self.stylus_leave();
+        }
+        let Some(tablet) = self.seat.tablet_seat().get_tablet(&tablet_descriptor()) else {
+            return;
+        };
+        self.state.frames.schedule();
+        self.state.cursor_visible = false;
+
+        let display_handle = self.display.handle();
+        let tool = self.seat.tablet_seat().add_tool::<State>(
+            &mut self.state,
+            &display_handle,
+            &tool_descriptor(stylus.eraser),
+        );
+        tool.pressure(stylus.pressure);
+        tool.tilt(stylus.tilt);
+        tool.distance(stylus.distance);
+        // Like a pointer button, the surface the tip went down on keeps the stylus until it is lifted
+        let focus = match &self.stylus_grab {
+            Some(grab) => Some(grab.clone()),
+            None => self.state.surface_under(stylus.location),
+        };
+        let serial = SERIAL_COUNTER.next_serial();
+        tool.motion(stylus.location, focus, &tablet, serial, time);
+
+        let previous = self.stylus.replace(stylus);
+        let buttons = [
+            (
+                BTN_STYLUS,
+                stylus.primary_button,
+                previous.map(|it| it.primary_button),
+            ),
+            (
+                BTN_STYLUS2,
+                stylus.secondary_button,
+                previous.map(|it| it.secondary_button),
+            ),
+        ];
+        for (button, pressed, was_pressed) in buttons {
+            if pressed != was_pressed.unwrap_or(false) {
+                let state = if pressed {
+                    ButtonState::Pressed
+                } else {
+                    ButtonState::Released
+                };
+                tool.button(button, state, SERIAL_COUNTER.next_serial(), time);
+            }
+        }
+    }
+
+    /// The tip of the stylus touched the screen where it was last moved to
+    pub fn stylus_down(&mut self) {
+        let Some(stylus) = self.stylus else {
+            return;
+        };
+        let Some(tool) = self
+            .seat
+            .tablet_seat()
+            .get_tool(&tool_descriptor(stylus.eraser))
+        else {
+            return;
+        };
+        self.state.frames.schedule();
+        let serial = SERIAL_COUNTER.next_serial();
+        let time = self.time();
+
+        self.state.dismiss_popups_outside(stylus.location);
+        self.state.focus_window_under(stylus.location, serial);
+        self.stylus_grab = self.state.surface_under(stylus.location);
+        tool.tip_down(serial, time);
+    }
+
+    /// The tip of the stylus no longer touches the screen
+    pub fn stylus_up(&mut self) {
+        self.stylus_grab = None;
+        let Some(stylus) = self.stylus else {
+            return;
+        };
+        if let Some(tool) = self
+            .seat
+            .tablet_seat()
+            .get_tool(&tool_descriptor(stylus.eraser))
+        {
+            self.state.frames.schedule();
+            tool.tip_up(self.time());
+        }
+    }
+
+    /// The stylus went out of range
+    pub fn stylus_leave(&mut self) {
+        self.stylus_grab = None;
+        let Some(stylus) = self.stylus.take() else {
+            return;
+        };
+        if let Some(tool) = self
+            .seat
+            .tablet_seat()
+            .get_tool(&tool_descriptor(stylus.eraser))
+        {
+            self.state.frames.schedule();
+            tool.proximity_out(self.time());
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use super::state::{Compositor, State};
+use crate::core::text_input::utf8_delete_lengths;
+use smithay::{
+    backend::input::KeyState,
+    delegate_input_method_manager, delegate_text_input_manager,
+    desktop::{PopupKind, PopupManager},
+    input::keyboard::Keycode,
+    reexports::wayland_server::protocol::wl_surface::WlSurface,
+    utils::{Logical, Rectangle},
+    wayland::{
+        input_method::{InputMethodHandler, InputMethodSeat, PopupSurface},
+        seat::WaylandFocus,
+        text_input::{TextInputInfo, TextInputSeat},
+    },
+};
+
+impl InputMethodHandler for State {
+    fn new_popup(&mut self, surface: PopupSurface) {
+        if let Err(e) = self.popups.track_popup(PopupKind::from(surface)) {
+            log::info!("Failed to track the input method popup: {}", e);
+        }
+    }
+
+    fn dismiss_popup(&mut self, surface: PopupSurface) {
+        if let Some(parent) = surface.get_parent().map(|parent| parent.surface.clone()) {
+            let _ = PopupManager::dismiss_popup(&parent, &PopupKind::from(surface));
+        }
+    }
+
+    fn popup_repositioned(&mut self, _surface: PopupSurface) {}
+
+    fn parent_geometry(&self, parent: &WlSurface) -> Rectangle<i32, Logical> {
+        self.space
+            .elements()
+            .find(|window| window.wl_surface().as_deref() == Some(parent))
+            .map(|window| window.geometry())
+            .unwrap_or_default()
+    }
+}
+
+delegate_input_method_manager!(State);
+delegate_text_input_manager!(State);
+
+/// `KEY_BACKSPACE` and `KEY_DELETE`, offset by 8 like all XKB keycodes
+const BACKSPACE: Keycode = Keycode::new(22);
+const DELETE: Keycode = Keycode::new(119);
+
+/// Text typed with the keyboard of the platform (e.g. the Android soft keyboard) rather than with keys.
+/// It goes to the focused client through text-input, unless an input method runs in the container.
+impl Compositor {
+    /// The text input the platform keyboard should type into, `None` when no client wants text
+    pub fn active_text_input(&self) -> Option<TextInputInfo> {
+        if self.seat.input_method().has_client() {
+            return None;
+        }
+        self.seat.text_input().active_text_input_info()
+    }
+
+    /// Show `text` as being composed at the cursor, `cursor` being the selected byte range within it
+    pub fn ime_preedit(&mut self, text: String, cursor: Option<(usize, usize)>) {
+        let (begin, end) = cursor.map_or((-1, -1), |(begin, end)| (begin as i32, end as i32));
+        self.preedit = text.clone();
+        let text_input = self.seat.text_input();
+        text_input.with_active_text_input(|text_input, _| {
+            text_input.preedit_string(Some(text.clone()), begin, end);
+        });
+        text_input.done(false);
+        self.state.frames.schedule();
+    }
+
+    /// Insert `text` at the cursor, replacing the text being composed
+    pub fn ime_commit(&mut self, text: String) {
+        self.preedit.clear();
+        let text_input = self.seat.text_input();
+        text_input.with_active_text_input(|text_input, _| {
+            text_input.commit_string(Some(text.clone()));
+        });
+        text_input.done(false);
+        self.state.frames.schedule();
+    }
+
+    /// Keep the text being composed as it is
+    pub fn ime_finish_composing(&mut self) {
+        if !self.preedit.is_empty() {
+            self.ime_commit(self.preedit.clone());
+        }
+    }
+
+    /// Delete `before` UTF-16 code units before the cursor and `after` after it, as the platform counts
+    /// them. They are converted to bytes within the text the client said is around the cursor. Clients
+    /// that do not say press backspace and delete instead, once per code unit.
+    pub fn ime_delete_surrounding(&mut self, before: u32, after: u32) {
+        let lengths = self
+            .active_text_input()
+            .and_then(|info| info.surrounding_text)
+            .and_then(|(text, cursor, anchor)| {
+                utf8_delete_lengths(&text, cursor, anchor, before, after)
+            });
+        let Some((before, after)) = lengths else {
+            for (keycode, count) in [(BACKSPACE, before), (DELETE, after)] {
+                for _ in 0..count {
+                    self.key(keycode, KeyState::Pressed);
+                    self.key(keycode, KeyState::Released);
+                }
+            }
+            return;
+        };
+
+        let text_input = self.seat.text_input();
+        text_input.with_active_text_input(|text_input, _| {
+            text_input.delete_surrounding_text(before, after);
+        });
+        text_input.done(false);
+        self.state.frames.schedule();
+    }
+}
@@ -0,0 +1,234 @@
+use super::state::{Compositor, State};
+use smithay::{
+    backend::input::{Axis, AxisSource, ButtonState},
+    delegate_virtual_keyboard_manager,
+    input::pointer::AxisFrame,
+    output::Output,
+    reexports::{
+        wayland_protocols_wlr::virtual_pointer::v1::server::{
+            zwlr_virtual_pointer_manager_v1::{self, ZwlrVirtualPointerManagerV1},
+            zwlr_virtual_pointer_v1::{self, ZwlrVirtualPointerV1},
+        },
+        wayland_server::{
+            protocol::wl_pointer, Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New,
+            Resource, WEnum,
+        },
+    },
+    utils::{Logical, Point},
+};
+use std::sync::Mutex;
+
+// On-screen keyboards like wvkbd and squeekboard upload their own keymap, and their keys go to the
+// focused client like those of a real keyboard
+delegate_virtual_keyboard_manager!(State);
+
+/// Pointers of clients, e.g. a touch gamepad overlay or ydotool, moved with wlr-virtual-pointer.
+/// Their events are queued while the clients are dispatched, and go through the pointer of the seat
+/// like those of a real mouse in `Compositor::dispatch`.
+pub struct VirtualPointerState {
+    events: Vec<VirtualPointerEvent>,
+}
+
+impl VirtualPointerState {
+    pub fn new(display_handle: &DisplayHandle) -> Self {
+        display_handle.create_global::<State, ZwlrVirtualPointerManagerV1, _>(2, ());
+        Self { events: Vec::new() }
+    }
+}
+
+enum VirtualPointerEvent {
+    Motion(Point<f64, Logical>),
+    /// Where in the output, or in all of them, the pointer is, from 0 to 1
+    MotionAbsolute(Option<Output>, Point<f64, Logical>),
+    Button(u32, ButtonState),
+    Axis(AxisFrame),
+}
+
+/// A virtual pointer, and the axis events it sent since its last frame
+pub struct VirtualPointerData {
+    /// The output it is mapped to, all of them otherwise
+    output: Option<Output>,
+    axis: Mutex<Option<AxisFrame>>,
+}
+
+impl VirtualPointerData {
+    /// Add an axis event to the frame sent with the next `frame` request
+    fn axis(&self, event: impl FnOnce(AxisFrame) -> AxisFrame) {
+        let mut axis = self.axis.lock().unwrap();
+        // The time is that of the compositor, see `Compositor::virtual_pointer_input`
+        *axis = Some(event(axis.take().unwrap_or_else(|| AxisFrame::new(0))));
+    }
+}
+
+impl GlobalDispatch<ZwlrVirtualPointerManagerV1, ()> for State {
+    fn bind(
+        _state: &mut Self,
+        _handle: &DisplayHandle,
+        _client: &Client,
+        resource: New<ZwlrVirtualPointerManagerV1>,
+        _global_data: &(),
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        data_init.init(resource, ());
+    }
+}
+
+impl Dispatch<ZwlrVirtualPointerManagerV1, ()> for State {
+    fn request(
+        _state: &mut Self,
+        _client: &Client,
+        _resource: &ZwlrVirtualPointerManagerV1,
+        request: zwlr_virtual_pointer_manager_v1::Request,
+        _data: &(),
+        _dhandle: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        use zwlr_virtual_pointer_manager_v1::Request;
+
+        // There is only one seat, so the one asked for is ignored
+        let (id, output) = match request {
+            Request::CreateVirtualPointer { id, .. } => (id, None),
+            Request::CreateVirtualPointerWithOutput { id, output, .. } => {
+                (id, output.as_ref().and_then(Output::from_resource))
+            }
+            _ => return,
+        };
+        data_init.init(
+            id,
+            VirtualPointerData {
+                output,
+                axis: Mutex::new(None),
+            },
+        );
+    }
+}
+
+impl Dispatch<ZwlrVirtualPointerV1, VirtualPointerData> for State {
+    fn request(
+        state: &mut Self,
+        _client: &Client,
+        resource: &ZwlrVirtualPointerV1,
+        request: zwlr_virtual_pointer_v1::Request,
+        data: &VirtualPointerData,
+        _dhandle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, Self>,
+    ) {
+        use zwlr_virtual_pointer_v1::{Error, Request};
+
+        let valid_axis = |axis: WEnum<wl_pointer::Axis>| {
+            let axis = axis
+                .into_result()
+                .ok()
+                .and_then(|axis| Axis::try_from(axis).ok());
+            if axis.is_none() {
+                resource.post_error(Error::InvalidAxis, "Invalid axis");
+            }
+            axis
+        };
+
+        let events = &mut state.virtual_pointer_state.events;
+        match request {
+            Request::Motion { dx, dy, .. } => {
+                events.push(VirtualPointerEvent::Motion((dx, dy).into()));
+            }
+            Request::MotionAbsolute {
+                x,
+                y,
+                x_extent,
+                y_extent,
+                ..
+            } => {
+                if x_extent == 0 || y_extent == 0 {
+                    return;
+                }
+                let location = (x as f64 / x_extent as f64, y as f64 / y_extent as f64);
+                events.push(VirtualPointerEvent::MotionAbsolute(
+                    data.output.clone(),
+                    location.into(),
+                ));
+            }
+            Request::Button {
+                button,
+                state: WEnum::Value(button_state),
+                ..
+            } => {
+                if let Ok(button_state) = ButtonState::try_from(button_state) {
+                    events.push(VirtualPointerEvent::Button(button, button_state));
+                }
+            }
+            Request::Axis { axis, value, .. } => {
+                if let Some(axis) = valid_axis(axis) {
+                    data.axis(|frame| frame.value(axis, value));
+                }
+            }
+            Request::AxisDiscrete {
+                axis,
+                value,
+                discrete,
+                ..
+            } => {
+                if let Some(axis) = valid_axis(axis) {
+                    data.axis(|frame| frame.value(axis, value).v120(axis, discrete * 120));
+                }
+            }
+            Request::AxisStop { axis, .. } => {
+                if let Some(axis) = valid_axis(axis) {
+                    data.axis(|frame| frame.stop(axis));
+                }
+            }
+            Request::AxisSource { axis_source } => {
+                let source = match axis_source {
+                    WEnum::Value(wl_pointer::AxisSource::Wheel) => AxisSource::Wheel,
+                    WEnum::Value(wl_pointer::AxisSource::Finger) => AxisSource::Finger,
+                    WEnum::Value(wl_pointer::AxisSource::Continuous) => AxisSource::Continuous,
+                    WEnum::Value(wl_pointer::AxisSource::WheelTilt) => AxisSource::WheelTilt,
+                    _ => {
+                        resource.post_error(Error::InvalidAxisSource, "Invalid axis source");
+                        return;
+                    }
+                };
+                data.axis(|frame| frame.source(source));
+            }
+            Request::Frame => {
+                if let Some(frame) = data.axis.lock().unwrap().take() {
+                    events.push(VirtualPointerEvent::Axis(frame));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Compositor {
+    /// Move the pointer as the virtual pointers of the clients asked since the last time
+    pub(super) fn virtual_pointer_input(&mut self) {
+        let events = std::mem::take(&mut self.state.virtual_pointer_state.events);
+        for event in events {
+            let time = self.time();
+            match event {
+                VirtualPointerEvent::Motion(delta) => self.pointer_motion_relative(delta, time),
+                VirtualPointerEvent::MotionAbsolute(output, location) => {
+                    let bounds = match output {
+                        Some(output) => self.state.space.output_geometry(&output),
+                        None => self.state.outputs_bounds(),
+                    };
+                    let Some(bounds) = bounds else {
+                        continue;
+                    };
+                    let bounds = bounds.to_f64();
+                    // Like wlroots, so that an extent of the output size maps to its pixels
+                    let location = bounds.loc
+                        + Point::from((location.x * bounds.size.w, location.y * bounds.size.h));
+                    self.pointer_motion(location, time);
+                }
+                VirtualPointerEvent::Button(button, state) => {
+                    self.pointer_button(button, state, time)
+                }
+                VirtualPointerEvent::Axis(mut frame) => {
+                    frame.time = time;
+                    self.pointer_axis(frame);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,365 @@
+use super::{
+    decoration::server_decorated_x11,
+    focus::KeyboardFocusTarget,
+    grabs::{MoveSurfaceGrab, ResizeSurfaceGrab, TouchMoveSurfaceGrab, TouchResizeSurfaceGrab},
+    selection::{send_android_selection, ServerSelection},
+    state::{Compositor, State},
+};
+use crate::core::config;
+use smithay::{
+    desktop::Window,
+    input::pointer::Focus,
+    reexports::wayland_protocols::xdg::shell::server::xdg_toplevel,
+    utils::{Logical, Rectangle, SERIAL_COUNTER},
+    wayland::{
+        selection::{
+            data_device::{
+                clear_data_device_selection, current_data_device_selection_userdata,
+                request_data_device_client_selection, set_data_device_selection,
+            },
+            primary_selection::{
+                clear_primary_selection, current_primary_selection_userdata,
+                request_primary_client_selection, set_primary_selection,
+            },
+            SelectionTarget,
+        },
+        xwayland_shell::{XWaylandShellHandler, XWaylandShellState},
+    },
+    xwayland::{
+        xwm::{Reorder, ResizeEdge, WmWindowProperty, XwmId},
+        X11Surface, X11Wm, XWayland, XWaylandEvent, XwmHandler,
+    },
+};
+use std::{fs, os::unix::io::OwnedFd, process::Command};
+
+/// X11 clients find the display at `DISPLAY=:1`, like the rootful Xwayland used to be
+const XWAYLAND_DISPLAY: u32 = 1;
+
+impl Compositor {
+    /// Start a rootless Xwayland and become its window manager, so X11 windows are managed next to the Wayland ones.
+    /// `command` runs Xwayland inside the container, the arguments and the sockets it needs are added to it.
+    pub fn start_xwayland(&mut self, command: Command) {
+        let tmp_dir = config::ARCH_FS_ROOT.to_owned() + "/tmp";
+        // A previous run of the app may have left its lock behind, with a PID that has since been reused
+        let _ = fs::remove_file(format!("{}/.X{}-lock", tmp_dir, XWAYLAND_DISPLAY));
+        if let Err(e) = fs::create_dir_all(format!("{}/.X11-unix", tmp_dir)) {
+            log::info!("Failed to create the X11 socket directory: {}", e);
+        }
+
+        let (xwayland, client) = match XWayland::spawn_command(
+            &self.display.handle(),
+            XWAYLAND_DISPLAY,
+            command,
+            tmp_dir,
+            false,
+            |_| (),
+        ) {
+            Ok(it) => it,
+            Err(e) => {
+                log::error!("Failed to start Xwayland: {}", e);
+                return;
+            }
+        };
+
+        let result = self
+            .event_loop
+            .handle()
+            .insert_source(xwayland, move |event, _, state| match event {
+                XWaylandEvent::Ready { x11_socket, .. } => {
+                    match X11Wm::start_wm(state.loop_handle.clone(), x11_socket, client.clone()) {
+                        Ok(xwm) => state.xwm = Some(xwm),
+                        Err(e) => log::error!("Failed to start the X11 window manager: {}", e),
+                    }
+                }
+                XWaylandEvent::Error => {
+                    log::error!("Xwayland exited during startup");
+                }
+            });
+        if let Err(e) = result {
+            log::error!("Failed to watch Xwayland: {}", e);
+        }
+    }
+}
+
+impl State {
+    pub fn window_for_x11_surface(&self, surface: &X11Surface) -> Option<Window> {
+        self.space
+            .elements()
+            .find(|window| window.x11_surface() == Some(surface))
+            .cloned()
+    }
+}
+
+impl XWaylandShellHandler for State {
+    fn xwayland_shell_state(&mut self) -> &mut XWaylandShellState {
+        &mut self.xwayland_shell_state
+    }
+}
+
+impl XwmHandler for State {
+    fn xwm_state(&mut self, _xwm: XwmId) -> &mut X11Wm {
+        self.xwm.as_mut().unwrap()
+    }
+
+    fn new_window(&mut self, _xwm: XwmId, _window: X11Surface) {}
+
+    fn new_override_redirect_window(&mut self, _xwm: XwmId, _window: X11Surface) {}
+
+    fn map_window_request(&mut self, _xwm: XwmId, surface: X11Surface) {
+        if let Err(e) = surface.set_mapped(true) {
+            log::info!("Failed to map X11 window: {}", e);
+            return;
+        }
+        let mut location = self.next_window_location();
+        if server_decorated_x11(&surface) {
+            location = self.below_titlebar(location);
+        }
+        let _ = surface.configure(Rectangle::new(location, surface.geometry().size));
+        let window = Window::new_x11_window(surface);
+        self.space.map_element(window, location, true);
+        self.frames.schedule();
+    }
+
+    fn mapped_override_redirect_window(&mut self, _xwm: XwmId, surface: X11Surface) {
+        // Menus and tooltips place themselves
+        let location = surface.geometry().loc;
+        let window = Window::new_x11_window(surface);
+        self.space.map_element(window, location, true);
+        self.frames.schedule();
+    }
+
+    fn unmapped_window(&mut self, _xwm: XwmId, surface: X11Surface) {
+        if let Some(window) = self.window_for_x11_surface(&surface) {
+            self.space.unmap_elem(&window);
+            self.frames.schedule();
+        }
+        if !surface.is_override_redirect() {
+            let _ = surface.set_mapped(false);
+            self.focus_top_window();
+        }
+    }
+
+    fn destroyed_window(&mut self, _xwm: XwmId, _window: X11Surface) {}
+
+    fn configure_request(
+        &mut self,
+        _xwm: XwmId,
+        surface: X11Surface,
+        _x: Option<i32>,
+        _y: Option<i32>,
+        w: Option<u32>,
+        h: Option<u32>,
+        _reorder: Option<Reorder>,
+    ) {
+        // Windows can pick their size, but are placed by us like the Wayland ones
+        let mut geometry = surface.geometry();
+        if let Some(window) = self.window_for_x11_surface(&surface) {
+            if let Some(location) = self.space.element_location(&window) {
+                geometry.loc = location;
+            }
+        }
+        if let Some(w) = w {
+            geometry.size.w = w as i32;
+        }
+        if let Some(h) = h {
+            geometry.size.h = h as i32;
+        }
+        let _ = surface.configure(geometry);
+    }
+
+    fn configure_notify(
+        &mut self,
+        _xwm: XwmId,
+        surface: X11Surface,
+        geometry: Rectangle<i32, Logical>,
+        _above: Option<u32>,
+    ) {
+        if let Some(window) = self.window_for_x11_surface(&surface) {
+            self.space.map_element(window, geometry.loc, false);
+            self.frames.schedule();
+        }
+    }
+
+    fn property_notify(&mut self, _xwm: XwmId, _surface: X11Surface, property: WmWindowProperty) {
+        // The title bar shows it
+        if property == WmWindowProperty::Title {
+            self.frames.schedule();
+        }
+    }
+
+    fn maximize_request(&mut self, _xwm: XwmId, surface: X11Surface) {
+        self.cover_output_x11(&surface, false);
+        let _ = surface.set_maximized(true);
+    }
+
+    fn unmaximize_request(&mut self, _xwm: XwmId, surface: X11Surface) {
+        let _ = surface.set_maximized(false);
+        self.uncover_output_x11(&surface);
+    }
+
+    fn fullscreen_request(&mut self, _xwm: XwmId, surface: X11Surface) {
+        self.cover_output_x11(&surface, true);
+        let _ = surface.set_fullscreen(true);
+    }
+
+    fn unfullscreen_request(&mut self, _xwm: XwmId, surface: X11Surface) {
+        let _ = surface.set_fullscreen(false);
+        self.uncover_output_x11(&surface);
+    }
+
+    fn resize_request(
+        &mut self,
+        _xwm: XwmId,
+        surface: X11Surface,
+        _button: u32,
+        edges: ResizeEdge,
+    ) {
+        let Some(window) = self.window_for_x11_surface(&surface) else {
+            return;
+        };
+        let Some(initial_rect) = self.space.element_geometry(&window) else {
+            return;
+        };
+        let edges = xdg_resize_edge(edges);
+
+        // Like moves, with whichever of the pointer or a finger is down
+        if let Some(pointer) = self.seat.get_pointer() {
+            if let Some(start_data) = pointer.grab_start_data() {
+                let grab = ResizeSurfaceGrab::start(start_data, window, edges, initial_rect);
+                pointer.set_grab(self, grab, SERIAL_COUNTER.next_serial(), Focus::Clear);
+                return;
+            }
+        }
+
+        if let Some(touch) = self.seat.get_touch() {
+            if let Some(start_data) = touch.grab_start_data() {
+                let grab = TouchResizeSurfaceGrab::start(start_data, window, edges, initial_rect);
+                touch.set_grab(self, grab, SERIAL_COUNTER.next_serial());
+            }
+        }
+    }
+
+    fn move_request(&mut self, _xwm: XwmId, surface: X11Surface, _button: u32) {
+        let Some(window) = self.window_for_x11_surface(&surface) else {
+            return;
+        };
+        let Some(initial_window_location) = self.space.element_location(&window) else {
+            return;
+        };
+
+        // X11 requests carry no serial, so move with whichever of the pointer or a finger is down
+        if let Some(pointer) = self.seat.get_pointer() {
+            if let Some(start_data) = pointer.grab_start_data() {
+                let grab = MoveSurfaceGrab {
+                    start_data,
+                    window,
+                    initial_window_location,
+                };
+                pointer.set_grab(self, grab, SERIAL_COUNTER.next_serial(), Focus::Clear);
+                return;
+            }
+        }
+
+        if let Some(touch) = self.seat.get_touch() {
+            if let Some(start_data) = touch.grab_start_data() {
+                let grab = TouchMoveSurfaceGrab {
+                    start_data,
+                    window,
+                    initial_window_location,
+                };
+                touch.set_grab(self, grab, SERIAL_COUNTER.next_serial());
+            }
+        }
+    }
+
+    fn allow_selection_access(&mut self, xwm: XwmId, _selection: SelectionTarget) -> bool {
+        // Only the focused X11 window may read the clipboard
+        self.seat
+            .get_keyboard()
+            .and_then(|keyboard| keyboard.current_focus())
+            .is_some_and(|focus| {
+                matches!(focus, KeyboardFocusTarget::X11(surface) if surface.xwm_id() == Some(xwm))
+            })
+    }
+
+    /// An X11 client pastes what was copied in a Wayland window or on Android
+    fn send_selection(
+        &mut self,
+        _xwm: XwmId,
+        selection: SelectionTarget,
+        mime_type: String,
+        fd: OwnedFd,
+    ) {
+        let result = match selection {
+            SelectionTarget::Clipboard => {
+                let android =
+                    current_data_device_selection_userdata(&self.seat).and_then(|it| match &*it {
+                        ServerSelection::Android(content) => Some(content.clone()),
+                        ServerSelection::Xwayland => None,
+                    });
+                if let Some(content) = android {
+                    send_android_selection(content, mime_type, fd);
+                    return;
+                }
+                request_data_device_client_selection(&self.seat, mime_type, fd)
+                    .map_err(|e| e.to_string())
+            }
+            SelectionTarget::Primary => request_primary_client_selection(&self.seat, mime_type, fd)
+                .map_err(|e| e.to_string()),
+        };
+        if let Err(e) = result {
+            log::info!("Failed to send the Wayland selection to Xwayland: {}", e);
+        }
+    }
+
+    /// An X11 client copied something, offer it to the Wayland clients and to Android
+    fn new_selection(&mut self, _xwm: XwmId, selection: SelectionTarget, mime_types: Vec<String>) {
+        match selection {
+            SelectionTarget::Clipboard => {
+                self.pending_clipboard_export = Some(mime_types.clone());
+                set_data_device_selection(
+                    &self.display_handle,
+                    &self.seat,
+                    mime_types,
+                    ServerSelection::Xwayland,
+                );
+            }
+            SelectionTarget::Primary => {
+                set_primary_selection(
+                    &self.display_handle,
+                    &self.seat,
+                    mime_types,
+                    ServerSelection::Xwayland,
+                );
+            }
+        }
+    }
+
+    fn cleared_selection(&mut self, _xwm: XwmId, selection: SelectionTarget) {
+        match selection {
+            SelectionTarget::Clipboard => {
+                if current_data_device_selection_userdata(&self.seat).is_some() {
+                    clear_data_device_selection(&self.display_handle, &self.seat);
+                }
+            }
+            SelectionTarget::Primary => {
+                if current_primary_selection_userdata(&self.seat).is_some() {
+                    clear_primary_selection(&self.display_handle, &self.seat);
+                }
+            }
+        }
+    }
+}
+
+fn xdg_resize_edge(edge: ResizeEdge) -> xdg_toplevel::ResizeEdge {
+    match edge {
+        ResizeEdge::Top => xdg_toplevel::ResizeEdge::Top,
+        ResizeEdge::Bottom => xdg_toplevel::ResizeEdge::Bottom,
+        ResizeEdge::Left => xdg_toplevel::ResizeEdge::Left,
+        ResizeEdge::TopLeft => xdg_toplevel::ResizeEdge::TopLeft,
+        ResizeEdge::BottomLeft => xdg_toplevel::ResizeEdge::BottomLeft,
+        ResizeEdge::Right => xdg_toplevel::ResizeEdge::Right,
+        ResizeEdge::TopRight => xdg_toplevel::ResizeEdge::TopRight,
+        ResizeEdge::BottomRight => xdg_toplevel::ResizeEdge::BottomRight,
+    }
+}
@@ -0,0 +1,113 @@
+use std::io::{self, Read};
+use std::sync::Mutex;
+
+/// MIME types text is offered as, by order of preference. Xwayland offers the X11 names as well.
+pub const TEXT_MIME_TYPES: &[&str] = &[
+    "text/plain;charset=utf-8",
+    "text/plain",
+    "UTF8_STRING",
+    "STRING",
+    "TEXT",
+];
+
+/// Something copied on one side, waiting to be pasted on the other
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClipContent {
+    Text(String),
+    Image { mime_type: String, data: Vec<u8> },
+}
+
+impl ClipContent {
+    pub fn mime_types(&self) -> Vec<String> {
+        match self {
+            Self::Text(_) => TEXT_MIME_TYPES.iter().map(|it| it.to_string()).collect(),
+            Self::Image { mime_type, .. } => vec![mime_type.clone()],
+        }
+    }
+
+    /// The bytes to send to a client asking for `mime_type`
+    pub fn data(&self, mime_type: &str) -> Option<&[u8]> {
+        match self {
+            Self::Text(text) if TEXT_MIME_TYPES.contains(&mime_type) => Some(text.as_bytes()),
+            Self::Image {
+                mime_type: image_mime_type,
+                data,
+            } if image_mime_type == mime_type => Some(data),
+            _ => None,
+        }
+    }
+}
+
+/// The text MIME type to request from a Linux selection, if it has any
+pub fn preferred_text_mime_type(offered: &[String]) -> Option<&'static str> {
+    TEXT_MIME_TYPES
+        .iter()
+        .find(|mime_type| offered.iter().any(|it| it == *mime_type))
+        .copied()
+}
+
+/// Read until the end, or return `None` as soon as more than `limit` bytes came in
+pub fn read_limited(reader: impl Read, limit: usize) -> io::Result<Option<Vec<u8>>> {
+    let mut data = Vec::new();
+    reader.take(limit as u64 + 1).read_to_end(&mut data)?;
+    if data.len() > limit {
+        return Ok(None);
+    }
+    Ok(Some(data))
+}
+
+/// Copied on Android, to be offered to the Linux clients by the compositor
+static FROM_ANDROID: Mutex<Option<ClipContent>> = Mutex::new(None);
+/// Copied on the Linux desktop, to be put in the Android clipboard
+static TO_ANDROID: Mutex<Option<ClipContent>> = Mutex::new(None);
+
+pub fn send_to_desktop(content: ClipContent) {
+    *FROM_ANDROID.lock().expect("Failed to lock clipboard") = Some(content);
+}
+
+pub fn take_for_desktop() -> Option<ClipContent> {
+    FROM_ANDROID
+        .lock()
+        .expect("Failed to lock clipboard")
+        .take()
+}
+
+pub fn send_to_android(content: ClipContent) {
+    *TO_ANDROID.lock().expect("Failed to lock clipboard") = Some(content);
+}
+
+pub fn take_for_android() -> Option<ClipContent> {
+    TO_ANDROID.lock().expect("Failed to lock clipboard").take()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_prefer_utf8_text() {
+        let offered = vec![
+            "STRING".to_string(),
+            "text/html".to_string(),
+            "text/plain;charset=utf-8".to_string(),
+        ];
+        assert_eq!(
+            preferred_text_mime_type(&offered),
+            Some("text/plain;charset=utf-8")
+        );
+        assert_eq!(preferred_text_mime_type(&["image/png".to_string()]), None);
+
+        let text = ClipContent::Text("hello".to_string());
+        assert_eq!(text.data("UTF8_STRING"), Some("hello".as_bytes()));
+        assert_eq!(text.data("image/png"), None);
+    }
+
+    #[test]
+    fn should_drop_oversized_content() {
+        assert_eq!(
+            read_limited(&b"hello"[..], 5).unwrap(),
+            Some(b"hello".to_vec())
+        );
+        assert_eq!(read_limited(&b"hello!"[..], 5).unwrap(), None);
+    }
+}
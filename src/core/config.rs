@@ -36,6 +36,22 @@ pub struct LocalConfig {
     /// => So make sure that every config group has a `#[serde(default)]` attribute to avoid invalid sections breaking unrelated parts of the config.
     #[serde(default)]
     pub command: CommandConfig,
+
+    #[serde(default)]
+    pub session: SessionConfig,
+
+    #[serde(default)]
+    pub task_manager: TaskManagerConfig,
+
+    #[serde(default)]
+    pub clipboard: ClipboardConfig,
+
+    #[serde(default)]
+    pub display: DisplayConfig,
+
+    /// Servers to run inside the container, declared with repeated `[[service]]` tables
+    #[serde(default, rename = "service")]
+    pub services: Vec<ServiceConfig>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -51,6 +67,25 @@ impl Default for UserConfig {
     }
 }
 
+/// Whether `username` is accepted by `useradd`, i.e. matches `^[a-z_][a-z0-9_-]{0,31}$`
+pub fn is_valid_username(username: &str) -> bool {
+    let mut chars = username.chars();
+    chars
+        .next()
+        .is_some_and(|first| first.is_ascii_lowercase() || first == '_')
+        && username.len() <= 32
+        && chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-')
+}
+
+/// Home directory of a user inside the Arch FS, e.g. `/root` or `/home/alice`
+pub fn home_dir(username: &str) -> String {
+    if username == "root" {
+        "/root".to_string()
+    } else {
+        format!("/home/{}", username)
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct CommandConfig {
     #[serde(default = "default_check")]
@@ -62,18 +97,41 @@ pub struct CommandConfig {
 }
 
 fn default_check() -> String {
-    "pacman -Q noto-fonts && pacman -Q lxqt-session && pacman -Q lxqt-panel && pacman -Q pcmanfm-qt && pacman -Q openbox && pacman -Q xorg-xwayland && pacman -Q lxqt-wayland-session && pacman -Q labwc && pacman -Q breeze-icons && pacman -Q qterminal && pacman -Q onboard"
+    "pacman -Q noto-fonts && pacman -Q lxqt-session && pacman -Q lxqt-panel && pacman -Q pcmanfm-qt && pacman -Q xorg-xwayland && pacman -Q lxqt-wayland-session && pacman -Q labwc && pacman -Q breeze-icons && pacman -Q qterminal"
         .to_string()
 }
 
 fn default_install() -> String {
-    "stdbuf -oL pacman -Syu --needed --noconfirm --noprogressbar noto-fonts liblxqt lxqt-about lxqt-admin lxqt-archiver lxqt-config lxqt-globalkeys lxqt-menu-data lxqt-notificationd lxqt-openssh-askpass lxqt-panel lxqt-policykit lxqt-powermanagement lxqt-qtplugin lxqt-runner lxqt-session lxqt-sudo lxqt-themes lxqt-wayland-session pcmanfm-qt qps qterminal screengrab xdg-desktop-portal-lxqt openbox xorg-xwayland labwc breeze-icons onboard"
+    "stdbuf -oL pacman -Syu --needed --noconfirm --noprogressbar noto-fonts liblxqt lxqt-about lxqt-admin lxqt-archiver lxqt-config lxqt-globalkeys lxqt-menu-data lxqt-notificationd lxqt-openssh-askpass lxqt-panel lxqt-policykit lxqt-powermanagement lxqt-qtplugin lxqt-runner lxqt-session lxqt-sudo lxqt-themes lxqt-wayland-session pcmanfm-qt qps qterminal screengrab xdg-desktop-portal-lxqt xorg-xwayland labwc breeze-icons"
         .to_string()
 }
 
 fn default_launch() -> String {
-    "XDG_RUNTIME_DIR=/tmp Xwayland -hidpi :1 2>&1 & while [ ! -e /tmp/.X11-unix/X1 ]; do sleep 0.1; done; XDG_SESSION_TYPE=x11 DISPLAY=:1 dbus-run-session startlxqt 2>&1"
-        .to_string()
+    "XDG_SESSION_TYPE=x11 DISPLAY=:1 dbus-run-session startlxqt 2>&1".to_string()
+}
+
+/// Default launch command of earlier versions, starting a rootful Xwayland on `:1`
+const LEGACY_DEFAULT_LAUNCH: &str = "XDG_RUNTIME_DIR=/tmp Xwayland -hidpi :1 2>&1 & while [ ! -e /tmp/.X11-unix/X1 ]; do sleep 0.1; done; XDG_SESSION_TYPE=x11 DISPLAY=:1 dbus-run-session startlxqt 2>&1";
+
+impl CommandConfig {
+    /// `:1` is now the display of the rootless Xwayland the compositor runs itself, so the old default
+    /// launch command would fight over it and falls back to the new default. Custom commands are the
+    /// user's choice and only warned about.
+    fn ignore_legacy_launch(&mut self) {
+        if self.launch == LEGACY_DEFAULT_LAUNCH {
+            log::info!("Replacing the launch command of earlier versions with the default");
+            self.launch = default_launch();
+        } else if self
+            .launch
+            .split_whitespace()
+            .any(|word| word.rsplit('/').next() == Some("Xwayland"))
+        {
+            log::warn!(
+                "The launch command starts its own Xwayland, which may conflict with the one of the compositor: {}",
+                self.launch
+            );
+        }
+    }
 }
 
 impl Default for CommandConfig {
@@ -86,6 +144,125 @@ impl Default for CommandConfig {
     }
 }
 
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+pub struct SessionConfig {
+    /// Keep the container running in an Android foreground service after the app is closed
+    #[serde(default)]
+    pub background: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TaskManagerConfig {
+    /// The task manager page is served on `http://127.0.0.1:<port>`
+    #[serde(default = "default_task_manager_port")]
+    pub port: u16,
+    /// Warn when the available memory gets this close (in MiB) to the low memory killer threshold
+    #[serde(default = "default_low_memory_margin_mb")]
+    pub low_memory_margin_mb: u64,
+}
+
+fn default_task_manager_port() -> u16 {
+    7770
+}
+
+fn default_low_memory_margin_mb() -> u64 {
+    256
+}
+
+impl Default for TaskManagerConfig {
+    fn default() -> Self {
+        Self {
+            port: default_task_manager_port(),
+            low_memory_margin_mb: default_low_memory_margin_mb(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ClipboardConfig {
+    /// Share the clipboard between Android apps and the Linux desktop
+    #[serde(default = "default_clipboard_sync")]
+    pub sync: bool,
+    /// Bigger clips (in KiB) are not copied to the other side
+    #[serde(default = "default_clipboard_max_size_kb")]
+    pub max_size_kb: usize,
+}
+
+fn default_clipboard_sync() -> bool {
+    true
+}
+
+fn default_clipboard_max_size_kb() -> usize {
+    4096
+}
+
+impl Default for ClipboardConfig {
+    fn default() -> Self {
+        Self {
+            sync: default_clipboard_sync(),
+            max_size_kb: default_clipboard_max_size_kb(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DisplayConfig {
+    /// Where external displays (Samsung DeX, USB-C monitors) go, relative to the built-in one
+    #[serde(default)]
+    pub arrangement: OutputArrangement,
+    /// Scale of the built-in display, e.g. `2` or `1.5` so that Wayland apps are crisp and readable on a
+    /// high-DPI tablet. X11 apps are upscaled.
+    #[serde(default = "default_scale")]
+    pub scale: f64,
+    /// Scale of the external displays
+    #[serde(default = "default_scale")]
+    pub external_scale: f64,
+}
+
+fn default_scale() -> f64 {
+    1.0
+}
+
+impl Default for DisplayConfig {
+    fn default() -> Self {
+        Self {
+            arrangement: OutputArrangement::default(),
+            scale: default_scale(),
+            external_scale: default_scale(),
+        }
+    }
+}
+
+/// Side of the previous display that the next one is placed on
+#[derive(Debug, Serialize, Deserialize, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputArrangement {
+    #[default]
+    Right,
+    Left,
+    Above,
+    Below,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ServiceConfig {
+    pub name: String,
+    /// Shell command that starts the server, run as the configured user
+    pub command: String,
+    /// Port the server listens on inside the container
+    pub port: u16,
+    /// Start the service together with the desktop session
+    #[serde(default = "default_autostart")]
+    pub autostart: bool,
+    /// When set, the service is also reachable from other devices on `0.0.0.0:<public_port>`
+    #[serde(default)]
+    pub public_port: Option<u16>,
+}
+
+fn default_autostart() -> bool {
+    true
+}
+
 /// This function does 2 major tasks:
 /// - Read config from `CONFIG_FILE`, and override configs with their `try_*` versions, and return the configs line by line
 /// - Write back to the config file, with `try_*` configs commented out
@@ -94,12 +271,18 @@ impl Default for CommandConfig {
 fn process_config_file(full_config_path: String) -> Vec<String> {
     let mut write_back_lines: Vec<String> = vec![];
     let mut effective_config: Vec<String> = vec![];
+    // Duplicated keys are only detected inside the current table, so that repeated tables like `[[service]]` work
+    let mut table_start = 0;
 
     if let Ok(content) = fs::read_to_string(&full_config_path) {
         for line in content.lines() {
             let trimmed = line.trim();
 
-            if let Some((key, value)) = trimmed.split_once('=') {
+            if trimmed.starts_with('[') {
+                write_back_lines.push(trimmed.to_string());
+                effective_config.push(trimmed.to_string());
+                table_start = effective_config.len();
+            } else if let Some((key, value)) = trimmed.split_once('=') {
                 let key = key.trim();
                 let value = value.trim();
 
@@ -109,12 +292,13 @@ fn process_config_file(full_config_path: String) -> Vec<String> {
 
                     // Prefer the `try_*` configs
                     let actual_key = key.trim_start_matches("try_");
-                    if let Some(line_index) = effective_config
+                    if let Some(line_index) = effective_config[table_start..]
                         .iter()
                         .position(|line| line.starts_with(&format!("{}=", actual_key)))
                     {
                         // Config exists, overriding
-                        effective_config[line_index] = format!("{}={}", actual_key, value);
+                        effective_config[table_start + line_index] =
+                            format!("{}={}", actual_key, value);
                     } else {
                         // Config does not exist, appending
                         effective_config.push(format!("{}={}", actual_key, value));
@@ -124,7 +308,7 @@ fn process_config_file(full_config_path: String) -> Vec<String> {
                     // Keep the config as is
                     write_back_lines.push(trimmed.to_string());
 
-                    if effective_config
+                    if effective_config[table_start..]
                         .iter()
                         .any(|line| line.starts_with(&format!("{}=", key)))
                     {
@@ -161,7 +345,8 @@ fn process_config_file(full_config_path: String) -> Vec<String> {
 pub fn parse_config(full_config_path: String) -> LocalConfig {
     let lines = process_config_file(full_config_path);
     let content = lines.join("\n");
-    if let Ok(config) = toml::from_str::<LocalConfig>(&content) {
+    if let Ok(mut config) = toml::from_str::<LocalConfig>(&content) {
+        config.command.ignore_legacy_launch();
         return config;
     }
     // Config malformed, use the default config and the user can modify it again
@@ -230,6 +415,144 @@ mod tests {
         );
     }
 
+    #[test]
+    fn should_default_to_foreground_session() {
+        with_config_file(
+            r#"
+                [user]
+                username = "alice"
+            "#,
+            |full_config_path| {
+                let config = parse_config(full_config_path);
+                assert!(!config.session.background);
+            },
+        );
+        with_config_file(
+            r#"
+                [session]
+                background = true
+            "#,
+            |full_config_path| {
+                let config = parse_config(full_config_path);
+                assert!(config.session.background);
+            },
+        );
+    }
+
+    #[test]
+    fn should_handle_repeated_service_tables() {
+        with_config_file(
+            r#"
+                [user]
+                username = "alice"
+
+                [[service]]
+                name = "web"
+                command = "python -m http.server 8000"
+                port = 8000
+                public_port = 18000
+
+                [[service]]
+                name = "db"
+                command = "postgres -D /var/lib/postgres/data"
+                port = 5432
+                autostart = false
+            "#,
+            |full_config_path| {
+                let config = parse_config(full_config_path);
+                assert_eq!(config.user.username, "alice");
+                assert_eq!(config.services.len(), 2);
+                assert_eq!(config.services[0].name, "web");
+                assert_eq!(config.services[0].port, 8000);
+                assert_eq!(config.services[0].public_port, Some(18000));
+                assert!(config.services[0].autostart);
+                assert_eq!(config.services[1].name, "db");
+                assert_eq!(config.services[1].public_port, None);
+                assert!(!config.services[1].autostart);
+            },
+        );
+    }
+
+    #[test]
+    fn should_parse_display_config() {
+        with_config_file(
+            r#"
+                [display]
+                arrangement = "left"
+                scale = 1.5
+            "#,
+            |full_config_path| {
+                let config = parse_config(full_config_path);
+                assert_eq!(config.display.arrangement, OutputArrangement::Left);
+                assert_eq!(config.display.scale, 1.5);
+                assert_eq!(config.display.external_scale, 1.0);
+            },
+        );
+    }
+
+    #[test]
+    fn should_resolve_home_dir() {
+        assert_eq!(home_dir("root"), "/root");
+        assert_eq!(home_dir("alice"), "/home/alice");
+    }
+
+    #[test]
+    fn should_ignore_legacy_xwayland_launch() {
+        with_config_file(
+            &format!(
+                r#"
+                    [command]
+                    launch = "{}"
+                "#,
+                LEGACY_DEFAULT_LAUNCH
+            ),
+            |path| assert_eq!(parse_config(path).command.launch, default_launch()),
+        );
+        with_config_file(
+            r#"
+                [command]
+                launch = "/usr/bin/Xwayland :2 & DISPLAY=:2 dbus-run-session startxfce4"
+            "#,
+            |path| {
+                assert_eq!(
+                    parse_config(path).command.launch,
+                    "/usr/bin/Xwayland :2 & DISPLAY=:2 dbus-run-session startxfce4"
+                )
+            },
+        );
+        with_config_file(
+            r#"
+                [command]
+                launch = "dbus-run-session startplasma-x11"
+            "#,
+            |path| {
+                assert_eq!(
+                    parse_config(path).command.launch,
+                    "dbus-run-session startplasma-x11"
+                )
+            },
+        );
+    }
+
+    #[test]
+    fn should_validate_usernames() {
+        for valid in ["root", "alice", "_build", "bob-2", &"a".repeat(32)] {
+            assert!(is_valid_username(valid), "{} should be valid", valid);
+        }
+        for invalid in [
+            "",
+            "Alice",
+            "2bob",
+            "-x",
+            "a b",
+            "a;reboot",
+            "$(id)",
+            &"a".repeat(33),
+        ] {
+            assert!(!is_valid_username(invalid), "{} should be invalid", invalid);
+        }
+    }
+
     #[test]
     fn should_comment_out_try_configs() {
         with_config_file(
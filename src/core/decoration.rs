@@ -0,0 +1,283 @@
+use ab_glyph::{point, Font, FontVec, PxScale, ScaleFont};
+use std::{fs, path::Path};
+
+/// Height of the title bar drawn above windows that asked for server-side decorations, in logical
+/// pixels. It and the buttons are big enough to be hit with a finger.
+pub const TITLEBAR_HEIGHT: i32 = 40;
+
+/// Width of each button at the right of the title bar
+pub const BUTTON_WIDTH: i32 = 48;
+
+/// Width of the invisible border around the window that resizes it
+pub const RESIZE_BORDER: i32 = 12;
+
+/// Fonts of the container tried for the titles, relative to `/usr/share/fonts`
+const FONTS: &[&str] = &[
+    "noto/NotoSans-Regular.ttf",
+    "TTF/DejaVuSans.ttf",
+    "dejavu/DejaVuSans.ttf",
+    "liberation/LiberationSans-Regular.ttf",
+];
+
+const BACKGROUND: u32 = 0xff2b2b2b;
+const BACKGROUND_INACTIVE: u32 = 0xff3c3c3c;
+const FOREGROUND: u32 = 0xffffffff;
+const FOREGROUND_INACTIVE: u32 = 0xff9a9a9a;
+const CLOSE_BACKGROUND: u32 = 0xffc42b1c;
+
+/// The sides of a window a border resizes
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Edges {
+    pub top: bool,
+    pub bottom: bool,
+    pub left: bool,
+    pub right: bool,
+}
+
+/// What is under a point of the decorations of a window
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecorationPart {
+    /// Dragging it moves the window
+    Titlebar,
+    Minimize,
+    Maximize,
+    Close,
+    Border(Edges),
+}
+
+/// The part of the decorations at `location`, relative to the top left corner of a window of `size`
+/// without its decorations. `None` on the window itself and outside of the decorations. Only
+/// `resizable` windows have a border.
+pub fn decoration_part_at(
+    size: (i32, i32),
+    location: (f64, f64),
+    resizable: bool,
+) -> Option<DecorationPart> {
+    let (width, height) = (size.0 as f64, size.1 as f64);
+    let (x, y) = location;
+    let top = -TITLEBAR_HEIGHT as f64;
+    let border = if resizable { RESIZE_BORDER as f64 } else { 0.0 };
+    if x < -border || x >= width + border || y < top - border || y >= height + border {
+        return None;
+    }
+
+    if (0.0..width).contains(&x) && (top..0.0).contains(&y) {
+        // Buttons are laid out from the right: close, maximize, then minimize
+        return Some(match ((width - x) / BUTTON_WIDTH as f64) as i32 {
+            0 => DecorationPart::Close,
+            1 => DecorationPart::Maximize,
+            2 => DecorationPart::Minimize,
+            _ => DecorationPart::Titlebar,
+        });
+    }
+    let edges = Edges {
+        top: y < top,
+        bottom: y >= height,
+        left: x < 0.0,
+        right: x >= width,
+    };
+    (edges != Edges::default()).then_some(DecorationPart::Border(edges))
+}
+
+/// The first font of the container usable for the titles, `None` if there is none
+pub fn load_font(fonts_dir: &Path) -> Option<FontVec> {
+    FONTS.iter().find_map(|name| {
+        let data = fs::read(fonts_dir.join(name)).ok()?;
+        FontVec::try_from_vec(data)
+            .map_err(|e| log::info!("Invalid font {}: {}", name, e))
+            .ok()
+    })
+}
+
+/// Premultiplied ARGB pixels of a title bar `width` by `height` physical pixels, drawn at `scale`.
+/// The title is left out without a font.
+pub fn draw_titlebar(
+    width: u32,
+    height: u32,
+    scale: f64,
+    title: &str,
+    focused: bool,
+    font: Option<&FontVec>,
+) -> Vec<u8> {
+    let (background, foreground) = if focused {
+        (BACKGROUND, FOREGROUND)
+    } else {
+        (BACKGROUND_INACTIVE, FOREGROUND_INACTIVE)
+    };
+    let mut canvas = Canvas {
+        pixels: vec![background; width as usize * height as usize],
+        width: width as i32,
+        height: height as i32,
+    };
+
+    let button_width = (BUTTON_WIDTH as f64 * scale).round() as i32;
+    let icon = (height as f64 * 0.3).round() as i32;
+    let thickness = scale.round().max(1.0) as i32;
+    let buttons_left = canvas.width - 3 * button_width;
+    for index in 0..3 {
+        let left = canvas.width - (index + 1) * button_width;
+        if index == 0 {
+            canvas.fill(left, 0, button_width, canvas.height, CLOSE_BACKGROUND);
+        }
+        let x = left + (button_width - icon) / 2;
+        let y = (canvas.height - icon) / 2;
+        match index {
+            0 => canvas.cross(x, y, icon, thickness, foreground),
+            1 => {
+                canvas.fill(x, y, icon, thickness, foreground);
+                canvas.fill(x, y + icon - thickness, icon, thickness, foreground);
+                canvas.fill(x, y, thickness, icon, foreground);
+                canvas.fill(x + icon - thickness, y, thickness, icon, foreground);
+            }
+            _ => canvas.fill(x, y + icon - thickness, icon, thickness, foreground),
+        }
+    }
+
+    if let Some(font) = font {
+        let padding = (RESIZE_BORDER as f64 * scale) as f32;
+        canvas.text(
+            font,
+            title,
+            height as f32 * 0.4,
+            padding,
+            buttons_left as f32 - padding,
+            foreground,
+        );
+    }
+
+    canvas
+        .pixels
+        .iter()
+        .flat_map(|pixel| pixel.to_le_bytes())
+        .collect()
+}
+
+/// Opaque ARGB pixels, drawn on with clipping
+struct Canvas {
+    pixels: Vec<u32>,
+    width: i32,
+    height: i32,
+}
+
+impl Canvas {
+    /// Blend `color` over the pixel at `x` and `y` with the `coverage` of an antialiased shape
+    fn blend(&mut self, x: i32, y: i32, color: u32, coverage: f32) {
+        if !(0..self.width).contains(&x) || !(0..self.height).contains(&y) {
+            return;
+        }
+        let pixel = &mut self.pixels[(y * self.width + x) as usize];
+        let coverage = coverage.clamp(0.0, 1.0);
+        let channel = |shift: u32| {
+            let over = ((color >> shift) & 0xff) as f32;
+            let under = ((*pixel >> shift) & 0xff) as f32;
+            ((over * coverage + under * (1.0 - coverage)).round() as u32) << shift
+        };
+        *pixel = 0xff000000 | channel(16) | channel(8) | channel(0);
+    }
+
+    fn fill(&mut self, x: i32, y: i32, width: i32, height: i32, color: u32) {
+        for y in y..y + height {
+            for x in x..x + width {
+                self.blend(x, y, color, 1.0);
+            }
+        }
+    }
+
+    /// The two diagonals of a square of `size`
+    fn cross(&mut self, x: i32, y: i32, size: i32, thickness: i32, color: u32) {
+        for dy in 0..size {
+            for dx in 0..size {
+                if (dx - dy).abs() < thickness || (dx + dy - size + 1).abs() < thickness {
+                    self.blend(x + dx, y + dy, color, 1.0);
+                }
+            }
+        }
+    }
+
+    /// A single line of text of `size` pixels, vertically centered, cut where it would go past `right`
+    fn text(&mut self, font: &FontVec, text: &str, size: f32, left: f32, right: f32, color: u32) {
+        let scaled = font.as_scaled(PxScale::from(size));
+        let baseline = (self.height as f32 + scaled.ascent() + scaled.descent()) / 2.0;
+        let mut caret = left;
+        let mut previous = None;
+        for c in text.chars() {
+            let id = scaled.glyph_id(c);
+            if let Some(previous) = previous {
+                caret += scaled.kern(previous, id);
+            }
+            previous = Some(id);
+            let advance = scaled.h_advance(id);
+            if caret + advance > right {
+                break;
+            }
+            let glyph = id.with_scale_and_position(size, point(caret, baseline));
+            caret += advance;
+            let Some(outline) = font.outline_glyph(glyph) else {
+                continue;
+            };
+            let bounds = outline.px_bounds();
+            outline.draw(|x, y, coverage| {
+                self.blend(
+                    bounds.min.x as i32 + x as i32,
+                    bounds.min.y as i32 + y as i32,
+                    color,
+                    coverage,
+                );
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pixel(pixels: &[u8], width: u32, x: u32, y: u32) -> u32 {
+        let offset = ((y * width + x) * 4) as usize;
+        u32::from_le_bytes(pixels[offset..offset + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn should_find_decoration_parts() {
+        let size = (400, 300);
+        let part = |x, y| decoration_part_at(size, (x, y), true);
+        assert_eq!(part(200.0, 150.0), None);
+        assert_eq!(part(10.0, -20.0), Some(DecorationPart::Titlebar));
+        assert_eq!(part(390.0, -20.0), Some(DecorationPart::Close));
+        assert_eq!(part(340.0, -1.0), Some(DecorationPart::Maximize));
+        assert_eq!(part(300.0, -40.0), Some(DecorationPart::Minimize));
+        assert_eq!(part(250.0, -20.0), Some(DecorationPart::Titlebar));
+        assert_eq!(
+            part(-5.0, -45.0),
+            Some(DecorationPart::Border(Edges {
+                top: true,
+                left: true,
+                ..Edges::default()
+            }))
+        );
+        assert_eq!(
+            part(405.0, 150.0),
+            Some(DecorationPart::Border(Edges {
+                right: true,
+                ..Edges::default()
+            }))
+        );
+        assert_eq!(part(200.0, 320.0), None);
+        // Maximized windows cannot be resized
+        assert_eq!(decoration_part_at(size, (405.0, 150.0), false), None);
+    }
+
+    #[test]
+    fn should_draw_titlebar_buttons() {
+        let (width, height) = (300, 40);
+        let pixels = draw_titlebar(width, height, 1.0, "Title", true, None);
+        assert_eq!(pixels.len(), (width * height * 4) as usize);
+        assert_eq!(pixel(&pixels, width, 10, 5), BACKGROUND);
+        // The close button, with the cross in its center
+        assert_eq!(pixel(&pixels, width, 290, 5), CLOSE_BACKGROUND);
+        assert_eq!(pixel(&pixels, width, 276, 20), FOREGROUND);
+
+        let pixels = draw_titlebar(width, height, 1.0, "Title", false, None);
+        assert_eq!(pixel(&pixels, width, 10, 5), BACKGROUND_INACTIVE);
+    }
+}
@@ -0,0 +1,70 @@
+use std::io;
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::thread;
+
+/// Listen on `listen` and pipe every accepted TCP connection to `target`.
+/// Connections are served on background threads, the bound address is returned once listening.
+pub fn forward(listen: SocketAddr, target: SocketAddr) -> io::Result<SocketAddr> {
+    let listener = TcpListener::bind(listen)?;
+    let local_addr = listener.local_addr()?;
+
+    thread::spawn(move || {
+        for client in listener.incoming().filter_map(Result::ok) {
+            thread::spawn(move || {
+                if let Err(e) = pipe(client, target) {
+                    log::info!("Failed to forward {} to {}: {}", local_addr, target, e);
+                }
+            });
+        }
+    });
+
+    Ok(local_addr)
+}
+
+/// Copy bytes in both directions until both sides have closed their write half
+fn pipe(client: TcpStream, target: SocketAddr) -> io::Result<()> {
+    let upstream = TcpStream::connect(target)?;
+    let mut client_reader = client.try_clone()?;
+    let mut client_writer = client;
+    let mut upstream_reader = upstream.try_clone()?;
+    let mut upstream_writer = upstream;
+
+    let upload = thread::spawn(move || {
+        let _ = io::copy(&mut client_reader, &mut upstream_writer);
+        let _ = upstream_writer.shutdown(Shutdown::Write);
+    });
+    let _ = io::copy(&mut upstream_reader, &mut client_writer);
+    let _ = client_writer.shutdown(Shutdown::Write);
+    let _ = upload.join();
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+
+    #[test]
+    fn should_forward_both_directions() {
+        // An upstream server that answers with the uppercased request
+        let upstream = TcpListener::bind("127.0.0.1:0").unwrap();
+        let upstream_addr = upstream.local_addr().unwrap();
+        thread::spawn(move || {
+            let (mut stream, _) = upstream.accept().unwrap();
+            let mut request = String::new();
+            stream.read_to_string(&mut request).unwrap();
+            stream.write_all(request.to_uppercase().as_bytes()).unwrap();
+        });
+
+        let forwarder = forward("127.0.0.1:0".parse().unwrap(), upstream_addr).unwrap();
+
+        let mut client = TcpStream::connect(forwarder).unwrap();
+        client.write_all(b"hello").unwrap();
+        client.shutdown(Shutdown::Write).unwrap();
+        let mut response = String::new();
+        client.read_to_string(&mut response).unwrap();
+
+        assert_eq!(response, "HELLO");
+    }
+}
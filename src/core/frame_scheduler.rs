@@ -0,0 +1,135 @@
+use std::time::{Duration, Instant};
+
+/// Refresh rate used until the display reports its own, in mHz like Wayland output modes
+pub const DEFAULT_REFRESH_MHZ: i32 = 60_000;
+
+/// Decides when to draw: only once something may have changed on screen, and no more often than
+/// the display refreshes. Counts the frames too, to tell how busy the compositor keeps the GPU.
+#[derive(Debug)]
+pub struct FrameScheduler {
+    interval: Duration,
+    last_frame: Option<Instant>,
+    dirty: bool,
+    /// Draw at every refresh even without changes, see `set_continuous`
+    continuous: bool,
+    /// Frames that had damage, so were drawn and presented
+    pub rendered: u64,
+    /// Frames that turned out to have nothing new to draw
+    pub skipped: u64,
+}
+
+impl Default for FrameScheduler {
+    fn default() -> Self {
+        Self::new(DEFAULT_REFRESH_MHZ)
+    }
+}
+
+impl FrameScheduler {
+    pub fn new(refresh_mhz: i32) -> Self {
+        let mut scheduler = Self {
+            interval: Duration::ZERO,
+            last_frame: None,
+            dirty: true,
+            continuous: false,
+            rendered: 0,
+            skipped: 0,
+        };
+        scheduler.set_refresh(refresh_mhz);
+        scheduler
+    }
+
+    pub fn set_refresh(&mut self, refresh_mhz: i32) {
+        let refresh_mhz = if refresh_mhz > 0 {
+            refresh_mhz
+        } else {
+            DEFAULT_REFRESH_MHZ
+        };
+        self.interval = Duration::from_nanos(1_000_000_000_000 / refresh_mhz as u64);
+    }
+
+    /// Something on screen may have changed: a client committed, a window moved, the pointer moved...
+    pub fn schedule(&mut self) {
+        self.dirty = true;
+    }
+
+    /// Keep drawing at the display rate while a video or a game is shown, so its frame callbacks
+    /// come at a steady pace instead of whenever something else happens to change
+    pub fn set_continuous(&mut self, continuous: bool) {
+        self.continuous = continuous;
+    }
+
+    pub fn is_idle(&self) -> bool {
+        !self.dirty && !self.continuous
+    }
+
+    /// When to draw the next frame, `None` while there is nothing to draw
+    pub fn next_frame(&self, now: Instant) -> Option<Instant> {
+        if self.is_idle() {
+            return None;
+        }
+        let next = self.last_frame.map_or(now, |last| last + self.interval);
+        Some(next.max(now))
+    }
+
+    /// A frame was drawn, `damaged` tells whether anything actually changed
+    pub fn frame_done(&mut self, now: Instant, damaged: bool) {
+        self.dirty = false;
+        self.last_frame = Some(now);
+        if damaged {
+            self.rendered += 1;
+        } else {
+            self.skipped += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_stop_drawing_when_idle() {
+        let start = Instant::now();
+        let mut frames = FrameScheduler::new(60_000);
+
+        // The first frame is drawn right away
+        assert_eq!(frames.next_frame(start), Some(start));
+        frames.frame_done(start, true);
+        assert!(frames.is_idle());
+        assert_eq!(frames.next_frame(start + Duration::from_secs(1)), None);
+
+        // Changes are drawn at most once per refresh
+        frames.schedule();
+        frames.schedule();
+        let next = frames.next_frame(start).unwrap();
+        assert_eq!(next - start, Duration::from_nanos(16_666_666));
+        frames.frame_done(next, false);
+        assert_eq!(frames.next_frame(next), None);
+
+        let later = next + Duration::from_secs(1);
+        frames.schedule();
+        assert_eq!(frames.next_frame(later), Some(later));
+
+        assert_eq!((frames.rendered, frames.skipped), (1, 1));
+    }
+
+    #[test]
+    fn should_draw_at_every_refresh_while_continuous() {
+        let start = Instant::now();
+        let mut frames = FrameScheduler::new(50_000);
+        frames.frame_done(start, true);
+
+        frames.set_continuous(true);
+        assert!(!frames.is_idle());
+        let next = frames.next_frame(start).unwrap();
+        assert_eq!(next - start, Duration::from_millis(20));
+        frames.frame_done(next, false);
+        assert_eq!(
+            frames.next_frame(next),
+            Some(next + Duration::from_millis(20))
+        );
+
+        frames.set_continuous(false);
+        assert_eq!(frames.next_frame(next), None);
+    }
+}
@@ -0,0 +1,73 @@
+use std::collections::BTreeSet;
+
+/// File name for a volume labelled `label`, unless the label cannot name a file of its own
+fn link_name(label: &str) -> Option<String> {
+    let name = label.replace(['/', '\0'], "_").trim().to_string();
+    (!name.is_empty() && name != "." && name != "..").then_some(name)
+}
+
+/// Names of the `/media` links of the given `(uuid, label)` volumes, in the same order. A volume is
+/// named after its label, or its uuid when the label is unusable or already taken by another volume.
+pub fn media_link_names(volumes: &[(&str, &str)]) -> Vec<String> {
+    let mut used = BTreeSet::new();
+    volumes
+        .iter()
+        .map(|(uuid, label)| {
+            let name = [link_name(label), link_name(uuid)]
+                .into_iter()
+                .flatten()
+                .find(|name| !used.contains(name))
+                .unwrap_or_else(|| {
+                    let base = link_name(uuid).unwrap_or_else(|| "volume".to_string());
+                    (2..)
+                        .map(|n| format!("{}-{}", base, n))
+                        .find(|name| !used.contains(name))
+                        .expect("Ran out of link names")
+                });
+            used.insert(name.clone());
+            name
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_name_links_after_labels() {
+        assert_eq!(
+            media_link_names(&[
+                ("1234-ABCD", "SanDisk SD card"),
+                ("5678-EF01", "USB/drive ")
+            ]),
+            ["SanDisk SD card", "USB_drive"]
+        );
+    }
+
+    #[test]
+    fn should_fall_back_to_uuid_for_unusable_labels() {
+        assert_eq!(
+            media_link_names(&[
+                ("AAAA-0001", "."),
+                ("AAAA-0002", ".."),
+                ("AAAA-0003", "  "),
+                ("AAAA-0004", ""),
+            ]),
+            ["AAAA-0001", "AAAA-0002", "AAAA-0003", "AAAA-0004"]
+        );
+    }
+
+    #[test]
+    fn should_deduplicate_colliding_names() {
+        assert_eq!(
+            media_link_names(&[
+                ("AAAA-0001", "USB drive"),
+                ("AAAA-0002", "USB drive"),
+                ("AAAA-0003", "AAAA-0002"),
+                ("AAAA-0002", "USB drive"),
+            ]),
+            ["USB drive", "AAAA-0002", "AAAA-0003", "AAAA-0002-2"]
+        );
+    }
+}
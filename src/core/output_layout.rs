@@ -0,0 +1,125 @@
+use crate::core::{config::OutputArrangement, frame_scheduler::DEFAULT_REFRESH_MHZ};
+
+/// Top left corner of each output, given their sizes in logical pixels. The first output, the built-in
+/// display, stays at the origin so that touch input keeps its coordinates, and each next one is placed
+/// next to the previous one, aligned on its top or left edge.
+pub fn arrange(arrangement: OutputArrangement, sizes: &[(i32, i32)]) -> Vec<(i32, i32)> {
+    let mut locations = Vec::with_capacity(sizes.len());
+    let mut previous: Option<((i32, i32), (i32, i32))> = None;
+    for &(width, height) in sizes {
+        let location = match previous {
+            None => (0, 0),
+            Some(((x, y), (previous_width, previous_height))) => match arrangement {
+                OutputArrangement::Right => (x + previous_width, y),
+                OutputArrangement::Left => (x - width, y),
+                OutputArrangement::Below => (x, y + previous_height),
+                OutputArrangement::Above => (x, y - height),
+            },
+        };
+        locations.push(location);
+        previous = Some((location, (width, height)));
+    }
+    locations
+}
+
+/// Length in millimetres of `pixels` at `dpi` dots per inch, 0 when the density is unknown
+pub fn pixels_to_mm(pixels: i32, dpi: f32) -> i32 {
+    if dpi > 0.0 {
+        (pixels as f32 / dpi * 25.4).round() as i32
+    } else {
+        0
+    }
+}
+
+/// A configured output scale made valid: between 0.5 and 4, in steps of 1/120 as fractional scaling
+/// tells clients
+pub fn output_scale(configured: f64) -> f64 {
+    if !configured.is_finite() {
+        return 1.0;
+    }
+    (configured.clamp(0.5, 4.0) * 120.0).round() / 120.0
+}
+
+/// A refresh rate in Hz as Wayland counts it, in mHz
+pub fn refresh_mhz(hz: f32) -> i32 {
+    if hz > 0.0 {
+        (hz * 1000.0).round() as i32
+    } else {
+        DEFAULT_REFRESH_MHZ
+    }
+}
+
+/// The refresh rates in mHz, highest first, of the display `modes` (resolution and rate in Hz) that have
+/// the current `resolution` of the display. Android describes modes in the natural orientation of the
+/// display, so a rotated resolution matches as well.
+pub fn refresh_rates(modes: &[((i32, i32), f32)], resolution: (i32, i32)) -> Vec<i32> {
+    let (width, height) = resolution;
+    let mut rates = modes
+        .iter()
+        .filter(|(size, _)| *size == (width, height) || *size == (height, width))
+        .map(|&(_, hz)| refresh_mhz(hz))
+        .collect::<Vec<_>>();
+    rates.sort_unstable_by(|a, b| b.cmp(a));
+    rates.dedup();
+    rates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_arrange_outputs_next_to_each_other() {
+        let sizes = [(1920, 1200), (1280, 720), (800, 600)];
+        assert_eq!(
+            arrange(OutputArrangement::Right, &sizes),
+            vec![(0, 0), (1920, 0), (3200, 0)]
+        );
+        assert_eq!(
+            arrange(OutputArrangement::Left, &sizes),
+            vec![(0, 0), (-1280, 0), (-2080, 0)]
+        );
+        assert_eq!(
+            arrange(OutputArrangement::Above, &sizes[..2]),
+            vec![(0, 0), (0, -720)]
+        );
+        assert_eq!(
+            arrange(OutputArrangement::Below, &sizes[..2]),
+            vec![(0, 0), (0, 1200)]
+        );
+
+        // A 27" 1440p monitor is about 597x336 mm
+        assert_eq!(pixels_to_mm(2560, 108.8), 598);
+        assert_eq!(pixels_to_mm(2560, 0.0), 0);
+    }
+
+    #[test]
+    fn should_list_refresh_rates_of_the_current_resolution() {
+        let modes = [
+            ((1600, 2560), 60.0),
+            ((1600, 2560), 120.0),
+            ((1600, 2560), 90.000_01),
+            ((1200, 1920), 60.0),
+            ((1600, 2560), 120.0),
+        ];
+        // In landscape, the resolution is rotated
+        assert_eq!(
+            refresh_rates(&modes, (2560, 1600)),
+            vec![120_000, 90_000, 60_000]
+        );
+        assert_eq!(refresh_rates(&modes, (1200, 1920)), vec![60_000]);
+        assert_eq!(refresh_rates(&modes, (720, 1280)), Vec::<i32>::new());
+        assert_eq!(refresh_mhz(59.94), 59_940);
+        assert_eq!(refresh_mhz(0.0), DEFAULT_REFRESH_MHZ);
+    }
+
+    #[test]
+    fn should_keep_output_scales_usable() {
+        assert_eq!(output_scale(2.0), 2.0);
+        assert_eq!(output_scale(1.25), 1.25);
+        assert_eq!(output_scale(1.333), 160.0 / 120.0);
+        assert_eq!(output_scale(0.0), 0.5);
+        assert_eq!(output_scale(10.0), 4.0);
+        assert_eq!(output_scale(f64::NAN), 1.0);
+    }
+}
@@ -0,0 +1,178 @@
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::path::Path;
+
+/// `USER_HZ`, the unit of the CPU times in `/proc/<pid>/stat`. It is 100 on every Android ABI.
+const CLOCK_TICKS_PER_SECOND: u64 = 100;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    pub ppid: u32,
+    /// Full command line, or the process name for processes without one (e.g. zombies)
+    pub command: String,
+    /// Resident set size in KiB
+    pub rss_kb: u64,
+    /// User + system CPU time in milliseconds
+    pub cpu_time_ms: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct MemoryInfo {
+    pub total_kb: u64,
+    pub available_kb: u64,
+}
+
+/// Fields of `/proc/<pid>/stat` we care about: `(pid, name, ppid, utime + stime in ticks)`.
+/// The name is enclosed in parentheses and may contain spaces and parentheses itself,
+/// so the remaining fields are split after the last `)`.
+fn parse_stat(content: &str) -> Option<(u32, String, u32, u64)> {
+    let (pid, rest) = content.split_once(" (")?;
+    let (name, fields) = rest.rsplit_once(") ")?;
+    let fields: Vec<&str> = fields.split_whitespace().collect();
+
+    // `fields[0]` is field 3 (state) of proc(5)
+    let ppid = fields.get(1)?.parse().ok()?;
+    let utime: u64 = fields.get(11)?.parse().ok()?;
+    let stime: u64 = fields.get(12)?.parse().ok()?;
+
+    Some((
+        pid.trim().parse().ok()?,
+        name.to_string(),
+        ppid,
+        utime + stime,
+    ))
+}
+
+/// Value of a `Key:   1234 kB` line, as found in `/proc/<pid>/status` and `/proc/meminfo`
+fn parse_kb(content: &str, key: &str) -> Option<u64> {
+    content
+        .lines()
+        .find_map(|line| line.strip_prefix(key)?.strip_prefix(':'))
+        .and_then(|value| value.split_whitespace().next())
+        .and_then(|value| value.parse().ok())
+}
+
+fn read_process(proc_root: &Path, pid: u32) -> Option<ProcessInfo> {
+    let dir = proc_root.join(pid.to_string());
+    let (pid, name, ppid, ticks) = parse_stat(&fs::read_to_string(dir.join("stat")).ok()?)?;
+
+    let status = fs::read_to_string(dir.join("status")).unwrap_or_default();
+    let cmdline = fs::read(dir.join("cmdline")).unwrap_or_default();
+    let command = String::from_utf8_lossy(&cmdline)
+        .split('\0')
+        .filter(|arg| !arg.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    Some(ProcessInfo {
+        pid,
+        ppid,
+        command: if command.is_empty() { name } else { command },
+        // Kernel threads and zombies have no VmRSS
+        rss_kb: parse_kb(&status, "VmRSS").unwrap_or(0),
+        cpu_time_ms: ticks * 1000 / CLOCK_TICKS_PER_SECOND,
+    })
+}
+
+/// List `roots` and all of their descendants, parents before children.
+/// `proc_root` is `/proc` outside of tests.
+pub fn process_tree(proc_root: &Path, roots: &[u32]) -> Vec<ProcessInfo> {
+    let processes: Vec<ProcessInfo> = fs::read_dir(proc_root)
+        .into_iter()
+        .flatten()
+        .filter_map(Result::ok)
+        .filter_map(|entry| entry.file_name().to_str()?.parse().ok())
+        .filter_map(|pid| read_process(proc_root, pid))
+        .collect();
+
+    let mut children: HashMap<u32, Vec<&ProcessInfo>> = HashMap::new();
+    for process in &processes {
+        children.entry(process.ppid).or_default().push(process);
+    }
+
+    let mut tree = Vec::new();
+    let mut queue: VecDeque<&ProcessInfo> = processes
+        .iter()
+        .filter(|process| roots.contains(&process.pid))
+        .collect();
+    while let Some(process) = queue.pop_front() {
+        if let Some(children) = children.get(&process.pid) {
+            queue.extend(children);
+        }
+        tree.push(process.clone());
+    }
+    tree
+}
+
+/// System wide memory, from `/proc/meminfo`
+pub fn memory_info(proc_root: &Path) -> Option<MemoryInfo> {
+    let content = fs::read_to_string(proc_root.join("meminfo")).ok()?;
+    Some(MemoryInfo {
+        total_kb: parse_kb(&content, "MemTotal")?,
+        available_kb: parse_kb(&content, "MemAvailable")?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn write_process(proc_root: &Path, pid: u32, ppid: u32, name: &str, cmdline: &str) {
+        let dir = proc_root.join(pid.to_string());
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("stat"),
+            format!(
+                "{} ({}) S {} {} {} 0 -1 4194560 100 0 0 0 250 50 0 0 20 0 1 0 100 1000 10",
+                pid, name, ppid, pid, pid
+            ),
+        )
+        .unwrap();
+        fs::write(
+            dir.join("status"),
+            format!("Name:\t{}\nVmRSS:\t    2048 kB\n", name),
+        )
+        .unwrap();
+        fs::write(dir.join("cmdline"), cmdline.replace(' ', "\0")).unwrap();
+    }
+
+    #[test]
+    fn should_parse_stat_with_odd_names() {
+        let (pid, name, ppid, ticks) =
+            parse_stat("42 (Web Content (1)) S 7 42 42 0 -1 0 0 0 0 0 30 12 0 0").unwrap();
+        assert_eq!(pid, 42);
+        assert_eq!(name, "Web Content (1)");
+        assert_eq!(ppid, 7);
+        assert_eq!(ticks, 42);
+    }
+
+    #[test]
+    fn should_only_list_descendants_of_roots() {
+        let dir = tempdir().unwrap();
+        let proc_root = dir.path();
+        write_process(proc_root, 10, 1, "libproot.so", "libproot.so -r /arch sh");
+        write_process(proc_root, 11, 10, "sh", "sh -c startlxqt");
+        write_process(proc_root, 12, 11, "lxqt-session", "");
+        write_process(proc_root, 20, 1, "unrelated", "unrelated");
+        fs::write(
+            proc_root.join("meminfo"),
+            "MemTotal: 8000 kB\nMemAvailable: 3000 kB\n",
+        )
+        .unwrap();
+
+        let tree = process_tree(proc_root, &[10]);
+        let pids: Vec<u32> = tree.iter().map(|process| process.pid).collect();
+        assert_eq!(pids, vec![10, 11, 12]);
+        assert_eq!(tree[1].command, "sh -c startlxqt");
+        assert_eq!(tree[2].command, "lxqt-session");
+        assert_eq!(tree[0].rss_kb, 2048);
+        assert_eq!(tree[0].cpu_time_ms, 3000);
+
+        let memory = memory_info(proc_root).unwrap();
+        assert_eq!(memory.total_kb, 8000);
+        assert_eq!(memory.available_kb, 3000);
+    }
+}
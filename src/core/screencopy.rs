@@ -0,0 +1,50 @@
+/// Copy `width` by `height` pixels read back from the renderer as RGBA bytes into a shared memory
+/// buffer of a screenshot, as ARGB8888 or XRGB8888 with rows `stride` bytes apart.
+/// Returns `false` without copying anything if `dst` is too small.
+pub fn copy_rgba_to_argb(
+    src: &[u8],
+    width: usize,
+    height: usize,
+    dst: &mut [u8],
+    stride: usize,
+) -> bool {
+    let row = width * 4;
+    if src.len() < row * height || stride < row || dst.len() < stride * height {
+        return false;
+    }
+    for (src, dst) in src.chunks_exact(row).zip(dst.chunks_mut(stride)) {
+        for (src, dst) in src.chunks_exact(4).zip(dst[..row].chunks_exact_mut(4)) {
+            // Little-endian ARGB is stored as B, G, R, A
+            dst.copy_from_slice(&[src[2], src[1], src[0], src[3]]);
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_copy_rows_into_a_padded_buffer() {
+        let src = [
+            0x11, 0x22, 0x33, 0xff, 0x44, 0x55, 0x66, 0xff, //
+            0x77, 0x88, 0x99, 0xff, 0xaa, 0xbb, 0xcc, 0xff,
+        ];
+        let mut dst = [0; 20];
+        assert!(copy_rgba_to_argb(&src, 2, 2, &mut dst, 10));
+        assert_eq!(
+            u32::from_le_bytes(dst[0..4].try_into().unwrap()),
+            0xff112233
+        );
+        assert_eq!(
+            u32::from_le_bytes(dst[14..18].try_into().unwrap()),
+            0xffaabbcc
+        );
+        // The padding at the end of the rows is left alone
+        assert_eq!(&dst[8..10], &[0, 0]);
+
+        assert!(!copy_rgba_to_argb(&src, 2, 2, &mut dst, 6));
+        assert!(!copy_rgba_to_argb(&src, 2, 2, &mut dst[..19], 10));
+    }
+}
@@ -0,0 +1,129 @@
+//! Status of the `[[service]]` entries of the config, shown by the setup page and the task manager
+use super::config::ServiceConfig;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ServiceState {
+    /// Not started yet, either because it is not autostarted or the desktop session has not started
+    Stopped,
+    Running,
+    Exited,
+}
+
+/// Status of a `[[service]]` entry
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ServiceStatus {
+    pub name: String,
+    pub port: u16,
+    pub public_port: Option<u16>,
+    pub state: ServiceState,
+    /// Whether something accepts connections on the service port
+    pub listening: bool,
+}
+
+/// State of the service `name`, among the `states` of those started so far
+fn state_of(states: &[(String, ServiceState)], name: &str) -> ServiceState {
+    states
+        .iter()
+        .find(|(it, _)| it == name)
+        .map_or(ServiceState::Stopped, |(_, state)| *state)
+}
+
+/// Status of every configured service, `is_listening` tells whether a port accepts connections
+pub fn service_statuses(
+    services: Vec<ServiceConfig>,
+    states: &[(String, ServiceState)],
+    is_listening: impl Fn(u16) -> bool,
+) -> Vec<ServiceStatus> {
+    services
+        .into_iter()
+        .map(|service| ServiceStatus {
+            state: state_of(states, &service.name),
+            listening: is_listening(service.port),
+            name: service.name,
+            port: service.port,
+            public_port: service.public_port,
+        })
+        .collect()
+}
+
+/// The service `name` asked to be started by the user, unless it is not configured or already running
+pub fn startable_service(
+    services: Vec<ServiceConfig>,
+    states: &[(String, ServiceState)],
+    name: &str,
+) -> Result<ServiceConfig, String> {
+    let service = services
+        .into_iter()
+        .find(|service| service.name == name)
+        .ok_or_else(|| format!("Unknown service {}", name))?;
+    if state_of(states, name) == ServiceState::Running {
+        return Err(format!("Service {} is already running", name));
+    }
+    Ok(service)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn service(name: &str, port: u16) -> ServiceConfig {
+        ServiceConfig {
+            name: name.to_string(),
+            command: format!("serve {}", port),
+            port,
+            autostart: false,
+            public_port: None,
+        }
+    }
+
+    #[test]
+    fn should_report_service_states() {
+        let services = vec![
+            service("web", 8000),
+            service("db", 5432),
+            service("ssh", 22),
+        ];
+        let states = [
+            ("web".to_string(), ServiceState::Running),
+            ("db".to_string(), ServiceState::Exited),
+        ];
+        let statuses = service_statuses(services, &states, |port| port == 8000);
+        let summary = statuses
+            .iter()
+            .map(|it| (it.name.as_str(), it.state, it.listening))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            [
+                ("web", ServiceState::Running, true),
+                ("db", ServiceState::Exited, false),
+                ("ssh", ServiceState::Stopped, false),
+            ]
+        );
+        assert_eq!(
+            serde_json::to_value(&statuses[2]).unwrap()["state"],
+            "stopped"
+        );
+    }
+
+    #[test]
+    fn should_only_start_services_not_running() {
+        let services = || vec![service("web", 8000), service("db", 5432)];
+        let states = [
+            ("web".to_string(), ServiceState::Running),
+            ("db".to_string(), ServiceState::Exited),
+        ];
+        assert!(startable_service(services(), &states, "web").is_err());
+        assert!(startable_service(services(), &states, "cache").is_err());
+        assert_eq!(
+            startable_service(services(), &states, "db").unwrap().port,
+            5432
+        );
+        assert_eq!(
+            startable_service(services(), &[], "web").unwrap().port,
+            8000
+        );
+    }
+}
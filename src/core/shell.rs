@@ -0,0 +1,21 @@
+//! Commands run inside the container go through `sh -c`, values from the config must not be able to
+//! break out of them.
+
+/// `value` as a single shell word, e.g. `it's` becomes `'it'\''s'`
+pub fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_quote_special_characters() {
+        assert_eq!(quote("alice"), "'alice'");
+        assert_eq!(quote("/home/a b"), "'/home/a b'");
+        assert_eq!(quote("$(reboot); `id`"), "'$(reboot); `id`'");
+        assert_eq!(quote("it's"), r"'it'\''s'");
+        assert_eq!(quote(""), "''");
+    }
+}
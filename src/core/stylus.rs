@@ -0,0 +1,60 @@
+/// The tilt of a stylus along the X and Y axes in degrees, as tablet-v2 tells it, from the angle
+/// between the stylus and the perpendicular of the screen and the direction it points to, as Android
+/// tells them in radians. A positive X tilt leans the stylus to the right, a positive Y tilt towards
+/// the user.
+pub fn tilt_degrees(tilt: f64, orientation: f64) -> (f64, f64) {
+    let (sin_tilt, cos_tilt) = tilt.sin_cos();
+    let x = (-orientation).sin() * sin_tilt;
+    let y = (-orientation).cos() * sin_tilt;
+    (
+        x.atan2(cos_tilt).to_degrees(),
+        y.atan2(cos_tilt).to_degrees(),
+    )
+}
+
+/// How far a hovering stylus is from the screen, from 0 when touching it to 1 as far as tablet-v2
+/// goes. Android does not tell a unit, most styluses report up to 1 or just 0 and 1.
+pub fn hover_distance(distance: f64) -> f64 {
+    if distance.is_finite() {
+        distance.clamp(0.0, 1.0)
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::{FRAC_PI_2, FRAC_PI_3, FRAC_PI_4};
+
+    fn assert_tilt(actual: (f64, f64), expected: (f64, f64)) {
+        assert!(
+            (actual.0 - expected.0).abs() < 1e-9 && (actual.1 - expected.1).abs() < 1e-9,
+            "{:?} != {:?}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn should_convert_stylus_tilt() {
+        // Upright, wherever it points to
+        assert_tilt(tilt_degrees(0.0, 0.0), (0.0, 0.0));
+        assert_tilt(tilt_degrees(0.0, FRAC_PI_2), (0.0, 0.0));
+        // Pointing to the top of the screen, leaning towards the user
+        assert_tilt(tilt_degrees(FRAC_PI_4, 0.0), (0.0, 45.0));
+        // Pointing to the right, leaning to the left
+        assert_tilt(tilt_degrees(FRAC_PI_4, FRAC_PI_2), (-45.0, 0.0));
+        // Pointing to the left, leaning to the right
+        assert_tilt(tilt_degrees(FRAC_PI_3, -FRAC_PI_2), (60.0, 0.0));
+    }
+
+    #[test]
+    fn should_clamp_hover_distance() {
+        assert_eq!(hover_distance(0.0), 0.0);
+        assert_eq!(hover_distance(0.25), 0.25);
+        assert_eq!(hover_distance(3.0), 1.0);
+        assert_eq!(hover_distance(-1.0), 0.0);
+        assert_eq!(hover_distance(f64::NAN), 0.0);
+    }
+}
@@ -0,0 +1,64 @@
+/// How many bytes of UTF-8 `text` to delete before and after the selection from `cursor` to `anchor`
+/// (byte offsets in `text`, equal without a selection), for `before` and `after` UTF-16 code units as
+/// Android counts them. Wayland counts in bytes. A count ending within a character deletes all of it,
+/// and no more than `text` is deleted. `None` when the offsets are not character boundaries of `text`.
+pub fn utf8_delete_lengths(
+    text: &str,
+    cursor: u32,
+    anchor: u32,
+    before: u32,
+    after: u32,
+) -> Option<(u32, u32)> {
+    let (start, end) = (cursor.min(anchor) as usize, cursor.max(anchor) as usize);
+    let before = utf8_length(text.get(..start)?.chars().rev(), before);
+    let after = utf8_length(text.get(end..)?.chars(), after);
+    Some((before, after))
+}
+
+/// Bytes of the first `chars` that make up `utf16_length` code units
+fn utf8_length(chars: impl Iterator<Item = char>, utf16_length: u32) -> u32 {
+    let mut units = 0;
+    let mut bytes = 0;
+    for char in chars {
+        if units >= utf16_length {
+            break;
+        }
+        units += char.len_utf16() as u32;
+        bytes += char.len_utf8() as u32;
+    }
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_count_deleted_characters_in_bytes() {
+        // One UTF-16 code unit each, 1 and 3 bytes
+        assert_eq!(utf8_delete_lengths("ab", 2, 2, 1, 0), Some((1, 0)));
+        assert_eq!(utf8_delete_lengths("漢字", 6, 6, 1, 0), Some((3, 0)));
+        assert_eq!(utf8_delete_lengths("漢字", 0, 0, 0, 2), Some((0, 6)));
+        // A surrogate pair of 4 bytes, deleted whole even when only half of it is asked for
+        assert_eq!(utf8_delete_lengths("a😀", 5, 5, 2, 0), Some((4, 0)));
+        assert_eq!(utf8_delete_lengths("a😀", 5, 5, 1, 0), Some((4, 0)));
+        assert_eq!(utf8_delete_lengths("a😀", 5, 5, 3, 0), Some((5, 0)));
+    }
+
+    #[test]
+    fn should_delete_around_the_selection() {
+        // "字" is selected, the counts go from its ends
+        assert_eq!(utf8_delete_lengths("漢字a", 6, 3, 1, 1), Some((3, 1)));
+    }
+
+    #[test]
+    fn should_stop_at_the_ends_of_the_text() {
+        assert_eq!(utf8_delete_lengths("漢", 3, 3, 5, 5), Some((3, 0)));
+    }
+
+    #[test]
+    fn should_refuse_offsets_within_characters() {
+        assert_eq!(utf8_delete_lengths("漢", 1, 1, 1, 0), None);
+        assert_eq!(utf8_delete_lengths("a", 2, 2, 1, 0), None);
+    }
+}
@@ -0,0 +1,176 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const MAGIC: &[u8] = b"Xcur";
+const IMAGE_TYPE: u32 = 0xfffd_0002;
+const IMAGE_HEADER_SIZE: usize = 36;
+
+/// Themes looked up when the one asked for has no such cursor, like libXcursor does
+const FALLBACK_THEMES: &[&str] = &["default", "Adwaita", "breeze_cursors"];
+
+/// One frame of an Xcursor file
+#[derive(Debug, Clone, PartialEq)]
+pub struct CursorImage {
+    /// Nominal size the image was drawn for
+    pub size: u32,
+    pub width: u32,
+    pub height: u32,
+    pub xhot: u32,
+    pub yhot: u32,
+    /// Premultiplied ARGB, one little endian `u32` per pixel
+    pub pixels: Vec<u8>,
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset + 4)?;
+    Some(u32::from_le_bytes(bytes.try_into().ok()?))
+}
+
+/// Parse every image of an Xcursor file, `None` if it is not one or is truncated
+pub fn parse_xcursor(data: &[u8]) -> Option<Vec<CursorImage>> {
+    if data.get(..4)? != MAGIC {
+        return None;
+    }
+    let header_size = read_u32(data, 4)? as usize;
+    let toc_length = read_u32(data, 12)? as usize;
+
+    let mut images = Vec::new();
+    for entry in 0..toc_length {
+        let entry = header_size + entry * 12;
+        if read_u32(data, entry)? != IMAGE_TYPE {
+            continue;
+        }
+        let position = read_u32(data, entry + 8)? as usize;
+        if read_u32(data, position + 4)? != IMAGE_TYPE {
+            return None;
+        }
+
+        let width = read_u32(data, position + 16)?;
+        let height = read_u32(data, position + 20)?;
+        let start = position + IMAGE_HEADER_SIZE;
+        let length = (width as usize)
+            .checked_mul(height as usize)?
+            .checked_mul(4)?;
+        images.push(CursorImage {
+            size: read_u32(data, position + 8)?,
+            width,
+            height,
+            xhot: read_u32(data, position + 24)?,
+            yhot: read_u32(data, position + 28)?,
+            pixels: data.get(start..start.checked_add(length)?)?.to_vec(),
+        });
+    }
+    Some(images)
+}
+
+/// The first frame of the nominal size closest to `size`. Animations are not played.
+pub fn nearest_image(images: &[CursorImage], size: u32) -> Option<&CursorImage> {
+    let nearest = images.iter().map(|it| it.size.abs_diff(size)).min()?;
+    images.iter().find(|it| it.size.abs_diff(size) == nearest)
+}
+
+/// Themes `theme` inherits from, as listed in its `index.theme`
+fn inherited_themes(icons_dir: &Path, theme: &str) -> Vec<String> {
+    let index = fs::read_to_string(icons_dir.join(theme).join("index.theme")).unwrap_or_default();
+    index
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("Inherits"))
+        .filter_map(|line| line.trim_start().strip_prefix('='))
+        .flat_map(|themes| themes.split([',', ';']))
+        .map(|it| it.trim().to_string())
+        .filter(|it| !it.is_empty())
+        .collect()
+}
+
+/// Find the file of the first of `names` that `theme`, the themes it inherits from, or a common theme has
+pub fn find_cursor(icons_dir: &Path, theme: &str, names: &[&str]) -> Option<PathBuf> {
+    let mut pending: Vec<String> = FALLBACK_THEMES
+        .iter()
+        .rev()
+        .map(|it| it.to_string())
+        .collect();
+    pending.push(theme.to_string());
+    let mut visited = HashSet::new();
+
+    while let Some(theme) = pending.pop() {
+        if !visited.insert(theme.clone()) {
+            continue;
+        }
+        let cursors = icons_dir.join(&theme).join("cursors");
+        if let Some(path) = names
+            .iter()
+            .map(|name| cursors.join(name))
+            .find(|path| path.is_file())
+        {
+            return Some(path);
+        }
+        // Inherited themes are looked up before the fallbacks
+        pending.extend(inherited_themes(icons_dir, &theme).into_iter().rev());
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An Xcursor file with one `size`x`size` image per size
+    fn xcursor(sizes: &[u32]) -> Vec<u8> {
+        let mut data = MAGIC.to_vec();
+        for value in [16, 0x1_0000, sizes.len() as u32] {
+            data.extend(value.to_le_bytes());
+        }
+
+        let mut position = 16 + sizes.len() * 12;
+        for size in sizes {
+            for value in [IMAGE_TYPE, *size, position as u32] {
+                data.extend(value.to_le_bytes());
+            }
+            position += IMAGE_HEADER_SIZE + (size * size * 4) as usize;
+        }
+        for size in sizes {
+            for value in [36, IMAGE_TYPE, *size, 1, *size, *size, 1, 2, 0] {
+                data.extend(value.to_le_bytes());
+            }
+            data.extend(vec![0xff; (size * size * 4) as usize]);
+        }
+        data
+    }
+
+    #[test]
+    fn should_parse_xcursor_images() {
+        let images = parse_xcursor(&xcursor(&[24, 48])).unwrap();
+        assert_eq!(images.len(), 2);
+        assert_eq!(images[1].size, 48);
+        assert_eq!((images[1].xhot, images[1].yhot), (1, 2));
+        assert_eq!(images[1].pixels.len(), 48 * 48 * 4);
+
+        assert_eq!(nearest_image(&images, 32).unwrap().size, 24);
+        assert_eq!(nearest_image(&images, 40).unwrap().size, 48);
+
+        let truncated = xcursor(&[24]);
+        assert_eq!(parse_xcursor(&truncated[..truncated.len() - 1]), None);
+        assert_eq!(parse_xcursor(b"not a cursor"), None);
+    }
+
+    #[test]
+    fn should_find_inherited_cursor() {
+        let icons = tempfile::tempdir().unwrap();
+        let path = icons.path();
+        fs::create_dir_all(path.join("default")).unwrap();
+        fs::write(
+            path.join("default/index.theme"),
+            "[Icon Theme]\nInherits=Base\n",
+        )
+        .unwrap();
+        fs::create_dir_all(path.join("Base/cursors")).unwrap();
+        fs::write(path.join("Base/cursors/left_ptr"), xcursor(&[24])).unwrap();
+
+        assert_eq!(
+            find_cursor(path, "missing", &["default", "left_ptr"]),
+            Some(path.join("Base/cursors/left_ptr"))
+        );
+        assert_eq!(find_cursor(path, "default", &["text"]), None);
+    }
+}
@@ -1,7 +1,22 @@
 pub mod core {
+    pub mod clipboard;
     pub mod config;
+    pub mod decoration;
+    pub mod forward;
+    pub mod frame_scheduler;
+    pub mod media;
+    pub mod output_layout;
+    pub mod procfs;
+    pub mod screencopy;
+    pub mod services;
+    pub mod shell;
+    pub mod stylus;
+    pub mod text_input;
+    pub mod xcursor;
 }
 
+pub mod compositor;
+
 #[cfg(target_os = "android")]
 pub mod android {
 
@@ -17,12 +32,21 @@ pub mod android {
     pub mod proot {
         pub mod launch;
         pub mod process;
+        pub mod services;
         pub mod setup;
+        pub mod storage;
+        pub mod task_manager;
     }
     pub mod utils {
         pub mod application_context;
+        pub mod clipboard;
+        pub mod displays;
+        pub mod foreground_service;
         pub mod fullscreen_immersive;
+        pub mod keyboard_capture;
         pub mod ndk;
+        pub mod pointer_capture;
+        pub mod soft_keyboard;
         pub mod webview;
     }
 }